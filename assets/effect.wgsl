@@ -0,0 +1,300 @@
+// Composites the offscreen target of a layer, applying its effects and then
+// blending it with its backdrop
+#import bevy_sprite::mesh2d_vertex_output MeshVertexOutput
+
+struct Effect {
+    ty: u32,
+    colors: array<vec4<f32>, 3>,
+    values: vec4<f32>,
+    offset: vec2<f32>,
+};
+
+struct EffectInfo {
+    effects: array<Effect, 4>,
+    effect_count: u32,
+};
+
+@group(1) @binding(0)
+var texture: texture_2d<f32>;
+
+@group(1) @binding(1)
+var texture_sampler: sampler;
+
+@group(1) @binding(2)
+var<uniform> info: EffectInfo;
+
+@group(1) @binding(3)
+var backdrop: texture_2d<f32>;
+
+@group(1) @binding(4)
+var backdrop_sampler: sampler;
+
+// `bm` value of the layer, 0 for normal
+@group(1) @binding(5)
+var<uniform> blend_mode: u32;
+
+const FILL = 1u;
+const TINT = 2u;
+const TRITONE = 3u;
+const DROP_SHADOW = 4u;
+const GAUSSIAN_BLUR = 5u;
+
+const MULTIPLY = 1u;
+const SCREEN = 2u;
+const OVERLAY = 3u;
+const DARKEN = 4u;
+const LIGHTEN = 5u;
+const COLOR_DODGE = 6u;
+const COLOR_BURN = 7u;
+const HARD_LIGHT = 8u;
+const SOFT_LIGHT = 9u;
+const DIFFERENCE = 10u;
+const EXCLUSION = 11u;
+const HUE = 12u;
+const SATURATION = 13u;
+const COLOR = 14u;
+const LUMINOSITY = 15u;
+const ADD = 16u;
+const HARD_MIX = 17u;
+
+// Exact sRGB transfer functions, matching the encoding of the targets so that
+// colors are blended as they are stored
+
+fn linearTosRGB(color: vec3<f32>) -> vec3<f32> {
+    let c = max(color, vec3(0.0));
+    return select(1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3(0.0031308));
+}
+
+fn sRGBToLinear(color: vec3<f32>) -> vec3<f32> {
+    let c = max(color, vec3(0.0));
+    return select(pow((c + 0.055) / 1.055, vec3(2.4)), c / 12.92, c <= vec3(0.04045));
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3(0.3, 0.59, 0.11));
+}
+
+fn set_luminance(color: vec3<f32>, lum: f32) -> vec3<f32> {
+    let c = color + (lum - luminance(color));
+    let l = luminance(c);
+    let n = min(min(c.r, c.g), c.b);
+    let x = max(max(c.r, c.g), c.b);
+    var result = c;
+    if n < 0.0 {
+        result = l + (result - l) * l / (l - n);
+    }
+    if x > 1.0 {
+        result = l + (result - l) * (1.0 - l) / (x - l);
+    }
+    return result;
+}
+
+fn saturation(color: vec3<f32>) -> f32 {
+    return max(max(color.r, color.g), color.b) - min(min(color.r, color.g), color.b);
+}
+
+fn set_saturation(color: vec3<f32>, sat: f32) -> vec3<f32> {
+    let n = min(min(color.r, color.g), color.b);
+    let x = max(max(color.r, color.g), color.b);
+    if x <= n {
+        return vec3(0.0);
+    }
+    return (color - n) * sat / (x - n);
+}
+
+fn hard_light(b: vec3<f32>, s: vec3<f32>) -> vec3<f32> {
+    return select(1.0 - (1.0 - b) * (2.0 - 2.0 * s), b * 2.0 * s, s <= vec3(0.5));
+}
+
+fn soft_light(b: vec3<f32>, s: vec3<f32>) -> vec3<f32> {
+    let d = select(sqrt(b), ((16.0 * b - 12.0) * b + 4.0) * b, b <= vec3(0.25));
+    let light = b + (2.0 * s - 1.0) * (d - b);
+    let dark = b - (1.0 - 2.0 * s) * b * (1.0 - b);
+    return select(light, dark, s <= vec3(0.5));
+}
+
+fn color_dodge(b: vec3<f32>, s: vec3<f32>) -> vec3<f32> {
+    let dodged = select(min(vec3(1.0), b / max(1.0 - s, vec3(0.0001))), vec3(1.0), s >= vec3(1.0));
+    return select(dodged, vec3(0.0), b <= vec3(0.0));
+}
+
+fn color_burn(b: vec3<f32>, s: vec3<f32>) -> vec3<f32> {
+    let burnt = select(1.0 - min(vec3(1.0), (1.0 - b) / max(s, vec3(0.0001))), vec3(0.0), s <= vec3(0.0));
+    return select(burnt, vec3(1.0), b >= vec3(1.0));
+}
+
+// Blend of the unpremultiplied sRGB backdrop `b` and source `s` colors, as
+// defined by the W3C compositing spec
+fn blend_color(mode: u32, b: vec3<f32>, s: vec3<f32>) -> vec3<f32> {
+    if mode == MULTIPLY {
+        return b * s;
+    } else if mode == SCREEN {
+        return b + s - b * s;
+    } else if mode == OVERLAY {
+        return hard_light(s, b);
+    } else if mode == DARKEN {
+        return min(b, s);
+    } else if mode == LIGHTEN {
+        return max(b, s);
+    } else if mode == COLOR_DODGE {
+        return color_dodge(b, s);
+    } else if mode == COLOR_BURN {
+        return color_burn(b, s);
+    } else if mode == HARD_LIGHT {
+        return hard_light(b, s);
+    } else if mode == SOFT_LIGHT {
+        return soft_light(b, s);
+    } else if mode == DIFFERENCE {
+        return abs(b - s);
+    } else if mode == EXCLUSION {
+        return b + s - 2.0 * b * s;
+    } else if mode == HUE {
+        return set_luminance(set_saturation(s, saturation(b)), luminance(b));
+    } else if mode == SATURATION {
+        return set_luminance(set_saturation(b, saturation(s)), luminance(b));
+    } else if mode == COLOR {
+        return set_luminance(s, luminance(b));
+    } else if mode == LUMINOSITY {
+        return set_luminance(b, luminance(s));
+    } else if mode == ADD {
+        return min(vec3(1.0), b + s);
+    } else if mode == HARD_MIX {
+        return select(vec3(0.0), vec3(1.0), b + s >= vec3(1.0));
+    }
+    return s;
+}
+
+// Premultiplied color of the layer, transparent outside of the target
+fn layer_color(uv: vec2<f32>) -> vec4<f32> {
+    if any(uv < vec2(0.0)) || any(uv > vec2(1.0)) {
+        return vec4(0.0);
+    }
+    return textureSampleLevel(texture, texture_sampler, uv, 0.0);
+}
+
+// Premultiplied sRGB color of the layer passed through the color effects
+// before the one at `end`
+fn filtered(uv: vec2<f32>, end: u32) -> vec4<f32> {
+    let layer = layer_color(uv);
+    if layer.a <= 0.0 {
+        return vec4(0.0);
+    }
+    var color = vec4(linearTosRGB(layer.rgb / layer.a), layer.a);
+    for (var i = 0u; i < end; i++) {
+        color = apply_color(info.effects[i], color);
+    }
+    return vec4(color.rgb * color.a, color.a);
+}
+
+// Gaussian blur of the filtered layer with a standard deviation of `sigma`
+// pixels, taking at most 17 samples in each direction
+fn blurred(uv: vec2<f32>, sigma: vec2<f32>, end: u32) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(texture));
+    let radius = ceil(sigma * 3.0);
+    let stride = max(radius / 8.0, vec2(1.0));
+    let falloff = 1.0 / max(2.0 * sigma * sigma, vec2(0.0001));
+    var sum = vec4(0.0);
+    var total = 0.0;
+    for (var y = -radius.y; y <= radius.y; y += stride.y) {
+        for (var x = -radius.x; x <= radius.x; x += stride.x) {
+            let d = vec2(x, y);
+            let weight = exp(-dot(d * d, falloff));
+            sum += filtered(uv + d / size, end) * weight;
+            total += weight;
+        }
+    }
+    return sum / total;
+}
+
+fn unpremultiply(color: vec4<f32>) -> vec4<f32> {
+    if color.a <= 0.0 {
+        return vec4(0.0);
+    }
+    return vec4(color.rgb / color.a, color.a);
+}
+
+// Unpremultiplied `top` drawn over `bottom`
+fn over(top: vec4<f32>, bottom: vec4<f32>) -> vec4<f32> {
+    let alpha = top.a + bottom.a * (1.0 - top.a);
+    if alpha <= 0.0 {
+        return vec4(0.0);
+    }
+    return vec4((top.rgb * top.a + bottom.rgb * bottom.a * (1.0 - top.a)) / alpha, alpha);
+}
+
+// Apply a color effect to an unpremultiplied sRGB color
+fn apply_color(effect: Effect, color: vec4<f32>) -> vec4<f32> {
+    var result = color;
+    let lum = luminance(color.rgb);
+    if effect.ty == FILL {
+        result = vec4(effect.colors[0].rgb, color.a * effect.values.x);
+    } else if effect.ty == TINT {
+        let tinted = mix(effect.colors[0].rgb, effect.colors[1].rgb, lum);
+        result = vec4(mix(color.rgb, tinted, effect.values.x), color.a);
+    } else if effect.ty == TRITONE {
+        var toned = mix(effect.colors[2].rgb, effect.colors[1].rgb, lum * 2.0);
+        if lum >= 0.5 {
+            toned = mix(effect.colors[1].rgb, effect.colors[0].rgb, lum * 2.0 - 1.0);
+        }
+        result = vec4(mix(toned, color.rgb, effect.values.x), color.a);
+    }
+    return result;
+}
+
+@fragment
+fn fragment(mesh: MeshVertexOutput) -> @location(0) vec4<f32> {
+    // the quad is upside down in the y-down composition space
+    let uv = vec2(mesh.uv.x, 1.0 - mesh.uv.y);
+    let size = vec2<f32>(textureDimensions(texture));
+
+    // Effects apply in stack order, like `apply_effects` on the CPU, to the
+    // layer and to the shadows drawn below it so far. Blurs and shadows sample
+    // the layer through the color effects before them, with earlier blurs
+    // combined, so unlike on the CPU they neither blur nor cast earlier shadows
+    var sigma = vec2(0.0);
+    var visible = true;
+    var layer = unpremultiply(filtered(uv, 0u));
+    var shadows = vec4(0.0);
+    for (var i = 0u; i < info.effect_count; i++) {
+        let effect = info.effects[i];
+        if effect.ty == GAUSSIAN_BLUR {
+            sigma = sqrt(sigma * sigma + effect.values.xy * effect.values.xy);
+            if visible {
+                layer = unpremultiply(blurred(uv, sigma, i));
+            }
+        } else if effect.ty == DROP_SHADOW {
+            let shadow_sigma = sqrt(sigma * sigma + vec2(effect.values.y * effect.values.y));
+            let alpha = blurred(uv - effect.offset / size, shadow_sigma, i).a * effect.values.x;
+            let shadow = vec4(effect.colors[0].rgb, alpha);
+            if effect.values.z != 0.0 {
+                visible = false;
+                layer = vec4(0.0);
+                shadows = shadow;
+            } else {
+                shadows = over(shadows, shadow);
+            }
+        } else {
+            layer = apply_color(effect, layer);
+            shadows = apply_color(effect, shadows);
+        }
+    }
+    let color = over(layer, shadows);
+
+    if color.a <= 0.0 {
+        return vec4(0.0);
+    }
+    var source = sRGBToLinear(color.rgb);
+
+    // finally blend with what is drawn below, which this is drawn over with
+    // normal alpha blending
+    if blend_mode != 0u {
+        let below = textureSampleLevel(backdrop, backdrop_sampler, uv, 0.0);
+        if below.a > 0.0 {
+            let b = linearTosRGB(below.rgb / below.a);
+            let s = linearTosRGB(source);
+            let mixed = mix(s, clamp(blend_color(blend_mode, b, s), vec3(0.0), vec3(1.0)), below.a);
+            source = sRGBToLinear(mixed);
+        }
+    }
+    return vec4(source, color.a);
+}
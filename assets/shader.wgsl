@@ -10,12 +10,14 @@ struct GradientStop {
 struct GradientInfo {
     start_pos: vec2<f32>,
     end_pos: vec2<f32>,
+    focal_pos: vec2<f32>,
     use_gradient: u32,
-    stops: array<GradientStop, 2>
+    stop_count: u32,
+    stops: array<GradientStop, 32>
 };
 
 struct MaskInfo {
-    masks: array<vec4<u32>, 4>,
+    masks: array<vec4<u32>, 16>,
     mask_count: u32,
     mask_total_count: u32
 }
@@ -35,6 +37,12 @@ var<uniform> mask_info: MaskInfo;
 @group(1) @binding(4)
 var<uniform> gradient: GradientInfo;
 
+@group(1) @binding(5)
+var<uniform> stroke_gradient: GradientInfo;
+
+@group(1) @binding(6)
+var<uniform> mask_transform: mat4x4<f32>;
+
 @group(2) @binding(0)
 var<uniform> mesh: Mesh2d;
 
@@ -54,6 +62,12 @@ struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     // We pass the vertex color to the fragment shader in location 0
     @location(0) color: vec4<f32>,
+    // Position in the coordinates of the shape, which gradients are defined in
+    @location(1) local_position: vec2<f32>,
+    // Index of the material gradient coloring the vertex, 0 for solid colors
+    @location(2) gradient: f32,
+    // Position in the pixels of the mask texture
+    @location(3) mask_position: vec2<f32>,
 };
 
 
@@ -66,6 +80,9 @@ fn vertex(vertex: Vertex) -> VertexOutput {
     out.clip_position = mesh2d_position_local_to_clip(mesh.model, vec4<f32>(vertex.position.xy, 0.0, 1.0));
     // Unpack the `u32` from the vertex buffer into the `vec4<f32>` used by the fragment shader
     out.color = vec4<f32>((vec4<u32>(vertex.color) >> vec4<u32>(0u, 8u, 16u, 24u)) & vec4<u32>(255u)) / 255.0;
+    out.local_position = vertex.position;
+    out.gradient = vertex.uv.x;
+    out.mask_position = (mask_transform * mesh.model * vec4<f32>(vertex.position.xy, 0.0, 1.0)).xy;
     return out;
 }
 
@@ -81,49 +98,102 @@ fn sRGBToLinear(color: vec3<f32>) -> vec3<f32> {
     return pow(color, vec3(GAMMA));
 }
 
-// https://docs.rs/lyon_geom/latest/src/lyon_geom/line.rs.html#650-655
-fn point_projection(pt: vec2<f32>, start: vec2<f32>, end: vec2<f32>) -> vec2<f32> {
-    let v = end - start;
-    var a = -v.y;
-    var b = v.x;
-    var c = -(a * start.x + b * start.y);
-    let div = 1.0 / length(vec2(a, b));
-    a = a * div;
-    b = b * div;
-    c = c * div;
-    let x = b * (b * pt.x - a * pt.y) - a * c;
-    let y = a * (a * pt.y - b * pt.x) - b * c;
-    return vec2(x, y);
+// Position of `pos` along a linear gradient, or between the focal point and
+// the circle of a radial gradient
+fn gradient_offset(info: GradientInfo, pos: vec2<f32>) -> f32 {
+    if info.use_gradient == 2u {
+        // solve |q - t * d| = t * radius for the circle through `pos`
+        let radius = distance(info.start_pos, info.end_pos);
+        let q = pos - info.focal_pos;
+        let d = info.start_pos - info.focal_pos;
+        let a = min(dot(d, d) - radius * radius, -0.0001);
+        let qd = dot(q, d);
+        return (qd - sqrt(max(qd * qd - a * dot(q, q), 0.0))) / a;
+    }
+    let v = info.end_pos - info.start_pos;
+    return dot(pos - info.start_pos, v) / max(dot(v, v), 0.0001);
+}
+
+// Color of a gradient at `pos`, interpolated between its stops in sRGB like
+// the other renderers do
+fn gradient_color(info: GradientInfo, pos: vec2<f32>) -> vec4<f32> {
+    var stops = info.stops;
+    let t = clamp(gradient_offset(info, pos), 0.0, 1.0);
+    var color = vec4(linearTosRGB(stops[0].color.rgb), stops[0].color.a);
+    for (var i: u32 = 1u; i < info.stop_count; i++) {
+        let prev = stops[i - 1u];
+        let next = stops[i];
+        if t > prev.offset {
+            let span = next.offset - prev.offset;
+            var f = 1.0;
+            if span > 0.0 {
+                f = clamp((t - prev.offset) / span, 0.0, 1.0);
+            }
+            let prev_color = vec4(linearTosRGB(prev.color.rgb), prev.color.a);
+            let next_color = vec4(linearTosRGB(next.color.rgb), next.color.a);
+            color = mix(prev_color, next_color, f);
+        }
+    }
+    return vec4(sRGBToLinear(color.rgb), color.a);
+}
+
+// Luminance of a color blended over transparent black in the mask texture, so
+// transparent areas count as black
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(linearTosRGB(color), vec3(0.2126, 0.7152, 0.0722));
+}
+
+// Alpha a set of layer masks starts from, see `MaskComposition::initial_alpha`
+fn mask_initial_alpha(mode: u32) -> f32 {
+    // Subtract, Intersect and Darken
+    if mode == 2u || mode == 3u || mode == 5u {
+        return 1.0;
+    }
+    return 0.0;
+}
+
+// Combine a layer mask with the previous ones, see `MaskComposition::combine`
+fn combine_mask(mode: u32, accumulated: f32, alpha: f32) -> f32 {
+    var result = accumulated;
+    switch mode {
+        case 1u: { result = accumulated + alpha - accumulated * alpha; }
+        case 2u: { result = accumulated * (1.0 - alpha); }
+        case 3u: { result = accumulated * alpha; }
+        case 4u: { result = max(accumulated, alpha); }
+        case 5u: { result = min(accumulated, alpha); }
+        case 6u: { result = abs(accumulated - alpha); }
+        default: {}
+    }
+    return result;
 }
 
 // The input of the fragment shader must correspond to the output of the vertex shader for all `location`s
 struct FragmentInput {
     // The color is interpolated between vertices by default
     @location(0) color: vec4<f32>,
+    @location(1) local_position: vec2<f32>,
+    @location(2) gradient: f32,
+    @location(3) mask_position: vec2<f32>,
 };
 
 /// Entry point for the fragment shader
 @fragment
 fn fragment(@builtin(position) position: vec4<f32>, in: FragmentInput) -> @location(0) vec4<f32> {
     var out: vec4<f32>;
-    let scale = scene_size.z;
-    let pos = position.xy / scale;
-    if gradient.use_gradient == 1u {
-        var start = (mesh.model * vec4<f32>(gradient.start_pos, 0.0, 1.0)).xy;
-        let end = (mesh.model * vec4<f32>(gradient.end_pos, 0.0, 1.0)).xy;
-        let proj = point_projection(pos.xy, start, end);
-        let inv = smoothstep(start, end, proj);
-        var t = inv.x;
-        if end.x == start.x {
-            t = inv.y;
-        }
-        let color = mix(gradient.stops[0].color.xyz, gradient.stops[1].color.xyz, t);
-        out = vec4(color, 1.0);
+    let pos = in.mask_position;
+    if in.gradient > 1.5 {
+        let color = gradient_color(stroke_gradient, in.local_position);
+        out = vec4(color.rgb, color.a * in.color.a);
+    } else if in.gradient > 0.5 {
+        let color = gradient_color(gradient, in.local_position);
+        out = vec4(color.rgb, color.a * in.color.a);
     } else {
         out = in.color;
     }
     let mask_size = vec2<f32>(textureDimensions(mask));
     let count = mask_info.mask_count;
+    // Alpha of the layer masks combined so far
+    var combined = 1.0;
     for (var i: u32 = 0u; i < count; i++) {
         let info = mask_info.masks[i];
         let mask_index = f32(info.x);
@@ -131,11 +201,39 @@ fn fragment(@builtin(position) position: vec4<f32>, in: FragmentInput) -> @locat
         let stride = vec2(mask_size.x / mask_count, 0.0);
         let sample_pos = (pos.xy + stride * mask_index) / mask_size;
         var mask_pixel = textureSample(mask, mask_sampler, sample_pos);
-        if info.y == 2u {
-            out.a *= (1.0 - mask_pixel.a);
+        let mode = info.z & 7u;
+        if mode != 0u {
+            // smooth out the bands of feathered edges
+            let feather = bitcast<f32>(info.w);
+            if feather > 0.0 {
+                var alpha = mask_pixel.a;
+                var offsets = array<vec2<f32>, 4>(
+                    vec2(-feather, -feather),
+                    vec2(feather, -feather),
+                    vec2(-feather, feather),
+                    vec2(feather, feather)
+                );
+                for (var j: u32 = 0u; j < 4u; j++) {
+                    let offset = offsets[j] / 2.0 / mask_size;
+                    alpha += textureSample(mask, mask_sampler, sample_pos + offset).a;
+                }
+                mask_pixel.a = alpha / 5.0;
+            }
+            if (info.z & 8u) != 0u {
+                out.a *= combined;
+                combined = mask_initial_alpha(mode);
+            }
+            combined = combine_mask(mode, combined, mask_pixel.a);
         } else if info.y == 1u {
             out.a *= mask_pixel.a;
+        } else if info.y == 2u {
+            out.a *= (1.0 - mask_pixel.a);
+        } else if info.y == 3u {
+            out.a *= luminance(mask_pixel.rgb);
+        } else if info.y == 4u {
+            out.a *= (1.0 - luminance(mask_pixel.rgb));
         }
     }
+    out.a *= combined;
     return out;
-}
\ No newline at end of file
+}
@@ -0,0 +1,194 @@
+#[cfg(feature = "expressions")]
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::model::{Animated, Bezier, GradientColor, Rgb, Rgba, Vector2D};
+#[cfg(feature = "expressions")]
+use crate::Lerp;
+
+#[cfg(feature = "expressions")]
+mod bind;
+#[cfg(feature = "expressions")]
+mod eval;
+#[cfg(feature = "expressions")]
+mod parser;
+
+#[cfg(feature = "expressions")]
+pub(crate) use bind::CompScope;
+
+/// An After Effects expression computing the value of an animated property,
+/// as exported in its `x` field.
+///
+/// With the `expressions` feature, expressions are parsed when loaded and
+/// evaluated by [Animated::value] once `Model::bind_expressions` tied them to
+/// their composition, falling back to the keyframes if anything goes wrong.
+/// Without it they are only kept to be written back
+#[derive(Debug, Clone)]
+pub struct Expression {
+    source: String,
+    #[cfg(feature = "expressions")]
+    program: Option<Arc<Vec<parser::Stmt>>>,
+    #[cfg(feature = "expressions")]
+    binding: Option<bind::Binding>,
+    /// Seed of `wiggle` and `random`, unless set with `seedRandom`
+    #[cfg(feature = "expressions")]
+    seed: u64,
+}
+
+impl Expression {
+    pub fn new(source: impl Into<String>) -> Self {
+        let source = source.into();
+        #[cfg(feature = "expressions")]
+        {
+            let program = match parser::parse(&source) {
+                Ok(program) => Some(Arc::new(program)),
+                Err(err) => {
+                    log::warn!("unsupported expression {:?}: {}", source, err);
+                    None
+                }
+            };
+            // FNV-1a
+            let seed = source.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+            Expression {
+                source,
+                program,
+                binding: None,
+                seed,
+            }
+        }
+        #[cfg(not(feature = "expressions"))]
+        Expression { source }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the expression can be evaluated, being parsed and bound to its
+    /// composition
+    #[cfg(feature = "expressions")]
+    pub fn is_bound(&self) -> bool {
+        self.program.is_some() && self.binding.is_some()
+    }
+
+    #[cfg(not(feature = "expressions"))]
+    pub fn is_bound(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "expressions")]
+    pub(crate) fn evaluate<T>(&self, animated: &Animated<T>, frame: f32) -> Option<T>
+    where
+        T: Clone + Lerp<Target = T> + ExpressionValue,
+    {
+        let program = self.program.as_ref()?;
+        let binding = self.binding.as_ref()?;
+        let numbers = eval::evaluate(program, animated, binding, self.seed, frame)?;
+        T::from_numbers(&numbers.into_iter().map(|n| n as f32).collect::<Vec<_>>())
+    }
+
+    #[cfg(not(feature = "expressions"))]
+    pub(crate) fn evaluate<T>(&self, _: &Animated<T>, _: f32) -> Option<T> {
+        None
+    }
+}
+
+impl Serialize for Expression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Expression::new(String::deserialize(deserializer)?))
+    }
+}
+
+/// Values expressions can compute, which are arrays of numbers to them.
+/// Colors have 4 components from 0 to 1
+pub trait ExpressionValue: Sized {
+    fn to_numbers(&self) -> Option<Vec<f32>> {
+        None
+    }
+
+    fn from_numbers(_numbers: &[f32]) -> Option<Self> {
+        None
+    }
+}
+
+impl ExpressionValue for f32 {
+    fn to_numbers(&self) -> Option<Vec<f32>> {
+        Some(vec![*self])
+    }
+
+    fn from_numbers(numbers: &[f32]) -> Option<Self> {
+        numbers.first().cloned()
+    }
+}
+
+impl ExpressionValue for Vector2D {
+    fn to_numbers(&self) -> Option<Vec<f32>> {
+        Some(vec![self.x, self.y])
+    }
+
+    fn from_numbers(numbers: &[f32]) -> Option<Self> {
+        match numbers {
+            [x, y, ..] => Some(Vector2D::new(*x, *y)),
+            _ => None,
+        }
+    }
+}
+
+impl ExpressionValue for Rgb {
+    fn to_numbers(&self) -> Option<Vec<f32>> {
+        Some(vec![
+            self.r as f32 / 255.0,
+            self.g as f32 / 255.0,
+            self.b as f32 / 255.0,
+            1.0,
+        ])
+    }
+
+    fn from_numbers(numbers: &[f32]) -> Option<Self> {
+        match numbers {
+            [r, g, b, ..] => Some(Rgb::new_f32(
+                r.clamp(0.0, 1.0),
+                g.clamp(0.0, 1.0),
+                b.clamp(0.0, 1.0),
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl ExpressionValue for Rgba {
+    fn to_numbers(&self) -> Option<Vec<f32>> {
+        Some(vec![
+            self.r as f32 / 255.0,
+            self.g as f32 / 255.0,
+            self.b as f32 / 255.0,
+            self.a as f32 / 255.0,
+        ])
+    }
+
+    fn from_numbers(numbers: &[f32]) -> Option<Self> {
+        match numbers {
+            [r, g, b, rest @ ..] => Some(Rgba::new_f32(
+                r.clamp(0.0, 1.0),
+                g.clamp(0.0, 1.0),
+                b.clamp(0.0, 1.0),
+                rest.first().cloned().unwrap_or(1.0).clamp(0.0, 1.0),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Paths and gradient colors are left to their keyframes
+impl ExpressionValue for Vec<Bezier> {}
+
+impl ExpressionValue for Vec<GradientColor> {}
@@ -0,0 +1,320 @@
+use std::sync::{Arc, Weak};
+
+use crate::model::*;
+
+/// A composition as seen by the expressions of its layers through `thisComp`
+#[derive(Debug)]
+pub struct CompScope {
+    pub width: f32,
+    pub height: f32,
+    pub frame_rate: f32,
+    pub start_frame: f32,
+    pub end_frame: f32,
+    pub layers: Vec<LayerScope>,
+}
+
+/// The properties of a layer expressions can refer to
+#[derive(Debug)]
+pub struct LayerScope {
+    pub name: Option<String>,
+    pub index: Option<u32>,
+    pub start_frame: f32,
+    pub end_frame: f32,
+    pub start_time: f32,
+    pub transform: Transform,
+    pub effects: Vec<Effect>,
+}
+
+/// Where an expression is evaluated: the layer at `layer` in `comp`. The
+/// composition is owned by the [Model], so that the layers it clones do not
+/// keep it alive
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub comp: Weak<CompScope>,
+    pub layer: usize,
+}
+
+impl Model {
+    /// Bind the expressions of every layer to the composition they are in,
+    /// which they need to be evaluated
+    pub fn bind_expressions(&mut self) {
+        let mut scopes = vec![bind_layers(
+            &mut self.layers,
+            self.width as f32,
+            self.height as f32,
+            self.frame_rate,
+            self.start_frame,
+            self.end_frame,
+        )];
+        for asset in &mut self.assets {
+            if let Asset::Precomposition(precomp) = asset {
+                scopes.push(bind_layers(
+                    &mut precomp.layers,
+                    self.width as f32,
+                    self.height as f32,
+                    precomp.frame_rate.unwrap_or(self.frame_rate),
+                    self.start_frame,
+                    self.end_frame,
+                ));
+            }
+        }
+        self.expression_scopes = scopes;
+    }
+}
+
+fn bind_layers(
+    layers: &mut [Layer],
+    width: f32,
+    height: f32,
+    frame_rate: f32,
+    start_frame: f32,
+    end_frame: f32,
+) -> Arc<CompScope> {
+    let comp = Arc::new_cyclic(|comp: &Weak<CompScope>| {
+        let layers = layers
+            .iter()
+            .enumerate()
+            .map(|(index, layer)| {
+                let binding = Binding {
+                    comp: comp.clone(),
+                    layer: index,
+                };
+                let mut transform = layer.transform.clone().unwrap_or_default();
+                transform.bind(&binding);
+                let mut effects = layer.effects.clone();
+                effects.bind(&binding);
+                LayerScope {
+                    name: layer.name.clone(),
+                    index: layer.index,
+                    start_frame: layer.start_frame,
+                    end_frame: layer.end_frame,
+                    start_time: layer.start_time,
+                    transform,
+                    effects,
+                }
+            })
+            .collect();
+        CompScope {
+            width,
+            height,
+            frame_rate,
+            start_frame,
+            end_frame,
+            layers,
+        }
+    });
+    for (index, layer) in layers.iter_mut().enumerate() {
+        layer.bind(&Binding {
+            comp: Arc::downgrade(&comp),
+            layer: index,
+        });
+    }
+    comp
+}
+
+/// Attach a [Binding] to the expressions of every animated property
+trait Bind {
+    fn bind(&mut self, binding: &Binding);
+}
+
+impl<T> Bind for Animated<T> {
+    fn bind(&mut self, binding: &Binding) {
+        if let Some(expression) = self.expression.as_mut() {
+            expression.binding = Some(binding.clone());
+        }
+    }
+}
+
+impl<T: Bind> Bind for Option<T> {
+    fn bind(&mut self, binding: &Binding) {
+        if let Some(value) = self.as_mut() {
+            value.bind(binding);
+        }
+    }
+}
+
+impl<T: Bind> Bind for Vec<T> {
+    fn bind(&mut self, binding: &Binding) {
+        for value in self.iter_mut() {
+            value.bind(binding);
+        }
+    }
+}
+
+impl Bind for Layer {
+    fn bind(&mut self, binding: &Binding) {
+        self.transform.bind(binding);
+        self.masks_properties.bind(binding);
+        self.effects.bind(binding);
+        match &mut self.content {
+            LayerContent::Shape(group) => group.shapes.bind(binding),
+            LayerContent::PreCompositionRef(precomp) => precomp.time_remapping.bind(binding),
+            _ => {}
+        }
+    }
+}
+
+impl Bind for Transform {
+    fn bind(&mut self, binding: &Binding) {
+        self.anchor.bind(binding);
+        self.position.bind(binding);
+        self.scale.bind(binding);
+        self.rotation.bind(binding);
+        self.opacity.bind(binding);
+        self.skew.bind(binding);
+        self.skew_axis.bind(binding);
+    }
+}
+
+impl Bind for Position {
+    fn bind(&mut self, binding: &Binding) {
+        match self {
+            Position::Split(p) => {
+                p.x.bind(binding);
+                p.y.bind(binding);
+            }
+            Position::Combined(p) => p.bind(binding),
+        }
+    }
+}
+
+impl Bind for RepeaterTransform {
+    fn bind(&mut self, binding: &Binding) {
+        self.anchor.bind(binding);
+        self.position.bind(binding);
+        self.scale.bind(binding);
+        self.rotation.bind(binding);
+        self.start_opacity.bind(binding);
+        self.end_opacity.bind(binding);
+        self.skew.bind(binding);
+        self.skew_axis.bind(binding);
+    }
+}
+
+impl Bind for ShapeLayer {
+    fn bind(&mut self, binding: &Binding) {
+        match &mut self.shape {
+            Shape::Rectangle(rect) => {
+                rect.position.bind(binding);
+                rect.size.bind(binding);
+                rect.radius.bind(binding);
+            }
+            Shape::Ellipse(ellipse) => {
+                ellipse.position.bind(binding);
+                ellipse.size.bind(binding);
+            }
+            Shape::PolyStar(star) => {
+                star.position.bind(binding);
+                star.outer_radius.bind(binding);
+                star.outer_roundness.bind(binding);
+                star.inner_radius.bind(binding);
+                star.inner_roundness.bind(binding);
+                star.rotation.bind(binding);
+                star.points.bind(binding);
+            }
+            Shape::Path { d, .. } => d.bind(binding),
+            Shape::Fill(fill) => {
+                fill.opacity.bind(binding);
+                fill.color.bind(binding);
+            }
+            Shape::Stroke(stroke) => {
+                stroke.opacity.bind(binding);
+                stroke.width.bind(binding);
+                stroke.color.bind(binding);
+                stroke.dashes.bind(binding);
+            }
+            Shape::GradientFill(fill) => {
+                fill.opacity.bind(binding);
+                fill.gradient.bind(binding);
+            }
+            Shape::GradientStroke(stroke) => {
+                stroke.opacity.bind(binding);
+                stroke.width.bind(binding);
+                stroke.dashes.bind(binding);
+                stroke.gradient.bind(binding);
+            }
+            Shape::Group { shapes } | Shape::Merge { shapes, .. } => shapes.bind(binding),
+            Shape::Transform(transform) => transform.bind(binding),
+            Shape::Repeater {
+                copies,
+                offset,
+                transform,
+                ..
+            } => {
+                copies.bind(binding);
+                offset.bind(binding);
+                transform.bind(binding);
+            }
+            Shape::Trim(trim) => {
+                trim.start.bind(binding);
+                trim.end.bind(binding);
+                trim.offset.bind(binding);
+            }
+            Shape::RoundedCorners { radius } => radius.bind(binding),
+            Shape::PuckerBloat { amount } => amount.bind(binding),
+            Shape::Twist { angle, center } => {
+                angle.bind(binding);
+                center.bind(binding);
+            }
+            Shape::OffsetPath {
+                amount,
+                miter_limit,
+                ..
+            } => {
+                amount.bind(binding);
+                miter_limit.bind(binding);
+            }
+            Shape::ZigZag {
+                frequency,
+                amplitude,
+                point_type,
+            } => {
+                frequency.bind(binding);
+                amplitude.bind(binding);
+                point_type.bind(binding);
+            }
+        }
+    }
+}
+
+impl Bind for StrokeDash {
+    fn bind(&mut self, binding: &Binding) {
+        self.length.bind(binding);
+    }
+}
+
+impl Bind for Gradient {
+    fn bind(&mut self, binding: &Binding) {
+        self.start.bind(binding);
+        self.end.bind(binding);
+        self.colors.colors.bind(binding);
+        self.highlight_length.bind(binding);
+        self.highlight_angle.bind(binding);
+    }
+}
+
+impl Bind for Mask {
+    fn bind(&mut self, binding: &Binding) {
+        self.points.bind(binding);
+        self.opacity.bind(binding);
+        self.expand.bind(binding);
+        self.feather.bind(binding);
+    }
+}
+
+impl Bind for Effect {
+    fn bind(&mut self, binding: &Binding) {
+        for value in &mut self.values {
+            match &mut value.value {
+                EffectValueType::Slider(v)
+                | EffectValueType::Angle(v)
+                | EffectValueType::Checkbox(v)
+                | EffectValueType::DropDown(v)
+                | EffectValueType::Layer(v) => v.bind(binding),
+                EffectValueType::Color(v) => v.bind(binding),
+                EffectValueType::Point(v) => v.bind(binding),
+                EffectValueType::Other { .. } => {}
+            }
+        }
+    }
+}
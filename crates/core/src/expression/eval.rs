@@ -0,0 +1,1077 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::model::{Animated, EffectValueType, Transform};
+use crate::Lerp;
+
+use super::bind::{Binding, CompScope};
+use super::parser::{BinaryOp, Expr, Stmt, UnaryOp};
+use super::ExpressionValue;
+
+/// How deep expressions may go evaluating properties of other layers, which
+/// may have expressions referring back
+const MAX_DEPTH: u32 = 8;
+
+thread_local! {
+    static DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Keyframed values of the property an expression belongs to
+pub trait Source {
+    fn value_at(&self, frame: f32) -> Vec<f64>;
+    /// Frames of the keyframes, empty if the property is not animated
+    fn key_frames(&self) -> Vec<f32>;
+}
+
+impl<T: Clone + Lerp<Target = T> + ExpressionValue> Source for Animated<T> {
+    fn value_at(&self, frame: f32) -> Vec<f64> {
+        numbers(&self.keyframe_value(frame))
+    }
+
+    fn key_frames(&self) -> Vec<f32> {
+        if !self.is_keyframed() {
+            return vec![];
+        }
+        let mut frames = self.keyframes().collect::<Vec<_>>();
+        frames.dedup();
+        frames
+    }
+}
+
+fn numbers<T: ExpressionValue>(value: &T) -> Vec<f64> {
+    value
+        .to_numbers()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|n| n as f64)
+        .collect()
+}
+
+/// Run `program` for the property `source` at `frame`, returning the numbers
+/// of the result. Errors are logged and give `None`
+pub fn evaluate(
+    program: &[Stmt],
+    source: &dyn Source,
+    binding: &Binding,
+    seed: u64,
+    frame: f32,
+) -> Option<Vec<f64>> {
+    let comp = binding.comp.upgrade()?;
+    let depth = DEPTH.with(|depth| depth.get());
+    if depth >= MAX_DEPTH {
+        log::debug!("expressions are nested too deep");
+        return None;
+    }
+    DEPTH.with(|d| d.set(depth + 1));
+    let mut evaluator = Evaluator {
+        source,
+        comp,
+        layer: binding.layer,
+        frame,
+        vars: HashMap::new(),
+        seed: hash(seed ^ binding.layer as u64),
+        timeless: false,
+        random_count: 0,
+    };
+    let result = evaluator.run_program(program);
+    DEPTH.with(|d| d.set(depth));
+    match result {
+        Ok(numbers) => Some(numbers),
+        Err(err) => {
+            log::debug!("failed to evaluate expression: {}", err);
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Array(Vec<f64>),
+    Str(String),
+    Undefined,
+    Comp,
+    Layer(usize),
+    Transform(usize),
+    /// Effect of a layer, by position
+    Effect(usize, usize),
+    Property(Property),
+    Math,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Property {
+    /// The property the expression belongs to
+    This,
+    Transform(usize, TransformProperty),
+    /// Parameter of an effect of a layer, by position
+    Effect(usize, usize, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TransformProperty {
+    Anchor,
+    Position,
+    Scale,
+    Rotation,
+    Opacity,
+    Skew,
+    SkewAxis,
+}
+
+impl TransformProperty {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "anchorPoint" => TransformProperty::Anchor,
+            "position" => TransformProperty::Position,
+            "scale" => TransformProperty::Scale,
+            "rotation" | "zRotation" => TransformProperty::Rotation,
+            "opacity" => TransformProperty::Opacity,
+            "skew" => TransformProperty::Skew,
+            "skewAxis" => TransformProperty::SkewAxis,
+            _ => return None,
+        })
+    }
+
+    fn value(self, transform: &Transform, frame: f32) -> Vec<f64> {
+        match self {
+            TransformProperty::Anchor => transform
+                .anchor
+                .as_ref()
+                .map_or(vec![0.0, 0.0], |a| numbers(&a.value(frame))),
+            TransformProperty::Position => transform
+                .position
+                .as_ref()
+                .map_or(vec![0.0, 0.0], |p| numbers(&p.value(frame))),
+            TransformProperty::Scale => numbers(&transform.scale.value(frame)),
+            TransformProperty::Rotation => numbers(&transform.rotation.value(frame)),
+            TransformProperty::Opacity => numbers(&transform.opacity.value(frame)),
+            TransformProperty::Skew => transform
+                .skew
+                .as_ref()
+                .map_or(vec![0.0], |s| numbers(&s.value(frame))),
+            TransformProperty::SkewAxis => transform
+                .skew_axis
+                .as_ref()
+                .map_or(vec![0.0], |s| numbers(&s.value(frame))),
+        }
+    }
+}
+
+fn from_numbers(numbers: Vec<f64>) -> Value {
+    if numbers.len() == 1 {
+        Value::Number(numbers[0])
+    } else {
+        Value::Array(numbers)
+    }
+}
+
+struct Evaluator<'a> {
+    source: &'a dyn Source,
+    comp: Arc<CompScope>,
+    layer: usize,
+    frame: f32,
+    vars: HashMap<String, Value>,
+    seed: u64,
+    /// Whether `random` gives the same numbers at every frame
+    timeless: bool,
+    random_count: u64,
+}
+
+type Result<T> = std::result::Result<T, String>;
+
+impl<'a> Evaluator<'a> {
+    /// The result is what is assigned to `$bm_rt` as exported by bodymovin,
+    /// or the value of the last statement
+    fn run_program(&mut self, program: &[Stmt]) -> Result<Vec<f64>> {
+        let last = self.run(program)?;
+        let result = match self.vars.remove("$bm_rt") {
+            Some(Value::Undefined) | None => last,
+            Some(value) => value,
+        };
+        match self.resolve(result)? {
+            Value::Number(n) => Ok(vec![n]),
+            Value::Array(a) => Ok(a),
+            value => Err(format!("{:?} is not a property value", value)),
+        }
+    }
+
+    fn run(&mut self, statements: &[Stmt]) -> Result<Value> {
+        let mut last = Value::Undefined;
+        for statement in statements {
+            match statement {
+                Stmt::Expr(expr) => last = self.eval(expr)?,
+                Stmt::Var(name, value) => {
+                    let value = match value {
+                        Some(value) => self.eval(value)?,
+                        None => Value::Undefined,
+                    };
+                    self.vars.insert(name.clone(), value);
+                }
+                Stmt::If(condition, then, otherwise) => {
+                    let condition = self.eval(condition)?;
+                    last = if self.truthy(condition)? {
+                        self.run(then)?
+                    } else {
+                        self.run(otherwise)?
+                    };
+                }
+                Stmt::Block(statements) => last = self.run(statements)?,
+            }
+        }
+        Ok(last)
+    }
+
+    fn time(&self) -> f64 {
+        self.frame as f64 / self.comp.frame_rate as f64
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value> {
+        Ok(match expr {
+            Expr::Number(n) => Value::Number(*n),
+            Expr::Str(s) => Value::Str(s.clone()),
+            Expr::Array(items) => {
+                let mut numbers = vec![];
+                for item in items {
+                    let item = self.eval(item)?;
+                    numbers.push(self.number(item)?);
+                }
+                Value::Array(numbers)
+            }
+            Expr::Ident(name) => self.ident(name)?,
+            Expr::Member(object, name) => {
+                let object = self.eval(object)?;
+                self.member(object, name)?
+            }
+            Expr::Index(object, index) => {
+                let object = self.eval(object)?;
+                let index = self.eval(index)?;
+                let index = self.number(index)?;
+                match self.resolve(object)? {
+                    Value::Array(a) => a
+                        .get(index as usize)
+                        .map_or(Value::Undefined, |n| Value::Number(*n)),
+                    Value::Number(n) if index == 0.0 => Value::Number(n),
+                    value => return Err(format!("cannot index {:?}", value)),
+                }
+            }
+            Expr::Call(callee, args) => {
+                let mut values = vec![];
+                for arg in args {
+                    values.push(self.eval(arg)?);
+                }
+                match callee.as_ref() {
+                    Expr::Member(object, name) => {
+                        let object = self.eval(object)?;
+                        self.method(object, name, values)?
+                    }
+                    Expr::Ident(name) if !self.vars.contains_key(name) => {
+                        self.function(name, values)?
+                    }
+                    callee => {
+                        let callee = self.eval(callee)?;
+                        self.call(callee, values)?
+                    }
+                }
+            }
+            Expr::Unary(op, value) => {
+                let value = self.eval(value)?;
+                match op {
+                    UnaryOp::Not => Value::Number(!self.truthy(value)? as u8 as f64),
+                    UnaryOp::Plus => from_numbers(self.numbers(value)?),
+                    UnaryOp::Neg => from_numbers(self.numbers(value)?.iter().map(|n| -n).collect()),
+                }
+            }
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                self.binary(*op, left, right)?
+            }
+            Expr::And(left, right) => {
+                let left = self.eval(left)?;
+                if self.truthy(left.clone())? {
+                    self.eval(right)?
+                } else {
+                    left
+                }
+            }
+            Expr::Or(left, right) => {
+                let left = self.eval(left)?;
+                if self.truthy(left.clone())? {
+                    left
+                } else {
+                    self.eval(right)?
+                }
+            }
+            Expr::Conditional(condition, then, otherwise) => {
+                let condition = self.eval(condition)?;
+                if self.truthy(condition)? {
+                    self.eval(then)?
+                } else {
+                    self.eval(otherwise)?
+                }
+            }
+            Expr::Assign(target, op, value) => {
+                let mut value = self.eval(value)?;
+                match target.as_ref() {
+                    Expr::Ident(name) => {
+                        if let Some(op) = op {
+                            let current = self.ident(name)?;
+                            value = self.binary(*op, current, value)?;
+                        }
+                        self.vars.insert(name.clone(), value.clone());
+                    }
+                    Expr::Index(object, index) => {
+                        let name = match object.as_ref() {
+                            Expr::Ident(name) => name,
+                            _ => return Err("invalid assignment target".to_string()),
+                        };
+                        let index = self.eval(index)?;
+                        let index = self.number(index)? as usize;
+                        let current = self.ident(name)?;
+                        let mut array = self.numbers(current)?;
+                        if let Some(op) = op {
+                            let current = Value::Number(array.get(index).cloned().unwrap_or(0.0));
+                            value = self.binary(*op, current, value)?;
+                        }
+                        if array.len() <= index {
+                            array.resize(index + 1, 0.0);
+                        }
+                        array[index] = self.number(value.clone())?;
+                        self.vars.insert(name.clone(), Value::Array(array));
+                    }
+                    _ => return Err("invalid assignment target".to_string()),
+                }
+                value
+            }
+        })
+    }
+
+    fn ident(&self, name: &str) -> Result<Value> {
+        if let Some(value) = self.vars.get(name) {
+            return Ok(value.clone());
+        }
+        let layer = &self.comp.layers[self.layer];
+        let frame_rate = self.comp.frame_rate as f64;
+        Ok(match name {
+            "time" => Value::Number(self.time()),
+            "value" => from_numbers(self.source.value_at(self.frame)),
+            "thisComp" => Value::Comp,
+            "thisLayer" => Value::Layer(self.layer),
+            "thisProperty" => Value::Property(Property::This),
+            "transform" => Value::Transform(self.layer),
+            "Math" => Value::Math,
+            "undefined" => Value::Undefined,
+            "index" => Value::Number(layer.index.map_or(self.layer as f64 + 1.0, |i| i as f64)),
+            "inPoint" => Value::Number(layer.start_frame as f64 / frame_rate),
+            "outPoint" => Value::Number(layer.end_frame as f64 / frame_rate),
+            "startTime" => Value::Number(layer.start_time as f64 / frame_rate),
+            name => match TransformProperty::from_name(name) {
+                Some(property) => Value::Property(Property::Transform(self.layer, property)),
+                None => return Err(format!("unknown variable {}", name)),
+            },
+        })
+    }
+
+    fn member(&mut self, object: Value, name: &str) -> Result<Value> {
+        let frame_rate = self.comp.frame_rate as f64;
+        Ok(match (object, name) {
+            (Value::Comp, "width") => Value::Number(self.comp.width as f64),
+            (Value::Comp, "height") => Value::Number(self.comp.height as f64),
+            (Value::Comp, "frameDuration") => Value::Number(1.0 / frame_rate),
+            (Value::Comp, "duration") => {
+                Value::Number((self.comp.end_frame - self.comp.start_frame) as f64 / frame_rate)
+            }
+            (Value::Comp, "numLayers") => Value::Number(self.comp.layers.len() as f64),
+            (Value::Layer(layer), "transform") => Value::Transform(layer),
+            (Value::Layer(layer), "name") => {
+                Value::Str(self.comp.layers[layer].name.clone().unwrap_or_default())
+            }
+            (Value::Layer(layer), "index") => Value::Number(
+                self.comp.layers[layer]
+                    .index
+                    .map_or(layer as f64 + 1.0, |i| i as f64),
+            ),
+            (Value::Layer(layer), "inPoint") => {
+                Value::Number(self.comp.layers[layer].start_frame as f64 / frame_rate)
+            }
+            (Value::Layer(layer), "outPoint") => {
+                Value::Number(self.comp.layers[layer].end_frame as f64 / frame_rate)
+            }
+            (Value::Layer(layer), "startTime") => {
+                Value::Number(self.comp.layers[layer].start_time as f64 / frame_rate)
+            }
+            (Value::Layer(layer) | Value::Transform(layer), name) => {
+                match TransformProperty::from_name(name) {
+                    Some(property) => Value::Property(Property::Transform(layer, property)),
+                    None => return Err(format!("unknown layer property {}", name)),
+                }
+            }
+            (Value::Property(property), "value") => {
+                from_numbers(self.property_value(property, self.frame)?)
+            }
+            (Value::Property(Property::This), "numKeys") => {
+                Value::Number(self.source.key_frames().len() as f64)
+            }
+            (Value::Math, "PI") => Value::Number(std::f64::consts::PI),
+            (Value::Math, "E") => Value::Number(std::f64::consts::E),
+            (Value::Math, "SQRT2") => Value::Number(std::f64::consts::SQRT_2),
+            (Value::Math, "LN2") => Value::Number(std::f64::consts::LN_2),
+            (Value::Math, "LN10") => Value::Number(std::f64::consts::LN_10),
+            (Value::Str(s), "length") => Value::Number(s.chars().count() as f64),
+            (object, "length") => Value::Number(self.numbers(object)?.len() as f64),
+            (object, name) => return Err(format!("unknown member {} of {:?}", name, object)),
+        })
+    }
+
+    fn method(&mut self, object: Value, name: &str, args: Vec<Value>) -> Result<Value> {
+        match (object, name) {
+            (Value::Comp, "layer") => {
+                let comp = self.comp.clone();
+                let layers = &comp.layers;
+                let layer = match args.into_iter().next() {
+                    Some(Value::Str(name)) => layers
+                        .iter()
+                        .position(|layer| layer.name.as_deref() == Some(name.as_str())),
+                    Some(value) => {
+                        let index = self.number(value)?;
+                        layers
+                            .iter()
+                            .position(|layer| layer.index.map(|i| i as f64) == Some(index))
+                            .or_else(|| (index >= 1.0).then_some(index as usize - 1))
+                            .filter(|index| *index < layers.len())
+                    }
+                    None => None,
+                };
+                layer
+                    .map(Value::Layer)
+                    .ok_or_else(|| "no such layer".to_string())
+            }
+            (Value::Layer(layer), "effect") => self.effect(layer, args),
+            (Value::Effect(layer, effect), "param") => {
+                self.call(Value::Effect(layer, effect), args)
+            }
+            (Value::Property(property), "valueAtTime") => {
+                let time = self.arg(&args, 0)?;
+                let frame = (time * self.comp.frame_rate as f64) as f32;
+                Ok(from_numbers(self.property_value(property, frame)?))
+            }
+            (Value::Property(Property::This), name) => self.function(name, args),
+            (Value::Math, name) => self.math(name, args),
+            (object, name) => Err(format!("unknown method {} of {:?}", name, object)),
+        }
+    }
+
+    /// Call a value, which only effects support to get their parameters
+    fn call(&mut self, callee: Value, args: Vec<Value>) -> Result<Value> {
+        let (layer, effect) = match callee {
+            Value::Effect(layer, effect) => (layer, effect),
+            callee => return Err(format!("{:?} is not a function", callee)),
+        };
+        let comp = self.comp.clone();
+        let values = &comp.layers[layer].effects[effect].values;
+        let param = match args.into_iter().next() {
+            Some(Value::Str(name)) => values
+                .iter()
+                .position(|value| value.name.as_deref() == Some(name.as_str())),
+            Some(value) => {
+                let index = self.number(value)? as usize;
+                (index >= 1 && index <= values.len()).then(|| index - 1)
+            }
+            None => None,
+        };
+        param
+            .map(|param| Value::Property(Property::Effect(layer, effect, param)))
+            .ok_or_else(|| "no such effect parameter".to_string())
+    }
+
+    fn effect(&mut self, layer: usize, args: Vec<Value>) -> Result<Value> {
+        let comp = self.comp.clone();
+        let effects = &comp.layers[layer].effects;
+        let effect = match args.into_iter().next() {
+            Some(Value::Str(name)) => effects
+                .iter()
+                .position(|effect| effect.name.as_deref() == Some(name.as_str())),
+            Some(value) => {
+                let index = self.number(value)? as usize;
+                (index >= 1 && index <= effects.len()).then(|| index - 1)
+            }
+            None => None,
+        };
+        effect
+            .map(|effect| Value::Effect(layer, effect))
+            .ok_or_else(|| "no such effect".to_string())
+    }
+
+    fn function(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        let op = match name {
+            "add" | "sum" | "$bm_sum" => Some(BinaryOp::Add),
+            "sub" | "$bm_sub" => Some(BinaryOp::Sub),
+            "mul" | "$bm_mul" => Some(BinaryOp::Mul),
+            "div" | "$bm_div" => Some(BinaryOp::Div),
+            "mod" | "$bm_mod" => Some(BinaryOp::Rem),
+            _ => None,
+        };
+        if let Some(op) = op {
+            let mut args = args.into_iter();
+            let left = args.next().unwrap_or(Value::Undefined);
+            let right = args.next().unwrap_or(Value::Undefined);
+            return self.binary(op, left, right);
+        }
+        Ok(match name {
+            "$bm_neg" => {
+                let value = self.arg_numbers(&args, 0)?;
+                from_numbers(value.iter().map(|n| -n).collect())
+            }
+            "wiggle" => self.wiggle(&args)?,
+            "loopOut" => self.repeat(&args, true)?,
+            "loopIn" => self.repeat(&args, false)?,
+            "linear" => self.interpolate(&args, |t| t)?,
+            "ease" => self.interpolate(&args, |t| bezier_ease(0.33, 0.0, 0.667, 1.0, t))?,
+            "easeIn" => self.interpolate(&args, |t| bezier_ease(0.33, 0.0, 1.0, 1.0, t))?,
+            "easeOut" => self.interpolate(&args, |t| bezier_ease(0.0, 0.0, 0.667, 1.0, t))?,
+            "clamp" => {
+                let value = self.arg_numbers(&args, 0)?;
+                let min = self.arg(&args, 1)?;
+                let max = self.arg(&args, 2)?;
+                from_numbers(value.iter().map(|n| n.max(min).min(max)).collect())
+            }
+            "length" => {
+                let a = self.arg_numbers(&args, 0)?;
+                let b = match args.get(1) {
+                    Some(_) => self.arg_numbers(&args, 1)?,
+                    None => vec![],
+                };
+                let d = combine(&a, &b, |a, b| a - b);
+                Value::Number(d.iter().map(|n| n * n).sum::<f64>().sqrt())
+            }
+            "normalize" => {
+                let a = self.arg_numbers(&args, 0)?;
+                let length = a.iter().map(|n| n * n).sum::<f64>().sqrt();
+                if length == 0.0 {
+                    from_numbers(a)
+                } else {
+                    from_numbers(a.iter().map(|n| n / length).collect())
+                }
+            }
+            "degreesToRadians" => Value::Number(self.arg(&args, 0)?.to_radians()),
+            "radiansToDegrees" => Value::Number(self.arg(&args, 0)?.to_degrees()),
+            "timeToFrames" => {
+                let time = match args.first() {
+                    Some(_) => self.arg(&args, 0)?,
+                    None => self.time(),
+                };
+                Value::Number(time * self.comp.frame_rate as f64)
+            }
+            "framesToTime" => Value::Number(self.arg(&args, 0)? / self.comp.frame_rate as f64),
+            "valueAtTime" => self.method(Value::Property(Property::This), "valueAtTime", args)?,
+            "effect" => self.effect(self.layer, args)?,
+            "seedRandom" => {
+                self.seed = hash(self.arg(&args, 0)?.to_bits());
+                self.timeless = match args.get(1) {
+                    Some(value) => self.truthy(value.clone())?,
+                    None => false,
+                };
+                self.random_count = 0;
+                Value::Undefined
+            }
+            "random" => {
+                let mut numbers = vec![];
+                for _ in 0..args.iter().map(|a| self.width(a)).max().unwrap_or(1) {
+                    numbers.push(self.random());
+                }
+                let (min, max) = match args.len() {
+                    0 => (vec![0.0], vec![1.0]),
+                    1 => (vec![0.0], self.arg_numbers(&args, 0)?),
+                    _ => (self.arg_numbers(&args, 0)?, self.arg_numbers(&args, 1)?),
+                };
+                from_numbers(
+                    numbers
+                        .iter()
+                        .enumerate()
+                        .map(|(i, r)| {
+                            let min = min.get(i).or(min.first()).cloned().unwrap_or(0.0);
+                            let max = max.get(i).or(max.first()).cloned().unwrap_or(0.0);
+                            min + (max - min) * r
+                        })
+                        .collect(),
+                )
+            }
+            name => return Err(format!("unsupported function {}", name)),
+        })
+    }
+
+    fn math(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        let mut numbers = vec![];
+        for arg in args {
+            numbers.push(self.number(arg)?);
+        }
+        let a = numbers.first().cloned().unwrap_or(f64::NAN);
+        let b = numbers.get(1).cloned().unwrap_or(f64::NAN);
+        Ok(Value::Number(match name {
+            "abs" => a.abs(),
+            "sin" => a.sin(),
+            "cos" => a.cos(),
+            "tan" => a.tan(),
+            "asin" => a.asin(),
+            "acos" => a.acos(),
+            "atan" => a.atan(),
+            "atan2" => a.atan2(b),
+            "floor" => a.floor(),
+            "ceil" => a.ceil(),
+            "round" => (a + 0.5).floor(),
+            "sqrt" => a.sqrt(),
+            "pow" => a.powf(b),
+            "exp" => a.exp(),
+            "log" => a.ln(),
+            "sign" => a.signum(),
+            "min" => numbers.into_iter().fold(f64::INFINITY, f64::min),
+            "max" => numbers.into_iter().fold(f64::NEG_INFINITY, f64::max),
+            "random" => self.random(),
+            name => return Err(format!("unsupported function Math.{}", name)),
+        }))
+    }
+
+    fn property_value(&mut self, property: Property, frame: f32) -> Result<Vec<f64>> {
+        let comp = self.comp.clone();
+        Ok(match property {
+            Property::This => self.source.value_at(frame),
+            Property::Transform(layer, property) => {
+                property.value(&comp.layers[layer].transform, frame)
+            }
+            Property::Effect(layer, effect, param) => {
+                match &comp.layers[layer].effects[effect].values[param].value {
+                    EffectValueType::Slider(v)
+                    | EffectValueType::Angle(v)
+                    | EffectValueType::Checkbox(v)
+                    | EffectValueType::DropDown(v)
+                    | EffectValueType::Layer(v) => numbers(&v.value(frame)),
+                    EffectValueType::Color(v) => numbers(&v.value(frame)),
+                    EffectValueType::Point(v) => numbers(&v.value(frame)),
+                    EffectValueType::Other { .. } => {
+                        return Err("unsupported effect parameter".to_string())
+                    }
+                }
+            }
+        })
+    }
+
+    /// Properties are only read when their value is used
+    fn resolve(&mut self, value: Value) -> Result<Value> {
+        match value {
+            Value::Property(property) => {
+                Ok(from_numbers(self.property_value(property, self.frame)?))
+            }
+            value => Ok(value),
+        }
+    }
+
+    fn numbers(&mut self, value: Value) -> Result<Vec<f64>> {
+        match self.resolve(value)? {
+            Value::Number(n) => Ok(vec![n]),
+            Value::Array(a) => Ok(a),
+            Value::Str(s) => Ok(vec![s.trim().parse().unwrap_or(f64::NAN)]),
+            value => Err(format!("{:?} is not a number", value)),
+        }
+    }
+
+    fn number(&mut self, value: Value) -> Result<f64> {
+        match self.resolve(value)? {
+            Value::Number(n) => Ok(n),
+            Value::Str(s) => Ok(s.trim().parse().unwrap_or(f64::NAN)),
+            value => Err(format!("{:?} is not a number", value)),
+        }
+    }
+
+    fn arg(&mut self, args: &[Value], index: usize) -> Result<f64> {
+        let value = args.get(index).cloned().unwrap_or(Value::Undefined);
+        self.number(value)
+    }
+
+    fn arg_numbers(&mut self, args: &[Value], index: usize) -> Result<Vec<f64>> {
+        let value = args.get(index).cloned().unwrap_or(Value::Undefined);
+        self.numbers(value)
+    }
+
+    /// Number of dimensions of a value
+    fn width(&mut self, value: &Value) -> usize {
+        self.numbers(value.clone()).map_or(1, |n| n.len().max(1))
+    }
+
+    fn truthy(&mut self, value: Value) -> Result<bool> {
+        Ok(match self.resolve(value)? {
+            Value::Number(n) => n != 0.0 && !n.is_nan(),
+            Value::Str(s) => !s.is_empty(),
+            Value::Undefined => false,
+            _ => true,
+        })
+    }
+
+    /// Arithmetic follows bodymovin's helpers: numbers added to arrays only
+    /// change their first element, while arrays are scaled as a whole
+    fn binary(&mut self, op: BinaryOp, left: Value, right: Value) -> Result<Value> {
+        let left = self.resolve(left)?;
+        let right = self.resolve(right)?;
+        if let (BinaryOp::Add, Value::Str(_), _) | (BinaryOp::Add, _, Value::Str(_)) =
+            (op, &left, &right)
+        {
+            return Ok(Value::Str(to_string(&left) + &to_string(&right)));
+        }
+        match op {
+            BinaryOp::Eq => return Ok(Value::Number((left == right) as u8 as f64)),
+            BinaryOp::Ne => return Ok(Value::Number((left != right) as u8 as f64)),
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                let a = self.number(left)?;
+                let b = self.number(right)?;
+                let result = match op {
+                    BinaryOp::Lt => a < b,
+                    BinaryOp::Le => a <= b,
+                    BinaryOp::Gt => a > b,
+                    _ => a >= b,
+                };
+                return Ok(Value::Number(result as u8 as f64));
+            }
+            _ => {}
+        }
+        let f = match op {
+            BinaryOp::Add => |a: f64, b: f64| a + b,
+            BinaryOp::Sub => |a: f64, b: f64| a - b,
+            BinaryOp::Mul => |a: f64, b: f64| a * b,
+            BinaryOp::Div => |a: f64, b: f64| a / b,
+            _ => |a: f64, b: f64| a % b,
+        };
+        let scales = matches!(op, BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem);
+        Ok(match (left, right) {
+            (Value::Array(a), Value::Array(b)) => Value::Array(combine(&a, &b, f)),
+            (Value::Array(mut a), b) => {
+                let b = self.number(b)?;
+                if scales {
+                    a.iter_mut().for_each(|a| *a = f(*a, b));
+                } else if let Some(first) = a.first_mut() {
+                    *first = f(*first, b);
+                }
+                Value::Array(a)
+            }
+            (a, Value::Array(mut b)) => {
+                let a = self.number(a)?;
+                if scales {
+                    b.iter_mut().for_each(|b| *b = f(a, *b));
+                } else if let Some(first) = b.first_mut() {
+                    *first = f(a, *first);
+                }
+                Value::Array(b)
+            }
+            (a, b) => Value::Number(f(self.number(a)?, self.number(b)?)),
+        })
+    }
+
+    fn random(&mut self) -> f64 {
+        let frame = if self.timeless {
+            0
+        } else {
+            self.frame.to_bits() as u64
+        };
+        self.random_count += 1;
+        unit(self.seed ^ hash(frame) ^ hash(self.random_count << 32))
+    }
+
+    /// `wiggle(frequency, amplitude, octaves = 1, amplitude_multiplier = 0.5,
+    /// time = time)`, adding smooth noise to the value
+    fn wiggle(&mut self, args: &[Value]) -> Result<Value> {
+        let frequency = self.arg(args, 0)?;
+        let amplitude = self.arg(args, 1)?;
+        let octaves = match args.get(2) {
+            Some(_) => self.arg(args, 2)?.max(1.0) as u64,
+            None => 1,
+        };
+        let multiplier = match args.get(3) {
+            Some(_) => self.arg(args, 3)?,
+            None => 0.5,
+        };
+        let (time, value) = match args.get(4) {
+            Some(_) => {
+                let time = self.arg(args, 4)?;
+                let frame = (time * self.comp.frame_rate as f64) as f32;
+                (time, self.source.value_at(frame))
+            }
+            None => (self.time(), self.source.value_at(self.frame)),
+        };
+        let value = value
+            .iter()
+            .enumerate()
+            .map(|(dimension, value)| {
+                let mut offset = 0.0;
+                let mut octave_amplitude = 1.0;
+                let mut octave_frequency = frequency;
+                for octave in 0..octaves {
+                    let seed = hash(self.seed ^ ((dimension as u64) << 32 | octave));
+                    offset += noise(seed, time * octave_frequency) * octave_amplitude;
+                    octave_amplitude *= multiplier;
+                    octave_frequency *= 2.0;
+                }
+                value + offset * amplitude
+            })
+            .collect();
+        Ok(from_numbers(value))
+    }
+
+    /// `loopOut(type = "cycle", keyframes = 0)` and `loopIn`, repeating the
+    /// keyframes after the last or before the first one. All keyframes are
+    /// repeated when `keyframes` is 0
+    fn repeat(&mut self, args: &[Value], out: bool) -> Result<Value> {
+        let ty = match args.first() {
+            Some(Value::Str(ty)) => ty.clone(),
+            _ => "cycle".to_string(),
+        };
+        let count = match args.get(1) {
+            Some(_) => self.arg(args, 1)?.max(0.0) as usize,
+            None => 0,
+        };
+        let frames = self.source.key_frames();
+        let value = self.source.value_at(self.frame);
+        let n = frames.len();
+        if n < 2 {
+            return Ok(from_numbers(value));
+        }
+        let count = if count == 0 || count >= n {
+            n - 1
+        } else {
+            count
+        };
+        let (first, last) = if out {
+            (frames[n - 1 - count], frames[n - 1])
+        } else {
+            (frames[0], frames[count])
+        };
+        let duration = last - first;
+        let elapsed = if out {
+            self.frame - last
+        } else {
+            first - self.frame
+        };
+        if elapsed <= 0.0 || duration <= 0.0 {
+            return Ok(from_numbers(value));
+        }
+        let cycles = (elapsed / duration).floor();
+        let rest = elapsed - cycles * duration;
+        let source = self.source;
+        let at = |frame: f32| source.value_at(frame);
+        // the keyframe the loop starts from, and the other end
+        let (start, end) = if out { (first, last) } else { (last, first) };
+        let direction = if out { 1.0 } else { -1.0 };
+        let result = match ty.as_str() {
+            "pingpong" if cycles % 2.0 == 0.0 => at(end - rest * direction),
+            "pingpong" => at(start + rest * direction),
+            "offset" => {
+                let change = combine(&at(end), &at(start), |a, b| a - b);
+                let shift = (cycles + 1.0) as f64;
+                combine(&at(start + rest * direction), &change, |a, b| a + b * shift)
+            }
+            "continue" => {
+                let velocity = combine(&at(end), &at(end - direction), |a, b| a - b);
+                combine(&at(end), &velocity, |a, b| a + b * elapsed as f64)
+            }
+            _ => at(start + rest * direction),
+        };
+        Ok(from_numbers(result))
+    }
+
+    /// `linear(t, t_min, t_max, value1, value2)` or `linear(t, value1,
+    /// value2)` with `t` from 0 to 1, and its eased versions
+    fn interpolate(&mut self, args: &[Value], curve: fn(f64) -> f64) -> Result<Value> {
+        let t = self.arg(args, 0)?;
+        let (min, max, from, to) = match args.len() {
+            3 => (0.0, 1.0, 1, 2),
+            5 => (self.arg(args, 1)?, self.arg(args, 2)?, 3, 4),
+            _ => return Err("interpolation takes 3 or 5 arguments".to_string()),
+        };
+        let from = self.arg_numbers(args, from)?;
+        let to = self.arg_numbers(args, to)?;
+        let progress = if max == min {
+            (t >= max) as u8 as f64
+        } else {
+            ((t - min) / (max - min)).clamp(0.0, 1.0)
+        };
+        let progress = curve(progress);
+        Ok(from_numbers(combine(&from, &to, |a, b| {
+            a + (b - a) * progress
+        })))
+    }
+}
+
+/// Apply `f` to the elements of `a` and `b`, the shorter one padded with
+/// zeros
+fn combine(a: &[f64], b: &[f64], f: impl Fn(f64, f64) -> f64) -> Vec<f64> {
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            f(
+                a.get(i).cloned().unwrap_or(0.0),
+                b.get(i).cloned().unwrap_or(0.0),
+            )
+        })
+        .collect()
+}
+
+fn to_string(value: &Value) -> String {
+    match value {
+        Value::Number(n) => n.to_string(),
+        Value::Array(a) => a
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+        Value::Str(s) => s.clone(),
+        _ => "undefined".to_string(),
+    }
+}
+
+/// Progress along a CSS-like cubic bezier easing curve at `t`
+fn bezier_ease(x1: f64, y1: f64, x2: f64, y2: f64, t: f64) -> f64 {
+    let bezier = |a: f64, b: f64, s: f64| {
+        3.0 * a * s * (1.0 - s) * (1.0 - s) + 3.0 * b * s * s * (1.0 - s) + s * s * s
+    };
+    // x is monotonic, find where it reaches `t` by bisection
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..32 {
+        let mid = (low + high) / 2.0;
+        if bezier(x1, x2, mid) < t {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    bezier(y1, y2, (low + high) / 2.0)
+}
+
+pub(super) fn hash(mut x: u64) -> u64 {
+    // splitmix64
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Uniformly distributed in [0, 1)
+fn unit(x: u64) -> f64 {
+    (hash(x) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Smooth value noise from -1 to 1
+fn noise(seed: u64, x: f64) -> f64 {
+    let cell = x.floor();
+    let t = x - cell;
+    let a = unit(seed ^ hash(cell as i64 as u64)) * 2.0 - 1.0;
+    let b = unit(seed ^ hash((cell as i64 + 1) as u64)) * 2.0 - 1.0;
+    let t = t * t * (3.0 - 2.0 * t);
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::bind::LayerScope;
+    use crate::expression::parser::parse;
+
+    /// Linear keyframes at frames 0, 10 and 20 with values 0, 10 and 4
+    struct Keyframes;
+
+    impl Source for Keyframes {
+        fn value_at(&self, frame: f32) -> Vec<f64> {
+            let frame = frame.clamp(0.0, 20.0) as f64;
+            if frame <= 10.0 {
+                vec![frame]
+            } else {
+                vec![10.0 - (frame - 10.0) * 0.6]
+            }
+        }
+
+        fn key_frames(&self) -> Vec<f32> {
+            vec![0.0, 10.0, 20.0]
+        }
+    }
+
+    fn eval(source: &str, frame: f32) -> Option<Vec<f64>> {
+        let comp = Arc::new(CompScope {
+            width: 100.0,
+            height: 100.0,
+            frame_rate: 10.0,
+            start_frame: 0.0,
+            end_frame: 100.0,
+            layers: vec![LayerScope {
+                name: None,
+                index: Some(1),
+                start_frame: 0.0,
+                end_frame: 100.0,
+                start_time: 0.0,
+                transform: Transform::default(),
+                effects: vec![],
+            }],
+        });
+        let binding = Binding {
+            comp: Arc::downgrade(&comp),
+            layer: 0,
+        };
+        evaluate(&parse(source).unwrap(), &Keyframes, &binding, 0, frame)
+    }
+
+    fn assert_near(actual: Option<Vec<f64>>, expected: &[f64]) {
+        let actual = actual.unwrap();
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn loop_out() {
+        // 3 frames past the last keyframe
+        assert_near(eval("loopOut()", 23.0), &[3.0]);
+        assert_near(eval("loopOut('cycle', 1)", 23.0), &[8.2]);
+        assert_near(eval("loopOut('pingpong')", 23.0), &[5.8]);
+        assert_near(eval("loopOut('pingpong')", 43.0), &[3.0]);
+        // shifted by the change from the first to the last keyframe
+        assert_near(eval("loopOut('offset')", 23.0), &[7.0]);
+        assert_near(eval("loopOut('offset')", 43.0), &[11.0]);
+        // at the velocity of the last keyframe
+        assert_near(eval("loopOut('continue')", 23.0), &[2.2]);
+        // the keyframes play as usual
+        assert_near(eval("loopOut('pingpong')", 15.0), &[7.0]);
+    }
+
+    #[test]
+    fn loop_in() {
+        // 3 frames before the first keyframe
+        assert_near(eval("loopIn()", -3.0), &[5.8]);
+        assert_near(eval("loopIn('pingpong')", -3.0), &[3.0]);
+        assert_near(eval("loopIn('pingpong')", -23.0), &[5.8]);
+        assert_near(eval("loopIn('offset')", -3.0), &[1.8]);
+        assert_near(eval("loopIn('continue')", -3.0), &[-3.0]);
+        assert_near(eval("loopIn('continue')", 5.0), &[5.0]);
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_near(eval("linear(5, 0, 10, 0, 100)", 0.0), &[50.0]);
+        assert_near(eval("linear(0.25, 0, 100)", 0.0), &[25.0]);
+        // clamped outside of the range
+        assert_near(eval("linear(20, 0, 10, 0, 100)", 0.0), &[100.0]);
+        assert_near(eval("ease(-1, 0, 10, 0, 100)", 0.0), &[0.0]);
+        // arrays are eased element by element
+        assert_near(
+            eval("ease(10, 0, 10, [0, 0], [10, 20])", 0.0),
+            &[10.0, 20.0],
+        );
+        let eased = eval("ease(5, 0, 10, [0, 0], [10, 20])", 0.0).unwrap();
+        assert!((eased[0] - 5.0).abs() < 0.1 && (eased[1] - 10.0).abs() < 0.1);
+        let ease_in = eval("easeIn(0.5, 0, 100)", 0.0).unwrap()[0];
+        let ease_out = eval("easeOut(0.5, 0, 100)", 0.0).unwrap()[0];
+        assert!(
+            ease_in < 50.0 && ease_out > 50.0,
+            "{} {}",
+            ease_in,
+            ease_out
+        );
+        assert_eq!(eval("ease(0.5, 0)", 0.0), None);
+    }
+}
@@ -0,0 +1,517 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// The JavaScript subset expressions are written in
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Expr(Expr),
+    Var(String, Option<Expr>),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    Block(Vec<Stmt>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Str(String),
+    Array(Vec<Expr>),
+    Ident(String),
+    Member(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// Assignment to a variable or to an element of one, with the operator of
+    /// compound assignments
+    Assign(Box<Expr>, Option<BinaryOp>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Plus,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Punct(&'static str),
+}
+
+/// Longest punctuators first so that `===` is not read as `==` and `=`
+const PUNCTUATORS: &[&str] = &[
+    "===", "!==", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=", "*=", "/=", "(", ")", "[", "]",
+    "{", "}", ",", ".", ";", "?", ":", "+", "-", "*", "/", "%", "!", "=", "<", ">",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.clone().nth(1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            tokens.push(Token::Number(number(&mut chars)?));
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some('\\') => s.extend(chars.next()),
+                    Some(end) if end == c => break,
+                    Some(other) => s.push(other),
+                    None => return Err("unterminated string".to_string()),
+                }
+            }
+            tokens.push(Token::Str(s));
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let mut ident = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' || c == '$' {
+                    ident.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Ident(ident));
+        } else if c == '/' && matches!(chars.clone().nth(1), Some('/') | Some('*')) {
+            chars.next();
+            if chars.next() == Some('/') {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+            } else {
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => previous = c,
+                        None => return Err("unterminated comment".to_string()),
+                    }
+                }
+            }
+        } else {
+            let rest = chars.clone().take(3).collect::<String>();
+            let punct = PUNCTUATORS
+                .iter()
+                .find(|p| rest.starts_with(**p))
+                .ok_or_else(|| format!("unexpected character {:?}", c))?;
+            for _ in 0..punct.len() {
+                chars.next();
+            }
+            tokens.push(Token::Punct(punct));
+        }
+    }
+    Ok(tokens)
+}
+
+fn number(chars: &mut Peekable<Chars>) -> Result<f64, String> {
+    let mut s = String::new();
+    while let Some(&c) = chars.peek() {
+        let exponent_sign = (c == '-' || c == '+') && s.ends_with(['e', 'E']);
+        if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign {
+            s.push(c);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+    s.parse().map_err(|_| format!("invalid number {}", s))
+}
+
+/// Parse the statements of an expression
+pub fn parse(source: &str) -> Result<Vec<Stmt>, String> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        pos: 0,
+    };
+    let mut statements = vec![];
+    while parser.peek().is_some() {
+        if !parser.eat(";") {
+            statements.push(parser.statement()?);
+        }
+    }
+    Ok(statements)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn is(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if self.is(punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), String> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}`, found {:?}", punct, self.peek()))
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(i)) if i == keyword)
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Ident(ident)) => Ok(ident),
+            token => Err(format!("expected an identifier, found {:?}", token)),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Stmt, String> {
+        if self.is_keyword("var") || self.is_keyword("let") || self.is_keyword("const") {
+            self.pos += 1;
+            let name = self.ident()?;
+            let value = if self.eat("=") {
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            Ok(Stmt::Var(name, value))
+        } else if self.is_keyword("if") {
+            self.pos += 1;
+            self.expect("(")?;
+            let condition = self.expression()?;
+            self.expect(")")?;
+            let then = self.branch()?;
+            let otherwise = if self.is_keyword("else") {
+                self.pos += 1;
+                self.branch()?
+            } else {
+                vec![]
+            };
+            Ok(Stmt::If(condition, then, otherwise))
+        } else if self.eat("{") {
+            let mut statements = vec![];
+            while !self.eat("}") {
+                if self.peek().is_none() {
+                    return Err("unterminated block".to_string());
+                }
+                if !self.eat(";") {
+                    statements.push(self.statement()?);
+                }
+            }
+            Ok(Stmt::Block(statements))
+        } else {
+            Ok(Stmt::Expr(self.expression()?))
+        }
+    }
+
+    fn branch(&mut self) -> Result<Vec<Stmt>, String> {
+        Ok(match self.statement()? {
+            Stmt::Block(statements) => statements,
+            statement => vec![statement],
+        })
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        let target = self.conditional()?;
+        let op = match self.peek() {
+            Some(Token::Punct("=")) => None,
+            Some(Token::Punct("+=")) => Some(BinaryOp::Add),
+            Some(Token::Punct("-=")) => Some(BinaryOp::Sub),
+            Some(Token::Punct("*=")) => Some(BinaryOp::Mul),
+            Some(Token::Punct("/=")) => Some(BinaryOp::Div),
+            _ => return Ok(target),
+        };
+        if !matches!(target, Expr::Ident(_) | Expr::Index(..)) {
+            return Err("invalid assignment target".to_string());
+        }
+        self.pos += 1;
+        let value = self.expression()?;
+        Ok(Expr::Assign(Box::new(target), op, Box::new(value)))
+    }
+
+    fn conditional(&mut self) -> Result<Expr, String> {
+        let condition = self.or()?;
+        if !self.eat("?") {
+            return Ok(condition);
+        }
+        let then = self.expression()?;
+        self.expect(":")?;
+        let otherwise = self.expression()?;
+        Ok(Expr::Conditional(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.eat("||") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.binary(0)?;
+        while self.eat("&&") {
+            left = Expr::And(Box::new(left), Box::new(self.binary(0)?));
+        }
+        Ok(left)
+    }
+
+    /// Binary operators by increasing precedence
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        const LEVELS: &[&[(&str, BinaryOp)]] = &[
+            &[
+                ("===", BinaryOp::Eq),
+                ("!==", BinaryOp::Ne),
+                ("==", BinaryOp::Eq),
+                ("!=", BinaryOp::Ne),
+            ],
+            &[
+                ("<=", BinaryOp::Le),
+                (">=", BinaryOp::Ge),
+                ("<", BinaryOp::Lt),
+                (">", BinaryOp::Gt),
+            ],
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            &[
+                ("*", BinaryOp::Mul),
+                ("/", BinaryOp::Div),
+                ("%", BinaryOp::Rem),
+            ],
+        ];
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        'outer: loop {
+            for (punct, op) in LEVELS[level] {
+                if self.eat(punct) {
+                    let right = self.binary(level + 1)?;
+                    left = Expr::Binary(*op, Box::new(left), Box::new(right));
+                    continue 'outer;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let op = if self.eat("-") {
+            UnaryOp::Neg
+        } else if self.eat("+") {
+            UnaryOp::Plus
+        } else if self.eat("!") {
+            UnaryOp::Not
+        } else {
+            return self.postfix();
+        };
+        Ok(Expr::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat(".") {
+                expr = Expr::Member(Box::new(expr), self.ident()?);
+            } else if self.eat("[") {
+                let index = self.expression()?;
+                self.expect("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else if self.eat("(") {
+                let args = self.list(")")?;
+                expr = Expr::Call(Box::new(expr), args);
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Str(s)) => Ok(Expr::Str(s)),
+            Some(Token::Ident(i)) if i == "true" => Ok(Expr::Number(1.0)),
+            Some(Token::Ident(i)) if i == "false" => Ok(Expr::Number(0.0)),
+            Some(Token::Ident(i)) => Ok(Expr::Ident(i)),
+            Some(Token::Punct("(")) => {
+                let expr = self.expression()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(Token::Punct("[")) => Ok(Expr::Array(self.list("]")?)),
+            token => Err(format!("unexpected {:?}", token)),
+        }
+    }
+
+    /// Comma separated expressions up to `end`
+    fn list(&mut self, end: &str) -> Result<Vec<Expr>, String> {
+        let mut items = vec![];
+        while !self.eat(end) {
+            items.push(self.expression()?);
+            if !self.eat(",") {
+                self.expect(end)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expr(source: &str) -> Expr {
+        match parse(source).unwrap().as_slice() {
+            [Stmt::Expr(expr)] => expr.clone(),
+            statements => panic!("{:?}", statements),
+        }
+    }
+
+    fn number(n: f64) -> Box<Expr> {
+        Box::new(Expr::Number(n))
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            expr("1 + 2 * 3"),
+            Expr::Binary(
+                BinaryOp::Add,
+                number(1.0),
+                Box::new(Expr::Binary(BinaryOp::Mul, number(2.0), number(3.0)))
+            )
+        );
+        assert_eq!(
+            expr("-(1 - 2) % 3"),
+            Expr::Binary(
+                BinaryOp::Rem,
+                Box::new(Expr::Unary(
+                    UnaryOp::Neg,
+                    Box::new(Expr::Binary(BinaryOp::Sub, number(1.0), number(2.0)))
+                )),
+                number(3.0)
+            )
+        );
+        assert!(
+            matches!(expr("a || b && c"), Expr::Or(_, right) if matches!(*right, Expr::And(..)))
+        );
+        assert!(matches!(expr("a === 1 ? 2 : 3"), Expr::Conditional(..)));
+    }
+
+    #[test]
+    fn calls_and_members() {
+        assert_eq!(
+            expr("loopOut('pingpong', 2)"),
+            Expr::Call(
+                Box::new(Expr::Ident("loopOut".to_string())),
+                vec![Expr::Str("pingpong".to_string()), Expr::Number(2.0)]
+            )
+        );
+        assert_eq!(
+            expr("thisComp.layer(\"a\")[0]"),
+            Expr::Index(
+                Box::new(Expr::Call(
+                    Box::new(Expr::Member(
+                        Box::new(Expr::Ident("thisComp".to_string())),
+                        "layer".to_string()
+                    )),
+                    vec![Expr::Str("a".to_string())]
+                )),
+                number(0.0)
+            )
+        );
+        assert_eq!(
+            expr("[1, .5, 2e-1,]"),
+            Expr::Array(vec![
+                Expr::Number(1.0),
+                Expr::Number(0.5),
+                Expr::Number(0.2)
+            ])
+        );
+    }
+
+    #[test]
+    fn statements() {
+        let program = parse(
+            "var $bm_rt; // result\n\
+             if (time > 1) { $bm_rt = value; } else $bm_rt += 1; /* done */",
+        )
+        .unwrap();
+        assert_eq!(program.len(), 2);
+        assert_eq!(program[0], Stmt::Var("$bm_rt".to_string(), None));
+        match &program[1] {
+            Stmt::If(_, then, otherwise) => {
+                assert!(matches!(
+                    then.as_slice(),
+                    [Stmt::Expr(Expr::Assign(_, None, _))]
+                ));
+                assert!(matches!(
+                    otherwise.as_slice(),
+                    [Stmt::Expr(Expr::Assign(_, Some(BinaryOp::Add), _))]
+                ));
+            }
+            statement => panic!("{:?}", statement),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        for source in [
+            "'unterminated",
+            "/* unterminated",
+            "{ 1",
+            "1 = 2",
+            "(1",
+            "f(1 2)",
+            "1 +",
+            "a # b",
+            "1.2.3",
+            "var 1",
+        ] {
+            assert!(parse(source).is_err(), "{}", source);
+        }
+    }
+}
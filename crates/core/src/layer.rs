@@ -1,6 +1,8 @@
+pub mod effect;
 pub mod frame;
 pub mod hierarchy;
 pub mod media;
+pub mod modifier;
 pub mod shape;
 pub mod staged;
 pub mod text;
@@ -0,0 +1,361 @@
+use crate::model::{Animated, Effect, EffectType, Rgba};
+
+/// Ratio of Gaussian Blur's blurriness to the standard deviation of the blur,
+/// same as lottie-web
+const BLURRINESS_TO_SIGMA: f32 = 0.3;
+
+/// An [Effect] which renderers know how to apply, with its parameters
+#[derive(Debug, Clone)]
+pub enum LayerEffect {
+    /// Paint every pixel with `color`, keeping the alpha
+    Fill {
+        color: Animated<Rgba>,
+        /// From 0 to 1
+        opacity: Animated<f32>,
+    },
+    /// Map the luminance of every pixel from black to white
+    Tint {
+        black: Animated<Rgba>,
+        white: Animated<Rgba>,
+        /// Percentage the tinted color is mixed in with
+        amount: Animated<f32>,
+    },
+    /// Map the luminance of every pixel to shadows, midtones and highlights
+    Tritone {
+        highlights: Animated<Rgba>,
+        midtones: Animated<Rgba>,
+        shadows: Animated<Rgba>,
+        /// Percentage the original color is mixed back in with
+        blend: Animated<f32>,
+    },
+    DropShadow {
+        color: Animated<Rgba>,
+        /// From 0 to 255
+        opacity: Animated<f32>,
+        /// Clockwise from the top, in degrees
+        direction: Animated<f32>,
+        distance: Animated<f32>,
+        softness: Animated<f32>,
+        /// Draw the shadow without the layer when non zero
+        shadow_only: Animated<f32>,
+    },
+    GaussianBlur {
+        blurriness: Animated<f32>,
+        /// 1 to blur in both directions, 2 horizontally and 3 vertically
+        dimensions: Animated<f32>,
+    },
+}
+
+impl LayerEffect {
+    /// Typed version of `effect`. Returns `None` if the effect is disabled,
+    /// unsupported or lacks parameters
+    pub fn from_effect(effect: &Effect) -> Option<LayerEffect> {
+        if !effect.enabled {
+            return None;
+        }
+        let number = |index| effect.number(index).cloned();
+        let color = |index| effect.color(index).cloned();
+        let result = match effect.ty {
+            EffectType::Fill => LayerEffect::Fill {
+                color: color(2)?,
+                opacity: number(6)?,
+            },
+            EffectType::Tint => LayerEffect::Tint {
+                black: color(0)?,
+                white: color(1)?,
+                amount: number(2)?,
+            },
+            EffectType::Tritone => LayerEffect::Tritone {
+                highlights: color(0)?,
+                midtones: color(1)?,
+                shadows: color(2)?,
+                blend: number(3)?,
+            },
+            EffectType::DropShadow => LayerEffect::DropShadow {
+                color: color(0)?,
+                opacity: number(1)?,
+                direction: number(2)?,
+                distance: number(3)?,
+                softness: number(4)?,
+                shadow_only: number(5).unwrap_or_else(|| Animated::from_value(0.0)),
+            },
+            EffectType::GaussianBlur => LayerEffect::GaussianBlur {
+                blurriness: number(0)?,
+                dimensions: number(1).unwrap_or_else(|| Animated::from_value(1.0)),
+            },
+            // Custom effects only hold values for expressions
+            EffectType::Custom => return None,
+            ty => {
+                log::warn!("effect {:?} is not supported", ty);
+                return None;
+            }
+        };
+        Some(result)
+    }
+
+    pub fn is_animated(&self) -> bool {
+        match self {
+            LayerEffect::Fill { color, opacity } => color.is_animated() || opacity.is_animated(),
+            LayerEffect::Tint {
+                black,
+                white,
+                amount,
+            } => black.is_animated() || white.is_animated() || amount.is_animated(),
+            LayerEffect::Tritone {
+                highlights,
+                midtones,
+                shadows,
+                blend,
+            } => {
+                highlights.is_animated()
+                    || midtones.is_animated()
+                    || shadows.is_animated()
+                    || blend.is_animated()
+            }
+            LayerEffect::DropShadow {
+                color,
+                opacity,
+                direction,
+                distance,
+                softness,
+                shadow_only,
+            } => {
+                color.is_animated()
+                    || opacity.is_animated()
+                    || direction.is_animated()
+                    || distance.is_animated()
+                    || softness.is_animated()
+                    || shadow_only.is_animated()
+            }
+            LayerEffect::GaussianBlur {
+                blurriness,
+                dimensions,
+            } => blurriness.is_animated() || dimensions.is_animated(),
+        }
+    }
+
+    /// Standard deviation of the blur in both directions, in composition
+    /// units
+    pub fn blur_sigma(&self, frame: f32) -> (f32, f32) {
+        match self {
+            LayerEffect::GaussianBlur {
+                blurriness,
+                dimensions,
+            } => {
+                let sigma = blurriness.value(frame).max(0.0) * BLURRINESS_TO_SIGMA;
+                match dimensions.value(frame).round() as u32 {
+                    2 => (sigma, 0.0),
+                    3 => (0.0, sigma),
+                    _ => (sigma, sigma),
+                }
+            }
+            // lottie-web uses a quarter of the softness
+            LayerEffect::DropShadow { softness, .. } => {
+                let sigma = softness.value(frame).max(0.0) / 4.0;
+                (sigma, sigma)
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// Offset of a drop shadow from the layer, in composition units
+    pub fn shadow_offset(&self, frame: f32) -> (f32, f32) {
+        match self {
+            LayerEffect::DropShadow {
+                direction,
+                distance,
+                ..
+            } => {
+                let angle = direction.value(frame).to_radians();
+                let distance = distance.value(frame);
+                (angle.sin() * distance, -angle.cos() * distance)
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// Apply the effect to a `width` by `height` image of premultiplied RGBA
+    /// pixels, rendered at `scale` times the composition size
+    pub fn apply(&self, pixels: &mut [u8], width: usize, height: usize, frame: f32, scale: f32) {
+        match self {
+            LayerEffect::Fill { color, opacity } => {
+                let color = unit_rgb(color.value(frame));
+                let opacity = opacity.value(frame).clamp(0.0, 1.0);
+                map_pixels(pixels, |_, alpha| (color, alpha * opacity));
+            }
+            LayerEffect::Tint {
+                black,
+                white,
+                amount,
+            } => {
+                let black = unit_rgb(black.value(frame));
+                let white = unit_rgb(white.value(frame));
+                let amount = (amount.value(frame) / 100.0).clamp(0.0, 1.0);
+                map_pixels(pixels, |rgb, alpha| {
+                    let tinted = mix(black, white, luminance(rgb));
+                    (mix(rgb, tinted, amount), alpha)
+                });
+            }
+            LayerEffect::Tritone {
+                highlights,
+                midtones,
+                shadows,
+                blend,
+            } => {
+                let highlights = unit_rgb(highlights.value(frame));
+                let midtones = unit_rgb(midtones.value(frame));
+                let shadows = unit_rgb(shadows.value(frame));
+                let blend = (blend.value(frame) / 100.0).clamp(0.0, 1.0);
+                map_pixels(pixels, |rgb, alpha| {
+                    let lum = luminance(rgb);
+                    let toned = if lum < 0.5 {
+                        mix(shadows, midtones, lum * 2.0)
+                    } else {
+                        mix(midtones, highlights, lum * 2.0 - 1.0)
+                    };
+                    (mix(toned, rgb, blend), alpha)
+                });
+            }
+            LayerEffect::DropShadow {
+                color,
+                opacity,
+                shadow_only,
+                ..
+            } => {
+                let color = unit_rgb(color.value(frame));
+                let opacity = (opacity.value(frame) / 255.0).clamp(0.0, 1.0);
+                let (dx, dy) = self.shadow_offset(frame);
+                let (dx, dy) = ((dx * scale).round() as isize, (dy * scale).round() as isize);
+                let mut shadow = vec![0.0; width * height];
+                for y in 0..height as isize {
+                    for x in 0..width as isize {
+                        let (sx, sy) = (x - dx, y - dy);
+                        if sx < 0 || sy < 0 || sx >= width as isize || sy >= height as isize {
+                            continue;
+                        }
+                        let alpha = pixels[(sy as usize * width + sx as usize) * 4 + 3];
+                        shadow[y as usize * width + x as usize] = alpha as f32 / 255.0;
+                    }
+                }
+                let sigma = self.blur_sigma(frame).0 * scale;
+                blur(&mut shadow, 1, width, height, (sigma, sigma));
+                let shadow_only = shadow_only.value(frame) != 0.0;
+                for (pixel, alpha) in pixels.chunks_exact_mut(4).zip(shadow) {
+                    let alpha = alpha * opacity;
+                    let below = color.map(|c| c * alpha);
+                    let (src, src_alpha) = if shadow_only {
+                        ([0.0; 3], 0.0)
+                    } else {
+                        let src = [0, 1, 2].map(|i| pixel[i] as f32 / 255.0);
+                        (src, pixel[3] as f32 / 255.0)
+                    };
+                    // the layer is drawn over its shadow
+                    for i in 0..3 {
+                        pixel[i] = to_u8(src[i] + below[i] * (1.0 - src_alpha));
+                    }
+                    pixel[3] = to_u8(src_alpha + alpha * (1.0 - src_alpha));
+                }
+            }
+            LayerEffect::GaussianBlur { .. } => {
+                let (x, y) = self.blur_sigma(frame);
+                let mut data = pixels.iter().map(|c| *c as f32).collect::<Vec<_>>();
+                blur(&mut data, 4, width, height, (x * scale, y * scale));
+                for (pixel, value) in pixels.iter_mut().zip(data) {
+                    *pixel = value.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Apply `effects` in order, see [LayerEffect::apply]
+pub fn apply_effects(
+    effects: &[LayerEffect],
+    pixels: &mut [u8],
+    width: usize,
+    height: usize,
+    frame: f32,
+    scale: f32,
+) {
+    for effect in effects {
+        effect.apply(pixels, width, height, frame, scale);
+    }
+}
+
+/// Replace the unpremultiplied color and alpha of every visible pixel
+fn map_pixels<F>(pixels: &mut [u8], f: F)
+where
+    F: Fn([f32; 3], f32) -> ([f32; 3], f32),
+{
+    for pixel in pixels.chunks_exact_mut(4) {
+        if pixel[3] == 0 {
+            continue;
+        }
+        let alpha = pixel[3] as f32 / 255.0;
+        let rgb = [0, 1, 2].map(|i| pixel[i] as f32 / 255.0 / alpha);
+        let (rgb, alpha) = f(rgb, alpha);
+        for i in 0..3 {
+            pixel[i] = to_u8(rgb[i].clamp(0.0, 1.0) * alpha);
+        }
+        pixel[3] = to_u8(alpha);
+    }
+}
+
+fn unit_rgb(color: Rgba) -> [f32; 3] {
+    [color.r, color.g, color.b].map(|c| c as f32 / 255.0)
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Luminance weights used by lottie-web
+fn luminance(rgb: [f32; 3]) -> f32 {
+    rgb[0] * 0.3 + rgb[1] * 0.59 + rgb[2] * 0.11
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+/// Separable Gaussian blur of an image with `channels` values per pixel,
+/// treating everything outside of it as transparent
+fn blur(data: &mut [f32], channels: usize, width: usize, height: usize, sigma: (f32, f32)) {
+    let passes = [(sigma.0, 1, width, height), (sigma.1, width, height, width)];
+    for (sigma, stride, len, lines) in passes {
+        if sigma < 0.1 {
+            continue;
+        }
+        let radius = (sigma * 3.0).ceil() as isize;
+        let kernel = (-radius..=radius)
+            .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+            .collect::<Vec<_>>();
+        let total = kernel.iter().sum::<f32>();
+        // offset of the first pixel of a line, and of the next line
+        let line_step = if stride == 1 { width } else { 1 };
+        let mut line = vec![0.0; len * channels];
+        for l in 0..lines {
+            let start = l * line_step;
+            for i in 0..len {
+                let mut sum = [0.0; 4];
+                for (k, weight) in kernel.iter().enumerate() {
+                    let j = i as isize + k as isize - radius;
+                    if j < 0 || j >= len as isize {
+                        continue;
+                    }
+                    let index = (start + j as usize * stride) * channels;
+                    for c in 0..channels {
+                        sum[c] += data[index + c] * weight;
+                    }
+                }
+                for c in 0..channels {
+                    line[i * channels + c] = sum[c] / total;
+                }
+            }
+            for i in 0..len {
+                let index = (start + i * stride) * channels;
+                data[index..index + channels].copy_from_slice(&line[i * channels..][..channels]);
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
-use crate::model::{Animated, MatteMode, Transform};
+use crate::model::{Animated, MaskMode, MatteMode, Transform, Vector2D};
 
+use super::staged::FEATHER_STEPS;
 use crate::prelude::Id;
 
 #[derive(Default, Debug, Clone)]
@@ -51,10 +52,63 @@ impl OpacityHierarchy {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct StagedLayerMask {
     pub mode: MatteMode,
     pub id: Id,
+    /// Set for masks from `masksProperties`, which are combined with the other
+    /// masks of the same layer before being applied. Track mattes leave this
+    /// as `None`
+    pub composition: Option<MaskComposition>,
+}
+
+/// How a layer mask combines with the masks listed before it
+#[derive(Debug, Clone)]
+pub struct MaskComposition {
+    pub mode: MaskMode,
+    /// Whether this is the first mask of its layer, which starts a new
+    /// combination
+    pub first: bool,
+    /// Feather of the mask edge, `None` without feather
+    pub feather: Option<Animated<Vector2D>>,
+}
+
+impl MaskComposition {
+    /// Width of the bands a feathered mask edge fades through at `frame`,
+    /// `0.0` without feather. Renderers may blur the mask by this much to
+    /// hide the bands
+    pub fn feather_step(&self, frame: f32) -> f32 {
+        match &self.feather {
+            Some(feather) => {
+                let feather = feather.value(frame);
+                (feather.x + feather.y) / 2.0 / FEATHER_STEPS as f32
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Alpha the combination starts from. Layers whose first mask removes
+    /// area start fully visible
+    pub fn initial_alpha(&self) -> f32 {
+        match self.mode {
+            MaskMode::Subtract | MaskMode::Intersect | MaskMode::Darken => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Combine the `accumulated` alpha of the previous masks with the `alpha`
+    /// of this one
+    pub fn combine(&self, accumulated: f32, alpha: f32) -> f32 {
+        match self.mode {
+            MaskMode::None => accumulated,
+            MaskMode::Add => accumulated + alpha - accumulated * alpha,
+            MaskMode::Subtract => accumulated * (1.0 - alpha),
+            MaskMode::Intersect => accumulated * alpha,
+            MaskMode::Lighten => accumulated.max(alpha),
+            MaskMode::Darken => accumulated.min(alpha),
+            MaskMode::Difference => (accumulated - alpha).abs(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -0,0 +1,468 @@
+use crate::model::*;
+use lyon_path::geom::euclid::approxeq::ApproxEq;
+use lyon_path::geom::CubicBezierSegment;
+use lyon_path::path::Path;
+use lyon_path::Event;
+
+use super::shape::PathFactory;
+use crate::lerp::SpatialPath;
+
+/// Control point distance of a quarter circle approximated by a cubic bezier
+const KAPPA: f32 = 0.5519;
+
+/// Apply path `modifiers` to `path` at `frame`, in order
+pub fn modify_path(path: Path, modifiers: &[Shape], frame: f32) -> Path {
+    if modifiers.is_empty() {
+        return path;
+    }
+    let mut beziers = beziers_from_path(&path);
+    for modifier in modifiers {
+        beziers = match modifier {
+            Shape::RoundedCorners { radius } => {
+                let radius = radius.value(frame);
+                beziers.iter().map(|b| round_corners(b, radius)).collect()
+            }
+            Shape::PuckerBloat { amount } => {
+                let amount = amount.value(frame) / 100.0;
+                beziers.iter().map(|b| pucker_bloat(b, amount)).collect()
+            }
+            Shape::Twist { angle, center } => {
+                twist(&beziers, angle.value(frame), center.value(frame))
+            }
+            Shape::ZigZag {
+                frequency,
+                amplitude,
+                point_type,
+            } => {
+                let frequency = frequency.value(frame).max(0.0).round() as usize;
+                let amplitude = amplitude.value(frame);
+                let smooth = point_type.value(frame).round() as u32 == 2;
+                beziers
+                    .iter()
+                    .map(|b| zig_zag(b, frequency, amplitude, smooth))
+                    .collect()
+            }
+            Shape::OffsetPath {
+                amount,
+                line_join,
+                miter_limit,
+            } => {
+                let amount = amount.value(frame);
+                let miter_limit = miter_limit.as_ref().map(|m| m.value(frame)).unwrap_or(4.0);
+                beziers
+                    .iter()
+                    .map(|b| offset(b, amount, *line_join, miter_limit))
+                    .collect()
+            }
+            _ => beziers,
+        };
+    }
+    beziers.path(frame)
+}
+
+/// Convert `path` into beziers with tangents relative to their vertices
+fn beziers_from_path(path: &Path) -> Vec<Bezier> {
+    let mut result = vec![];
+    let mut current = Bezier::default();
+    for event in path.iter() {
+        match event {
+            Event::Begin { at } => {
+                current = Bezier::default();
+                current.push(at.to_vector(), Vector2D::zero());
+            }
+            Event::Line { to, .. } => current.push(to.to_vector(), Vector2D::zero()),
+            Event::Quadratic { from, ctrl, to } => {
+                let cubic = lyon_path::geom::QuadraticBezierSegment { from, ctrl, to }.to_cubic();
+                current.cubic_to(cubic);
+            }
+            Event::Cubic {
+                from,
+                ctrl1,
+                ctrl2,
+                to,
+            } => current.cubic_to(CubicBezierSegment {
+                from,
+                ctrl1,
+                ctrl2,
+                to,
+            }),
+            Event::End { close, .. } => {
+                current.closed = close;
+                current.remove_closing_vertex();
+                result.push(std::mem::take(&mut current));
+            }
+        }
+    }
+    result
+}
+
+impl Bezier {
+    fn push(&mut self, vertex: Vector2D, in_tangent: Vector2D) {
+        self.verticies.push(vertex);
+        self.in_tangent.push(in_tangent);
+        self.out_tangent.push(Vector2D::zero());
+    }
+
+    /// Append `segment`, which starts at the last vertex
+    fn cubic_to(&mut self, segment: CubicBezierSegment<f32>) {
+        if let Some(out) = self.out_tangent.last_mut() {
+            *out = segment.ctrl1 - segment.from;
+        }
+        self.push(segment.to.to_vector(), segment.ctrl2 - segment.to);
+    }
+
+    /// Closed paths end where they started, fold the last vertex into the
+    /// first one
+    fn remove_closing_vertex(&mut self) {
+        let len = self.verticies.len();
+        if self.closed && len > 1 && self.verticies[len - 1].approx_eq(&self.verticies[0]) {
+            self.verticies.pop();
+            self.out_tangent.pop();
+            self.in_tangent[0] = self.in_tangent.pop().unwrap();
+        }
+    }
+
+    fn segment_count(&self) -> usize {
+        match self.verticies.len() {
+            0 => 0,
+            len if self.closed => len,
+            len => len - 1,
+        }
+    }
+
+    fn segment(&self, index: usize) -> CubicBezierSegment<f32> {
+        let next = (index + 1) % self.verticies.len();
+        CubicBezierSegment {
+            from: self.verticies[index].to_point(),
+            ctrl1: (self.verticies[index] + self.out_tangent[index]).to_point(),
+            ctrl2: (self.verticies[next] + self.in_tangent[next]).to_point(),
+            to: self.verticies[next].to_point(),
+        }
+    }
+}
+
+/// Replace sharp corners with quarter circle-like arcs of `radius`, as
+/// lottie-web does
+fn round_corners(bezier: &Bezier, radius: f32) -> Bezier {
+    let len = bezier.verticies.len();
+    if radius <= 0.0 || len < 2 {
+        return bezier.clone();
+    }
+    let mut result = Bezier {
+        closed: bezier.closed,
+        ..Default::default()
+    };
+    for index in 0..len {
+        let vertex = bezier.verticies[index];
+        let sharp = bezier.in_tangent[index].approx_eq(&Vector2D::zero())
+            && bezier.out_tangent[index].approx_eq(&Vector2D::zero());
+        let end_point = !bezier.closed && (index == 0 || index == len - 1);
+        if !sharp || end_point {
+            result.verticies.push(vertex);
+            result.in_tangent.push(bezier.in_tangent[index]);
+            result.out_tangent.push(bezier.out_tangent[index]);
+            continue;
+        }
+        let corner = |neighbour: Vector2D| {
+            let distance = (neighbour - vertex).length();
+            let ratio = if distance > 0.0 {
+                (distance / 2.0).min(radius) / distance
+            } else {
+                0.0
+            };
+            let point = vertex + (neighbour - vertex) * ratio;
+            (point, (vertex - point) * KAPPA)
+        };
+        let (point, handle) = corner(bezier.verticies[(index + len - 1) % len]);
+        result.verticies.push(point);
+        result.in_tangent.push(Vector2D::zero());
+        result.out_tangent.push(handle);
+        let (point, handle) = corner(bezier.verticies[(index + 1) % len]);
+        result.verticies.push(point);
+        result.in_tangent.push(handle);
+        result.out_tangent.push(Vector2D::zero());
+    }
+    result
+}
+
+/// Move vertices towards the center of the shape and tangents away from it
+/// for positive `amount`, the other way around for negative ones
+fn pucker_bloat(bezier: &Bezier, amount: f32) -> Bezier {
+    if bezier.verticies.is_empty() || amount.approx_eq(&0.0) {
+        return bezier.clone();
+    }
+    let center = bezier
+        .verticies
+        .iter()
+        .fold(Vector2D::zero(), |sum, v| sum + *v)
+        / bezier.verticies.len() as f32;
+    let mut result = bezier.clone();
+    for index in 0..bezier.verticies.len() {
+        let vertex = bezier.verticies[index];
+        let moved = vertex + (center - vertex) * amount;
+        let tangent = |tangent: Vector2D| {
+            let point = vertex + tangent;
+            point + (center - point) * -amount - moved
+        };
+        result.verticies[index] = moved;
+        result.in_tangent[index] = tangent(bezier.in_tangent[index]);
+        result.out_tangent[index] = tangent(bezier.out_tangent[index]);
+    }
+    result
+}
+
+/// Rotate points by `angle` degrees around the center of the shape, offset by
+/// `center`, fading out towards the point furthest away from it
+fn twist(beziers: &[Bezier], angle: f32, center: Vector2D) -> Vec<Bezier> {
+    let mut vertices = beziers.iter().flat_map(|b| b.verticies.iter());
+    let first = match vertices.next() {
+        Some(v) if !angle.approx_eq(&0.0) => *v,
+        _ => return beziers.to_vec(),
+    };
+    let (min, max) = vertices.fold((first, first), |(min, max), v| (min.min(*v), max.max(*v)));
+    let center = (min + max) / 2.0 + center;
+    let radius = beziers
+        .iter()
+        .flat_map(|b| b.verticies.iter())
+        .map(|v| (*v - center).length())
+        .fold(0.0f32, f32::max);
+    if radius <= 0.0 {
+        return beziers.to_vec();
+    }
+    let rotate = |point: Vector2D| {
+        let offset = point - center;
+        let amount = (1.0 - offset.length() / radius).max(0.0);
+        let (sin, cos) = (angle.to_radians() * amount).sin_cos();
+        center
+            + Vector2D::new(
+                offset.x * cos - offset.y * sin,
+                offset.x * sin + offset.y * cos,
+            )
+    };
+    beziers
+        .iter()
+        // straight edges bend once their inner points are rotated
+        .map(|b| subdivided(b, 4))
+        .map(|b| {
+            let mut result = b.clone();
+            for index in 0..b.verticies.len() {
+                let vertex = b.verticies[index];
+                let rotated = rotate(vertex);
+                result.verticies[index] = rotated;
+                result.in_tangent[index] = rotate(vertex + b.in_tangent[index]) - rotated;
+                result.out_tangent[index] = rotate(vertex + b.out_tangent[index]) - rotated;
+            }
+            result
+        })
+        .collect()
+}
+
+/// Replace every segment with `frequency` ridges of `amplitude`, alternating
+/// sides of the path
+fn zig_zag(bezier: &Bezier, frequency: usize, amplitude: f32, smooth: bool) -> Bezier {
+    let count = bezier.segment_count();
+    if count == 0 || amplitude.approx_eq(&0.0) {
+        return bezier.clone();
+    }
+    // points with their direction along the path and distance to neighbours
+    let mut points = vec![];
+    let mut side = 1.0;
+    for index in 0..count {
+        let segment = bezier.segment(index);
+        let spacing = segment.approximate_length(0.1) / (frequency + 1) as f32;
+        let tangent = if index == 0 && !bezier.closed {
+            start_tangent(&segment)
+        } else {
+            let previous = bezier.segment((index + count - 1) % count);
+            normalized(
+                end_tangent(&previous) + start_tangent(&segment),
+                start_tangent(&segment),
+            )
+        };
+        points.push((segment.from.to_vector(), tangent, spacing, side));
+        side = -side;
+        // ridges are spread evenly along the segment
+        let path = SpatialPath::new(
+            segment.from.to_vector(),
+            segment.to.to_vector(),
+            (segment.ctrl1 - segment.from, segment.ctrl2 - segment.to),
+        );
+        for ridge in 1..=frequency {
+            let t = path.parameter(ridge as f32 / (frequency + 1) as f32);
+            let tangent = normalized(segment.derivative(t), start_tangent(&segment));
+            points.push((segment.sample(t).to_vector(), tangent, spacing, side));
+            side = -side;
+        }
+        if index == count - 1 && !bezier.closed {
+            points.push((segment.to.to_vector(), end_tangent(&segment), spacing, side));
+        }
+    }
+    let mut result = Bezier {
+        closed: bezier.closed,
+        ..Default::default()
+    };
+    for (point, tangent, spacing, side) in points {
+        let normal = Vector2D::new(tangent.y, -tangent.x);
+        let handle = if smooth {
+            tangent * spacing / 2.0
+        } else {
+            Vector2D::zero()
+        };
+        result.verticies.push(point + normal * amplitude * side);
+        result.in_tangent.push(-handle);
+        result.out_tangent.push(handle);
+    }
+    result
+}
+
+/// Grow the path by `amount` on the right hand side of its direction, which
+/// is outside for clockwise shapes
+fn offset(bezier: &Bezier, amount: f32, line_join: LineJoin, miter_limit: f32) -> Bezier {
+    if amount.approx_eq(&0.0) {
+        return bezier.clone();
+    }
+    let mut segments = vec![];
+    for index in 0..bezier.segment_count() {
+        let segment = bezier.segment(index);
+        if segment.from.approx_eq(&segment.to)
+            && segment.ctrl1.approx_eq(&segment.from)
+            && segment.ctrl2.approx_eq(&segment.to)
+        {
+            continue;
+        }
+        // curves are offset piecewise to keep close to the original shape
+        if segment.is_linear(0.01) {
+            segments.push((segment.from.to_vector(), offset_segment(&segment, amount)));
+        } else {
+            for piece in 0..4 {
+                let piece = segment.split_range((piece as f32 / 4.0)..((piece + 1) as f32 / 4.0));
+                segments.push((piece.from.to_vector(), offset_segment(&piece, amount)));
+            }
+        }
+    }
+    let mut result = Bezier {
+        closed: bezier.closed,
+        ..Default::default()
+    };
+    let first = match segments.first() {
+        Some((_, first)) => *first,
+        None => return bezier.clone(),
+    };
+    result.push(first.from.to_vector(), Vector2D::zero());
+    for index in 0..segments.len() {
+        let (_, segment) = segments[index];
+        result.cubic_to(segment);
+        let next = match segments.get(index + 1) {
+            Some(next) => *next,
+            None if bezier.closed => segments[0],
+            None => break,
+        };
+        join(&mut result, &segment, next, amount, line_join, miter_limit);
+    }
+    result.remove_closing_vertex();
+    result
+}
+
+fn offset_segment(segment: &CubicBezierSegment<f32>, amount: f32) -> CubicBezierSegment<f32> {
+    let normal = |tangent: Vector2D| Vector2D::new(tangent.y, -tangent.x) * amount;
+    let start = normal(start_tangent(segment));
+    let end = normal(end_tangent(segment));
+    CubicBezierSegment {
+        from: segment.from + start,
+        ctrl1: segment.ctrl1 + start,
+        ctrl2: segment.ctrl2 + end,
+        to: segment.to + end,
+    }
+}
+
+/// Connect the offset `segment` to the `next` one, around the original
+/// vertex `next.0`
+fn join(
+    result: &mut Bezier,
+    segment: &CubicBezierSegment<f32>,
+    next: (Vector2D, CubicBezierSegment<f32>),
+    amount: f32,
+    line_join: LineJoin,
+    miter_limit: f32,
+) {
+    let (vertex, next) = next;
+    let (from, to) = (segment.to.to_vector(), next.from.to_vector());
+    if from.approx_eq(&to) {
+        return;
+    }
+    let (a, b) = (end_tangent(segment), start_tangent(&next));
+    // inner corners overlap the path, a straight line is enough
+    if a.cross(b) * amount <= 0.0 {
+        result.push(to, Vector2D::zero());
+        return;
+    }
+    match line_join {
+        LineJoin::Bevel => result.push(to, Vector2D::zero()),
+        LineJoin::Round => {
+            let angle = a.angle_to(b).radians.abs();
+            let handle = 4.0 / 3.0 * (angle / 4.0).tan() * amount.abs();
+            result.cubic_to(CubicBezierSegment {
+                from: from.to_point(),
+                ctrl1: (from + a * handle).to_point(),
+                ctrl2: (to - b * handle).to_point(),
+                to: to.to_point(),
+            });
+        }
+        LineJoin::Miter => {
+            // intersection of the tangent lines from both ends
+            let distance = (to - from).cross(b) / a.cross(b);
+            let miter = from + a * distance;
+            if (miter - vertex).length() <= miter_limit * amount.abs() {
+                result.push(miter, Vector2D::zero());
+            }
+            result.push(to, Vector2D::zero());
+        }
+    }
+}
+
+/// Split every segment of `bezier` into `pieces` of the same curve
+fn subdivided(bezier: &Bezier, pieces: usize) -> Bezier {
+    let mut result = Bezier {
+        closed: bezier.closed,
+        ..Default::default()
+    };
+    match bezier.verticies.first() {
+        Some(first) => result.push(*first, bezier.in_tangent[0]),
+        None => return result,
+    }
+    for index in 0..bezier.segment_count() {
+        let segment = bezier.segment(index);
+        for piece in 0..pieces {
+            let range = (piece as f32 / pieces as f32)..((piece + 1) as f32 / pieces as f32);
+            result.cubic_to(segment.split_range(range));
+        }
+    }
+    result.remove_closing_vertex();
+    result
+}
+
+fn normalized(vector: Vector2D, fallback: Vector2D) -> Vector2D {
+    if vector.square_length() > 0.0 {
+        vector.normalize()
+    } else {
+        fallback
+    }
+}
+
+fn start_tangent(segment: &CubicBezierSegment<f32>) -> Vector2D {
+    [segment.ctrl1, segment.ctrl2, segment.to]
+        .into_iter()
+        .map(|p| p - segment.from)
+        .find(|v| !v.approx_eq(&Vector2D::zero()))
+        .map(|v| v.normalize())
+        .unwrap_or_default()
+}
+
+fn end_tangent(segment: &CubicBezierSegment<f32>) -> Vector2D {
+    [segment.ctrl2, segment.ctrl1, segment.from]
+        .into_iter()
+        .map(|p| segment.to - p)
+        .find(|v| !v.approx_eq(&Vector2D::zero()))
+        .map(|v| v.normalize())
+        .unwrap_or_default()
+}
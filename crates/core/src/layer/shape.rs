@@ -1,20 +1,37 @@
 use crate::model::*;
+use flo_curves::bezier::path::{
+    path_add, path_intersect, path_sub, PathWithIsClockwise, SimpleBezierPath,
+};
+use flo_curves::Coord2;
+use glam::Mat4;
+use lyon_algorithms::hit_test::hit_test_path;
+use lyon_algorithms::measure::{PathMeasurements, SampleType};
 use lyon_path::geom::euclid::approxeq::ApproxEq;
 use lyon_path::geom::euclid::vec2;
-use lyon_path::math::Angle;
+use lyon_path::math::{point, Angle};
 use lyon_path::path::{Builder, Path};
-use lyon_path::Winding;
+use lyon_path::{Event, FillRule, Winding};
+
+use super::modifier::modify_path;
 
 pub struct StyledShapeIter {
     shapes: Vec<ShapeLayer>,
     shape_index: usize,
     stroke_index: usize,
+    operations: Vec<PathOperation>,
 }
 
 impl StyledShapeIter {
     pub fn shape_count(&self) -> usize {
         self.shapes.len()
     }
+
+    /// Apply path `operations` from an enclosing group to every shape of this
+    /// iterator, after the trims and modifiers found in this group
+    pub fn with_operations(mut self, operations: &[PathOperation]) -> Self {
+        self.operations = operations.to_vec();
+        self
+    }
 }
 
 impl<'a> Iterator for StyledShapeIter {
@@ -31,6 +48,7 @@ impl<'a> Iterator for StyledShapeIter {
         if self.shape_index >= self.shapes.len() {
             return None;
         }
+        let current_index = self.shape_index;
         let shape = self.shapes[self.shape_index as usize].clone();
         let mut fill = None;
         let mut transform = Transform::default();
@@ -93,29 +111,43 @@ impl<'a> Iterator for StyledShapeIter {
             self.shape_index += 1;
             self.stroke_index = self.shape_index as usize;
         }
-        // collect trim info
-        let mut trims = vec![];
-        for (_index, shape) in self.shapes.iter().rev().enumerate() {
-            if let Shape::Trim(trim) = &shape.shape {
-                match trim.multiple_shape {
-                    TrimMultipleShape::Individually => trims.push(TrimInfo {
-                        trim: trim.clone(),
-                        shapes: vec![shape.shape.clone()],
-                    }),
+        // collect trims and modifiers in stack order, they only apply to shapes
+        // listed before them
+        let offset = leaf_shapes(&self.shapes[..current_index]).len();
+        let mut operations = vec![];
+        for index in (current_index + 1)..self.shapes.len() {
+            let layer = &self.shapes[index];
+            match &layer.shape {
+                Shape::Trim(trim) => match trim.multiple_shape {
+                    TrimMultipleShape::Individually => {
+                        operations.push(PathOperation::Trim(TrimInfo {
+                            trim: trim.clone(),
+                            shapes: vec![],
+                            index: 0,
+                        }))
+                    }
                     TrimMultipleShape::Simultaneously => {
-                        // TODO: implement this
-                        continue;
+                        operations.push(PathOperation::Trim(TrimInfo {
+                            trim: trim.clone(),
+                            shapes: leaf_shapes(&self.shapes[..index]),
+                            index: offset,
+                        }))
                     }
+                },
+                shape if shape.is_modifier() && !layer.hidden => {
+                    operations.push(PathOperation::Modifier(Box::new(shape.clone())))
                 }
+                _ => {}
             }
         }
+        operations.extend(self.operations.iter().map(|op| op.offset_by(offset)));
         Some(StyledShape {
             shape,
             styles: vec![],
             stroke,
             fill,
             transform,
-            trims,
+            operations,
         })
     }
 }
@@ -129,9 +161,276 @@ impl StyledShapeIterator for ShapeGroup {
         StyledShapeIter {
             shape_index: 0,
             stroke_index: 0,
-            shapes: self.shapes.clone(),
+            shapes: expand_merges(&expand_repeaters(&self.shapes)),
+            operations: vec![],
+        }
+    }
+}
+
+/// Replace every repeater in `shapes` with copies of the shapes listed before
+/// it. There are as many copies as the repeater ever has, each one in a group
+/// whose transform works out its placement and opacity at every frame.
+fn expand_repeaters(shapes: &[ShapeLayer]) -> Vec<ShapeLayer> {
+    let mut result = vec![];
+    for (index, layer) in shapes.iter().enumerate() {
+        let (copies, offset, composite, transform) = match &layer.shape {
+            Shape::Repeater {
+                copies,
+                offset,
+                composite,
+                transform,
+            } if !layer.hidden => (copies, offset, composite, transform),
+            _ => {
+                result.push(layer.clone());
+                continue;
+            }
+        };
+        // styles listed after the repeater are shared by all copies
+        let mut content = std::mem::take(&mut result);
+        content.extend(
+            shapes[(index + 1)..]
+                .iter()
+                .take_while(|layer| !matches!(layer.shape, Shape::Transform(_)))
+                .filter(|layer| layer.shape.is_style())
+                .cloned(),
+        );
+        let count = copies
+            .keyframes
+            .iter()
+            .flat_map(|k| [k.start_value, k.end_value])
+            .fold(0.0f32, f32::max)
+            .ceil() as usize;
+        for copy in 0..count {
+            let mut shapes = content.clone();
+            shapes.push(ShapeLayer {
+                name: None,
+                hidden: false,
+                shape: Shape::Transform(Transform {
+                    repeater: Some(Box::new(RepeaterCopy {
+                        copies: copies.clone(),
+                        offset: offset.clone(),
+                        transform: transform.clone(),
+                        index: copy,
+                    })),
+                    ..Transform::default()
+                }),
+            });
+            result.push(group_layer(shapes));
+        }
+        // shapes listed first are drawn on top
+        if let Composite::Above = composite {
+            result.reverse();
+        }
+    }
+    result
+}
+
+/// Replace every merge paths item in `shapes` with a single shape combining
+/// the geometry listed before it, along with the modifiers applied to it
+fn expand_merges(shapes: &[ShapeLayer]) -> Vec<ShapeLayer> {
+    let mut result: Vec<ShapeLayer> = vec![];
+    for layer in shapes {
+        match &layer.shape {
+            Shape::Merge { mode, .. } if !layer.hidden => {
+                let (sources, kept): (Vec<_>, Vec<_>) =
+                    std::mem::take(&mut result).into_iter().partition(|layer| {
+                        let shape = &layer.shape;
+                        shape.is_shape() || shape.is_group() || shape.is_modifier()
+                    });
+                result = kept;
+                result.push(ShapeLayer {
+                    shape: Shape::Merge {
+                        mode: *mode,
+                        shapes: sources,
+                    },
+                    ..layer.clone()
+                });
+            }
+            _ => result.push(layer.clone()),
+        }
+    }
+    result
+}
+
+fn group_layer(shapes: Vec<ShapeLayer>) -> ShapeLayer {
+    ShapeLayer {
+        name: None,
+        hidden: false,
+        shape: Shape::Group { shapes },
+    }
+}
+
+/// Combine the geometry of `shapes` at `frame` according to `mode`
+fn merge_path(shapes: &[ShapeLayer], mode: MergeMode, frame: f32) -> Path {
+    const ACCURACY: f64 = 0.01;
+    let paths = merge_sources(shapes, frame);
+    if let MergeMode::Normal = mode {
+        let mut builder = Path::builder();
+        for path in paths {
+            builder.extend_from_paths(&[path.as_slice()]);
         }
+        return builder.build();
+    }
+    // the bottom-most shape is the one the others are combined with
+    let mut paths = paths.iter().rev().map(flo_path);
+    let mut result = match paths.next() {
+        Some(path) => path,
+        None => return Path::new(),
+    };
+    for path in paths {
+        // flo_curves returns the other side when one of them is empty
+        result = match mode {
+            MergeMode::Subtract if result.is_empty() || path.is_empty() => result,
+            MergeMode::Intersect if result.is_empty() || path.is_empty() => vec![],
+            MergeMode::Add => path_add(&result, &path, ACCURACY),
+            MergeMode::Subtract => path_sub(&result, &path, ACCURACY),
+            MergeMode::Intersect => path_intersect(&result, &path, ACCURACY),
+            MergeMode::ExcludeIntersections => {
+                let union: Vec<SimpleBezierPath> = path_add(&result, &path, ACCURACY);
+                let intersection: Vec<SimpleBezierPath> = path_intersect(&result, &path, ACCURACY);
+                if intersection.is_empty() {
+                    union
+                } else {
+                    path_sub(&union, &intersection, ACCURACY)
+                }
+            }
+            MergeMode::Normal => unreachable!(),
+        };
     }
+    beziers_to_path(&result)
+}
+
+/// Paths of the geometry in `shapes` at `frame`, with the modifiers following
+/// it and the transforms of nested groups applied
+fn merge_sources(shapes: &[ShapeLayer], frame: f32) -> Vec<Path> {
+    let mut result = vec![];
+    for (index, layer) in shapes.iter().enumerate() {
+        if layer.hidden {
+            continue;
+        }
+        let paths = match &layer.shape {
+            Shape::Group { shapes } => {
+                let shapes = expand_merges(&expand_repeaters(shapes));
+                let matrix = shapes
+                    .iter()
+                    .find_map(|layer| match &layer.shape {
+                        Shape::Transform(transform) => Some(transform.value(frame)),
+                        _ => None,
+                    })
+                    .unwrap_or(Mat4::IDENTITY);
+                let transform = lyon_path::math::Transform::new(
+                    matrix.x_axis.x,
+                    matrix.x_axis.y,
+                    matrix.y_axis.x,
+                    matrix.y_axis.y,
+                    matrix.w_axis.x,
+                    matrix.w_axis.y,
+                );
+                merge_sources(&shapes, frame)
+                    .into_iter()
+                    .map(|path| path.transformed(&transform))
+                    .collect()
+            }
+            shape if shape.is_shape() => vec![shape.path(frame)],
+            _ => continue,
+        };
+        let modifiers = shapes[(index + 1)..]
+            .iter()
+            .filter(|layer| layer.shape.is_modifier() && !layer.hidden)
+            .map(|layer| layer.shape.clone())
+            .collect::<Vec<_>>();
+        result.extend(
+            paths
+                .into_iter()
+                .map(|path| modify_path(path, &modifiers, frame)),
+        );
+    }
+    result
+}
+
+fn flo_path(path: &Path) -> Vec<SimpleBezierPath> {
+    let coord = |p: lyon_path::math::Point| Coord2(p.x as f64, p.y as f64);
+    let mut result = vec![];
+    let mut current: Option<SimpleBezierPath> = None;
+    for event in path.iter() {
+        let segment = match event {
+            Event::Begin { at } => {
+                current = Some((coord(at), vec![]));
+                continue;
+            }
+            Event::Line { from, to } => (from.lerp(to, 1.0 / 3.0), from.lerp(to, 2.0 / 3.0), to),
+            Event::Quadratic { from, ctrl, to } => {
+                let cubic = lyon_path::geom::QuadraticBezierSegment { from, ctrl, to }.to_cubic();
+                (cubic.ctrl1, cubic.ctrl2, to)
+            }
+            Event::Cubic {
+                ctrl1, ctrl2, to, ..
+            } => (ctrl1, ctrl2, to),
+            Event::End { last, first, .. } => {
+                // flo_curves paths are always closed
+                if let Some(mut path) = current.take() {
+                    if !last.approx_eq(&first) {
+                        path.1.push((
+                            coord(last.lerp(first, 1.0 / 3.0)),
+                            coord(last.lerp(first, 2.0 / 3.0)),
+                            coord(first),
+                        ));
+                    }
+                    result.push(path);
+                }
+                continue;
+            }
+        };
+        if let Some(path) = current.as_mut() {
+            path.1
+                .push((coord(segment.0), coord(segment.1), coord(segment.2)));
+        }
+    }
+    result
+}
+
+/// Convert flo_curves paths back, winding holes the opposite way of their
+/// outlines so the result fills the same with both fill rules
+fn beziers_to_path(paths: &[SimpleBezierPath]) -> Path {
+    let point = |c: &Coord2| point(c.0 as f32, c.1 as f32);
+    let single = |path: &SimpleBezierPath| {
+        let mut builder = Path::builder();
+        builder.begin(point(&path.0));
+        for (c1, c2, to) in &path.1 {
+            builder.cubic_bezier_to(point(c1), point(c2), point(to));
+        }
+        builder.end(true);
+        builder.build()
+    };
+    let singles = paths.iter().map(single).collect::<Vec<_>>();
+    let mut builder = Path::builder();
+    for (index, path) in paths.iter().enumerate() {
+        let depth = singles
+            .iter()
+            .enumerate()
+            .filter(|(other, single)| {
+                *other != index
+                    && hit_test_path(&point(&path.0), single.iter(), FillRule::EvenOdd, 0.1)
+            })
+            .count();
+        if path.is_clockwise() == (depth % 2 == 0) {
+            builder.extend_from_paths(&[singles[index].as_slice()]);
+        } else {
+            builder.extend_from_paths(&[single(&reversed(path)).as_slice()]);
+        }
+    }
+    builder.build()
+}
+
+fn reversed(path: &SimpleBezierPath) -> SimpleBezierPath {
+    let mut from = path.0;
+    let mut segments = Vec::with_capacity(path.1.len());
+    for &(c1, c2, to) in &path.1 {
+        segments.push((c2, c1, from));
+        from = to;
+    }
+    segments.reverse();
+    (from, segments)
 }
 
 pub enum AnyFill {
@@ -175,18 +474,251 @@ impl AnyStroke {
         }
     }
 
+    pub fn miter_limit(&self) -> f32 {
+        match &self {
+            AnyStroke::Solid(s) => s.miter_limit,
+            AnyStroke::Gradient(g) => g.miter_limit,
+        }
+    }
+
     pub fn opacity(&self) -> &Animated<f32> {
         match &self {
             AnyStroke::Solid(s) => &s.opacity,
             AnyStroke::Gradient(g) => &g.opacity,
         }
     }
+
+    pub fn dashes(&self) -> &[StrokeDash] {
+        match &self {
+            AnyStroke::Solid(s) => &s.dashes,
+            AnyStroke::Gradient(g) => &g.dashes,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct TrimInfo {
     pub trim: Trim,
+    /// Shapes a simultaneous trim is distributed over, in drawing order. Empty
+    /// when the trim applies to each shape individually
     pub shapes: Vec<Shape>,
+    /// Position of the trimmed shape in `shapes`
+    pub index: usize,
+}
+
+impl TrimInfo {
+    /// Normalized ranges kept by this trim at `frame`, split in two when the
+    /// offset wraps the end of the trim around the start of the path
+    pub fn segments(&self, frame: f32) -> Vec<(f32, f32)> {
+        let start = self.trim.start.value(frame) / 100.0;
+        let end = self.trim.end.value(frame) / 100.0;
+        let (mut s, mut e) = (
+            start.min(end).clamp(0.0, 1.0),
+            start.max(end).clamp(0.0, 1.0),
+        );
+        if (e - s).approx_eq(&0.0) {
+            return vec![];
+        }
+        if (e - s).approx_eq(&1.0) {
+            return vec![(0.0, 1.0)];
+        }
+        let offset = (self.trim.offset.value(frame) / 360.0).rem_euclid(1.0);
+        s += offset;
+        e += offset;
+        if s >= 1.0 {
+            vec![(s - 1.0, e - 1.0)]
+        } else if e > 1.0 {
+            vec![(s, 1.0), (0.0, e - 1.0)]
+        } else {
+            vec![(s, e)]
+        }
+    }
+
+    /// Ranges of the trimmed shape itself kept by this trim at `frame`
+    fn local_segments(&self, frame: f32) -> Vec<(f32, f32)> {
+        let segments = self.segments(frame);
+        if self.shapes.is_empty() {
+            return segments;
+        }
+        let lengths = self
+            .shapes
+            .iter()
+            .map(|shape| path_length(&shape.path(frame)))
+            .collect::<Vec<_>>();
+        let total = lengths.iter().sum::<f32>();
+        let length = lengths.get(self.index).copied().unwrap_or(0.0);
+        if total <= 0.0 || length <= 0.0 {
+            return vec![];
+        }
+        let from = lengths[..self.index].iter().sum::<f32>() / total;
+        let to = from + length / total;
+        segments
+            .into_iter()
+            .filter_map(|(s, e)| {
+                let s = (s.max(from) - from) / (to - from);
+                let e = (e.min(to) - from) / (to - from);
+                (e > s).then_some((s, e))
+            })
+            .collect()
+    }
+
+    /// The same trim as seen by a shape `offset` shapes further in a group
+    pub(crate) fn offset_by(&self, offset: usize) -> TrimInfo {
+        let mut trim = self.clone();
+        if !trim.shapes.is_empty() {
+            trim.index += offset;
+        }
+        trim
+    }
+}
+
+/// Apply all `trims` to `path` at `frame`, in order
+pub fn trim_path(path: Path, trims: &[TrimInfo], frame: f32) -> Path {
+    let mut path = path;
+    for trim in trims {
+        if path.iter().next().is_none() {
+            break;
+        }
+        let segments = trim.local_segments(frame);
+        if segments.is_empty() {
+            return Path::new();
+        }
+        if let [(s, e)] = segments[..] {
+            if s.approx_eq(&0.0) && e.approx_eq(&1.0) {
+                continue;
+            }
+        }
+        let measures = PathMeasurements::from_path(&path, 1e-3);
+        let mut sampler = measures.create_sampler(&path, SampleType::Normalized);
+        let mut builder = Path::builder();
+        for (s, e) in segments {
+            sampler.split_range(s..e, &mut builder);
+        }
+        path = builder.build();
+    }
+    path
+}
+
+/// A trim or a path modifier applied to the shapes listed before it
+#[derive(Clone)]
+pub enum PathOperation {
+    Trim(TrimInfo),
+    Modifier(Box<Shape>),
+}
+
+impl PathOperation {
+    /// The same operation as seen by a shape `offset` shapes further in a
+    /// group
+    pub(crate) fn offset_by(&self, offset: usize) -> PathOperation {
+        match self {
+            PathOperation::Trim(trim) => PathOperation::Trim(trim.offset_by(offset)),
+            PathOperation::Modifier(shape) => PathOperation::Modifier(shape.clone()),
+        }
+    }
+}
+
+/// Apply trims and modifiers in `operations` to `path` at `frame`, in order
+pub fn apply_path_operations(path: Path, operations: &[PathOperation], frame: f32) -> Path {
+    let mut path = path;
+    for operation in operations {
+        path = match operation {
+            PathOperation::Trim(trim) => trim_path(path, std::slice::from_ref(trim), frame),
+            PathOperation::Modifier(shape) => {
+                modify_path(path, std::slice::from_ref(shape.as_ref()), frame)
+            }
+        };
+    }
+    path
+}
+
+/// Most dashes drawn along a single subpath, so that a tiny pattern on a long
+/// path can't stall rendering
+const MAX_DASHES: usize = 10000;
+
+/// Split `path` into the dashes of a stroke dash pattern at `frame`. Every
+/// subpath starts the pattern anew, shifted by the dash offset
+pub fn dash_path(path: Path, dashes: &[StrokeDash], frame: f32) -> Path {
+    let mut offset = 0.0;
+    let mut lengths = vec![];
+    for dash in dashes {
+        let length = dash.length.value(frame);
+        match dash.ty {
+            StrokeDashType::Offset => offset = length,
+            StrokeDashType::Dash | StrokeDashType::Gap => lengths.push(length.max(0.0)),
+        }
+    }
+    // an odd number of lengths is repeated, so dashes and gaps alternate
+    if lengths.len() % 2 == 1 {
+        lengths.extend(lengths.clone());
+    }
+    let total = lengths.iter().sum::<f32>();
+    if total <= 0.0 {
+        return path;
+    }
+    let mut builder = Path::builder();
+    for subpath in subpaths(&path) {
+        let measures = PathMeasurements::from_path(&subpath, 1e-3);
+        let length = measures.length();
+        let mut sampler = measures.create_sampler(&subpath, SampleType::Distance);
+        // skip the part of the pattern before the start of the subpath
+        let mut index = 0;
+        let mut start = -offset.rem_euclid(total);
+        while start + lengths[index] <= 0.0 {
+            start += lengths[index];
+            index = (index + 1) % lengths.len();
+        }
+        for _ in 0..MAX_DASHES {
+            if start > length {
+                break;
+            }
+            let end = start + lengths[index];
+            if index % 2 == 0 {
+                if end > start {
+                    sampler.split_range(start.max(0.0)..end.min(length), &mut builder);
+                } else {
+                    // empty dashes still get their caps, which draws dots
+                    let at = sampler.sample(start).position();
+                    builder.begin(at);
+                    builder.line_to(at);
+                    builder.end(false);
+                }
+            }
+            start = end;
+            index = (index + 1) % lengths.len();
+        }
+    }
+    builder.build()
+}
+
+/// Every subpath of `path` as a path of its own
+fn subpaths(path: &Path) -> Vec<Path> {
+    let mut result = vec![];
+    let mut builder = Path::builder();
+    for event in path.iter() {
+        let end = matches!(event, Event::End { .. });
+        builder.path_event(event);
+        if end {
+            result.push(std::mem::replace(&mut builder, Path::builder()).build());
+        }
+    }
+    result
+}
+
+fn path_length(path: &Path) -> f32 {
+    PathMeasurements::from_path(path, 1e-3).length()
+}
+
+/// Visible shapes of `shapes` in drawing order, with groups flattened
+fn leaf_shapes(shapes: &[ShapeLayer]) -> Vec<Shape> {
+    let mut result = vec![];
+    for layer in shapes.iter().filter(|layer| !layer.hidden) {
+        match &layer.shape {
+            Shape::Group { shapes } => result.extend(leaf_shapes(shapes)),
+            shape if shape.is_shape() => result.push(shape.clone()),
+            _ => {}
+        }
+    }
+    result
 }
 
 pub struct StyledShape {
@@ -195,7 +727,9 @@ pub struct StyledShape {
     pub stroke: Option<AnyStroke>,
     pub transform: Transform,
     pub styles: Vec<ShapeLayer>,
-    pub trims: Vec<TrimInfo>,
+    /// Trims and path modifiers to apply with [apply_path_operations], in
+    /// stack order
+    pub operations: Vec<PathOperation>,
 }
 
 impl Shape {
@@ -214,7 +748,19 @@ impl Shape {
             Shape::Rectangle { .. }
             | Shape::Ellipse { .. }
             | Shape::PolyStar { .. }
-            | Shape::Path { .. } => true,
+            | Shape::Path { .. }
+            | Shape::Merge { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_modifier(&self) -> bool {
+        match &self {
+            Shape::RoundedCorners { .. }
+            | Shape::PuckerBloat { .. }
+            | Shape::Twist { .. }
+            | Shape::ZigZag { .. }
+            | Shape::OffsetPath { .. } => true,
             _ => false,
         }
     }
@@ -233,6 +779,46 @@ pub trait PathFactory {
     fn is_animated(&self) -> bool;
 }
 
+impl PathFactory for Shape {
+    fn path(&self, frame: f32) -> Path {
+        match self {
+            Shape::Rectangle(rect) => rect.path(frame),
+            Shape::Ellipse(ellipse) => ellipse.path(frame),
+            Shape::PolyStar(star) => star.path(frame),
+            Shape::Path { d, .. } => d.value(frame).path(frame),
+            Shape::Merge { mode, shapes } => merge_path(shapes, *mode, frame),
+            _ => Path::new(),
+        }
+    }
+
+    fn is_animated(&self) -> bool {
+        match self {
+            Shape::Rectangle(rect) => rect.is_animated(),
+            Shape::Ellipse(ellipse) => ellipse.is_animated(),
+            Shape::PolyStar(star) => star.is_animated(),
+            Shape::Path { d, .. } => d.is_animated(),
+            Shape::Merge { shapes, .. } | Shape::Group { shapes } => {
+                shapes.iter().any(|layer| layer.shape.is_animated())
+            }
+            Shape::Transform(transform) => transform.is_animated(),
+            Shape::RoundedCorners { radius } => radius.is_animated(),
+            Shape::PuckerBloat { amount } => amount.is_animated(),
+            Shape::Twist { angle, center } => angle.is_animated() || center.is_animated(),
+            Shape::ZigZag {
+                frequency,
+                amplitude,
+                point_type,
+            } => frequency.is_animated() || amplitude.is_animated() || point_type.is_animated(),
+            Shape::OffsetPath {
+                amount,
+                miter_limit,
+                ..
+            } => amount.is_animated() || miter_limit.as_ref().is_some_and(|m| m.is_animated()),
+            _ => false,
+        }
+    }
+}
+
 impl PathFactory for Ellipse {
     fn path(&self, frame: f32) -> Path {
         let size = self.size.value(frame) / 2.0;
@@ -379,22 +965,59 @@ impl PathFactory for PolyStar {
 
 impl PathFactory for Rectangle {
     fn path(&self, frame: f32) -> Path {
+        /// Distance of bezier control points from a rounded corner's ends,
+        /// relative to the radius
+        const KAPPA: f32 = 0.5519;
         let mut builder = Builder::new();
-        let center = self.position.value(frame).to_point();
+        let center = self.position.value(frame);
         let size = self.size.value(frame) / 2.0;
-        let mut pts = vec![
-            center - size,
-            center + vec2(size.x, -size.y),
-            center + size,
-            center + vec2(-size.x, size.y),
-        ];
+        let radius = self.radius.value(frame).min(size.x).min(size.y).max(0.0);
+        let (l, t) = (center.x - size.x, center.y - size.y);
+        let (r, b) = (center.x + size.x, center.y + size.y);
+        // Vertices in clockwise order starting from top right like Lottie does,
+        // along with the corner rounded when going to the next vertex
+        let mut pts = if radius > 0.0 {
+            vec![
+                (point(r, t + radius), None),
+                (point(r, b - radius), Some(point(r, b))),
+                (point(r - radius, b), None),
+                (point(l + radius, b), Some(point(l, b))),
+                (point(l, b - radius), None),
+                (point(l, t + radius), Some(point(l, t))),
+                (point(l + radius, t), None),
+                (point(r - radius, t), Some(point(r, t))),
+            ]
+        } else {
+            vec![
+                (point(r, t), None),
+                (point(r, b), None),
+                (point(l, b), None),
+                (point(l, t), None),
+            ]
+        };
         if self.direction == ShapeDirection::CounterClockwise {
-            pts.reverse();
+            // Keep the starting vertex, each corner now belongs to the vertex
+            // before it
+            let corners = pts.iter().map(|(_, corner)| *corner).collect::<Vec<_>>();
+            pts[1..].reverse();
+            let len = pts.len();
+            for (index, (_, corner)) in pts.iter_mut().enumerate() {
+                *corner = corners[(len - index - 1) % len];
+            }
+        }
+        builder.begin(pts[0].0);
+        for (index, (from, corner)) in pts.iter().enumerate() {
+            let to = pts[(index + 1) % pts.len()].0;
+            match corner {
+                Some(c) => {
+                    builder.cubic_bezier_to(from.lerp(*c, KAPPA), to.lerp(*c, KAPPA), to);
+                }
+                None if index + 1 < pts.len() => {
+                    builder.line_to(to);
+                }
+                None => {}
+            }
         }
-        builder.begin(pts[0]);
-        builder.line_to(pts[1]);
-        builder.line_to(pts[2]);
-        builder.line_to(pts[3]);
         builder.end(true);
         builder.build()
     }
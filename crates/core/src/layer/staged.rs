@@ -1,12 +1,15 @@
 use crate::model::*;
+use lyon_algorithms::area::approximate_signed_area;
 
 use crate::font::FontDB;
-use crate::prelude::{Id, MaskHierarchy};
+use crate::prelude::{Id, MaskComposition, MaskHierarchy};
 use crate::Error;
 
+use super::effect::LayerEffect;
 use super::frame::{FrameTransform, FrameTransformHierarchy};
 use super::hierarchy::TransformHierarchy;
 use super::media::Media;
+use super::shape::PathFactory;
 
 #[derive(Debug, Clone)]
 pub enum RenderableContent {
@@ -37,8 +40,220 @@ impl RenderableContent {
             matte_mode: layer.matte_mode,
             mask_hierarchy: MaskHierarchy::default(),
             blend_mode: layer.blend_mode.unwrap_or(BlendMode::Normal),
+            effects: layer
+                .effects
+                .iter()
+                .filter_map(LayerEffect::from_effect)
+                .collect(),
         }
     }
+
+    /// Geometry of a layer mask, filled with the mask opacity. Expanded masks
+    /// are offset outwards, and feathered ones fade out through bands around
+    /// their edge. Like lottie-web, inverted masks cover the composition
+    /// rectangle except for the mask area
+    fn from_mask(mask: &Mask, model: &Model) -> RenderableContent {
+        let feather = mask
+            .feather
+            .as_ref()
+            .filter(|feather| feather.is_animated() || feather.initial_value() != Vector2D::zero());
+        // offsets grow paths on their right hand side, so make every subpath
+        // clockwise for it to be the outside
+        let points = match (&mask.expand, feather) {
+            (None, None) => mask.points.clone(),
+            _ => clockwise(&mask.points),
+        };
+        // Path of the mask edge, moved outwards by `feather` times `factor`
+        let edge = |factor: f32| {
+            let mut shapes = vec![Shape::Path {
+                d: points.clone(),
+                text_range: None,
+            }];
+            if let Some(expand) = &mask.expand {
+                shapes.push(offset_shape(expand.clone()));
+            }
+            if let (Some(feather), true) = (feather, factor != 0.0) {
+                let amount = feather
+                    .keyframes
+                    .iter()
+                    .map(|k| {
+                        let radius = |v: Vector2D| (v.x + v.y) / 2.0 * factor;
+                        KeyFrame {
+                            in_tangent: None,
+                            out_tangent: None,
+                            ..k.alter_value(radius(k.start_value), radius(k.end_value))
+                        }
+                    })
+                    .collect();
+                shapes.push(offset_shape(Animated {
+                    animated: feather.animated,
+                    keyframes: amount,
+                    expression: None,
+                }));
+            }
+            shapes
+        };
+        let cover = || {
+            let (width, height) = (model.width as f32, model.height as f32);
+            Shape::Path {
+                d: Animated::from_value(vec![Bezier {
+                    closed: true,
+                    verticies: vec![
+                        Vector2D::new(0.0, 0.0),
+                        Vector2D::new(width, 0.0),
+                        Vector2D::new(width, height),
+                        Vector2D::new(0.0, height),
+                    ],
+                    in_tangent: vec![Vector2D::zero(); 4],
+                    out_tangent: vec![Vector2D::zero(); 4],
+                }]),
+                text_range: None,
+            }
+        };
+        // Areas between two edges, from the inside out, along with their
+        // opacity. `None` stands for no edge at all
+        let steps = if feather.is_some() { FEATHER_STEPS } else { 0 };
+        let factor = |step: usize| match steps {
+            0 => 0.0,
+            _ => step as f32 / steps as f32 - 0.5,
+        };
+        let mut areas = vec![(None, Some(factor(0)), 1.0)];
+        for step in 1..=steps {
+            let opacity = 1.0 - step as f32 / (steps + 1) as f32;
+            areas.push((Some(factor(step - 1)), Some(factor(step)), opacity));
+        }
+        areas.push((Some(factor(steps)), None, 0.0));
+
+        let mut filled = vec![];
+        for (inner, outer, opacity) in areas {
+            let opacity = if mask.inverted {
+                1.0 - opacity
+            } else {
+                opacity
+            };
+            if opacity <= 0.0 {
+                continue;
+            }
+            let outer = match outer {
+                Some(outer) => edge(outer),
+                None => vec![cover()],
+            };
+            // both edges are merged into a single path for the even-odd fill
+            // to cover only the area between them
+            let mut area = match inner {
+                Some(inner) => vec![
+                    group_shape(edge(inner)),
+                    group_shape(outer),
+                    Shape::Merge {
+                        mode: MergeMode::Normal,
+                        shapes: vec![],
+                    },
+                ],
+                None => outer,
+            };
+            area.push(Shape::Fill(Fill {
+                opacity: Animated {
+                    animated: mask.opacity.animated,
+                    keyframes: mask
+                        .opacity
+                        .keyframes
+                        .iter()
+                        .map(|k| k.alter_value(k.start_value * opacity, k.end_value * opacity))
+                        .collect(),
+                    expression: None,
+                },
+                color: Animated::from_value(Rgb::new_u8(0, 0, 0)),
+                fill_rule: FillRule::EvenOdd,
+            }));
+            filled.push(area);
+        }
+        let mut shapes = match filled.len() {
+            1 => filled.remove(0),
+            _ => filled.into_iter().map(group_shape).collect(),
+        };
+        shapes.push(Shape::Transform(Transform::default()));
+        RenderableContent::Shape(ShapeGroup {
+            shapes: shapes
+                .into_iter()
+                .map(|shape| ShapeLayer {
+                    name: Some(mask.name.clone()),
+                    hidden: false,
+                    shape,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Number of bands the edge of a feathered mask fades out through. This only
+/// approximates a blur: the bands are offset outlines of decreasing opacity,
+/// spread from half the feather inside the mask edge to half of it outside.
+/// Renderers may blur the mask by
+/// [feather_step](super::hierarchy::MaskComposition::feather_step) to hide
+/// them
+pub(crate) const FEATHER_STEPS: usize = 8;
+
+fn offset_shape(amount: Animated<f32>) -> Shape {
+    Shape::OffsetPath {
+        amount,
+        line_join: LineJoin::Round,
+        miter_limit: None,
+    }
+}
+
+fn group_shape(mut shapes: Vec<Shape>) -> Shape {
+    shapes.push(Shape::Transform(Transform::default()));
+    Shape::Group {
+        shapes: shapes
+            .into_iter()
+            .map(|shape| ShapeLayer {
+                name: None,
+                hidden: false,
+                shape,
+            })
+            .collect(),
+    }
+}
+
+/// Reverse the subpaths of `points` running counterclockwise at their first
+/// keyframe
+fn clockwise(points: &Animated<Vec<Bezier>>) -> Animated<Vec<Bezier>> {
+    let reversed = points
+        .initial_value()
+        .iter()
+        .map(|bezier| {
+            let path = vec![bezier.clone()].path(0.0);
+            approximate_signed_area(0.1, path.iter()) < 0.0
+        })
+        .collect::<Vec<_>>();
+    let fix = |beziers: &Vec<Bezier>| {
+        beziers
+            .iter()
+            .zip(reversed.iter().chain(std::iter::repeat(&false)))
+            .map(|(bezier, reversed)| {
+                if !reversed {
+                    return bezier.clone();
+                }
+                // vertex order flips, with incoming and outgoing tangents
+                // trading places
+                Bezier {
+                    closed: bezier.closed,
+                    verticies: bezier.verticies.iter().rev().cloned().collect(),
+                    in_tangent: bezier.out_tangent.iter().rev().cloned().collect(),
+                    out_tangent: bezier.in_tangent.iter().rev().cloned().collect(),
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    Animated {
+        animated: points.animated,
+        keyframes: points
+            .keyframes
+            .iter()
+            .map(|k| k.alter_value(fix(&k.start_value), fix(&k.end_value)))
+            .collect(),
+        expression: None,
+    }
 }
 
 #[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Debug)]
@@ -49,9 +264,10 @@ pub enum TargetRef {
 
 pub(crate) enum ContentInfo {
     Simple(RenderableContent),
+    /// Any other content, masked by the layer's `masksProperties`
     ContentWithMasks {
-        content: RenderableContent,
-        masks: Vec<(RenderableContent, MatteMode)>,
+        content: Box<ContentInfo>,
+        masks: Vec<(RenderableContent, MaskComposition)>,
     },
     TextKeyframes(Vec<TextKeyframe>),
 }
@@ -85,6 +301,18 @@ pub struct StagedLayer {
     pub matte_mode: Option<MatteMode>,
     pub mask_hierarchy: MaskHierarchy,
     pub blend_mode: BlendMode,
+    /// Effects applied to the rendered layer, in order
+    pub effects: Vec<LayerEffect>,
+}
+
+impl StagedLayer {
+    /// Whether the content of this precomposition or media layer is drawn on
+    /// its own first, and then composited as a whole with the layer effects
+    /// and blend mode
+    pub fn is_composited(&self) -> bool {
+        matches!(self.content, RenderableContent::Group)
+            && (!self.effects.is_empty() || self.blend_mode != BlendMode::Normal)
+    }
 }
 
 impl ContentInfo {
@@ -96,61 +324,7 @@ impl ContentInfo {
     ) -> Result<ContentInfo, Error> {
         let content = match layer.content.clone() {
             LayerContent::Shape(shape_group) => {
-                let mut result = vec![];
-                let content = RenderableContent::Shape(shape_group);
-                if layer.has_mask {
-                    for mask in &layer.masks_properties {
-                        let mut opacity = mask.opacity.clone();
-                        for keyframe in &mut opacity.keyframes {
-                            keyframe.start_value /= 100.0;
-                            keyframe.end_value /= 100.0;
-                        }
-                        let content = RenderableContent::Shape(ShapeGroup {
-                            shapes: vec![
-                                ShapeLayer {
-                                    name: Some(mask.name.clone()),
-                                    hidden: false,
-                                    shape: Shape::Path {
-                                        d: mask.points.clone(),
-                                        text_range: None,
-                                    },
-                                },
-                                ShapeLayer {
-                                    name: None,
-                                    hidden: false,
-                                    shape: Shape::Fill(Fill {
-                                        opacity,
-                                        color: Animated {
-                                            animated: false,
-                                            keyframes: vec![KeyFrame::from_value(Rgb::new_u8(
-                                                0, 0, 0,
-                                            ))],
-                                        },
-                                        fill_rule: FillRule::EvenOdd,
-                                    }),
-                                },
-                                ShapeLayer {
-                                    name: None,
-                                    hidden: false,
-                                    shape: Shape::Transform(Transform::default()),
-                                },
-                            ],
-                        });
-                        let matte_mode = match mask.mode {
-                            MaskMode::Add => MatteMode::Alpha,
-                            MaskMode::Subtract => MatteMode::InvertedAlpha,
-                            MaskMode::None => MatteMode::Normal,
-                            _ => unimplemented!(),
-                        };
-                        result.push((content, matte_mode));
-                    }
-                    ContentInfo::ContentWithMasks {
-                        content,
-                        masks: result,
-                    }
-                } else {
-                    ContentInfo::Simple(content)
-                }
+                ContentInfo::Simple(RenderableContent::Shape(shape_group))
             }
             LayerContent::PreCompositionRef(_)
             | LayerContent::Empty
@@ -199,6 +373,27 @@ impl ContentInfo {
             )),
             _ => todo!(),
         };
-        Ok(content)
+        let masks = layer
+            .masks_properties
+            .iter()
+            .filter(|mask| layer.has_mask && mask.mode != MaskMode::None)
+            .enumerate()
+            .map(|(index, mask)| {
+                let composition = MaskComposition {
+                    mode: mask.mode,
+                    first: index == 0,
+                    feather: mask.feather.clone(),
+                };
+                (RenderableContent::from_mask(mask, model), composition)
+            })
+            .collect::<Vec<_>>();
+        if masks.is_empty() {
+            Ok(content)
+        } else {
+            Ok(ContentInfo::ContentWithMasks {
+                content: Box::new(content),
+                masks,
+            })
+        }
     }
 }
@@ -33,6 +33,7 @@ impl RenderableContent {
         Ok(Animated {
             animated: true,
             keyframes,
+            expression: None,
         })
     }
 }
@@ -154,10 +155,12 @@ impl<'a> TextDocumentParser<'a> {
                         opacity: Animated {
                             animated: false,
                             keyframes: vec![fill_opacity],
+                            expression: None,
                         },
                         color: Animated {
                             animated: false,
                             keyframes: vec![fill],
+                            expression: None,
                         },
                         fill_rule: FillRule::NonZero,
                     }),
@@ -242,10 +245,11 @@ impl<'a> TextDocumentParser<'a> {
                         } = data;
 
                         let mut transform = Transform::default();
-                        transform.position = Some(Animated {
+                        transform.position = Some(Position::Combined(Animated {
                             animated: false,
                             keyframes: vec![KeyFrame::from_value(Vector2D::new(offset_x, 0.0))],
-                        });
+                            expression: None,
+                        }));
                         let text_range = if self.text_ranges.is_empty() {
                             None
                         } else {
@@ -270,6 +274,7 @@ impl<'a> TextDocumentParser<'a> {
                                                 keyframes: vec![self
                                                     .keyframe
                                                     .alter_value(beziers.clone(), beziers)],
+                                                expression: None,
                                             },
                                             text_range,
                                         },
@@ -289,10 +294,11 @@ impl<'a> TextDocumentParser<'a> {
                 let shift = Vector2D::new(0.0, start_shift_y + line_y);
                 let transform_position = self.keyframe.alter_value(shift, shift);
                 let mut transform = Transform::default();
-                transform.position = Some(Animated {
+                transform.position = Some(Position::Combined(Animated {
                     animated: false,
                     keyframes: vec![transform_position],
-                });
+                    expression: None,
+                }));
 
                 glyphs.push(ShapeLayer {
                     name: None,
@@ -1,8 +1,33 @@
 use crate::model::{Bezier, GradientColor, Rgb, Rgba, Vector2D};
 
+/// Spatial tangents `(to, ti)` of a keyframe, relative to its start and end
+/// value respectively
+pub type SpatialTangents = (Vector2D, Vector2D);
+
+/// Number of chords used to approximate the length of a [SpatialPath]
+const SPATIAL_SAMPLES: usize = 64;
+
 pub trait Lerp {
     type Target;
     fn lerp(&self, other: &Self, t: f32) -> Self::Target;
+
+    /// Like [Lerp::lerp], but moves along the motion path bent by `tangents`,
+    /// `t` being the ratio of the path length covered. Only positions have a
+    /// motion path, other values ignore the tangents
+    fn lerp_spatial(&self, other: &Self, t: f32, _tangents: SpatialTangents) -> Self::Target {
+        self.lerp(other, t)
+    }
+
+    /// Tangents of the two halves of the motion path when splitting it at
+    /// length ratio `t`
+    fn split_spatial(
+        &self,
+        _other: &Self,
+        _t: f32,
+        tangents: SpatialTangents,
+    ) -> (SpatialTangents, SpatialTangents) {
+        (tangents, tangents)
+    }
 }
 
 impl Lerp for Vector2D {
@@ -11,6 +36,96 @@ impl Lerp for Vector2D {
     fn lerp(&self, other: &Self, t: f32) -> Self::Target {
         (*self - *other) * t + other
     }
+
+    fn lerp_spatial(&self, other: &Self, t: f32, tangents: SpatialTangents) -> Self::Target {
+        let path = SpatialPath::new(*other, *self, tangents);
+        path.point(path.parameter(t))
+    }
+
+    fn split_spatial(
+        &self,
+        other: &Self,
+        t: f32,
+        tangents: SpatialTangents,
+    ) -> (SpatialTangents, SpatialTangents) {
+        let path = SpatialPath::new(*other, *self, tangents);
+        let (a, b) = path.split(path.parameter(t));
+        (a.tangents(), b.tangents())
+    }
+}
+
+/// Cubic bezier motion path of a position keyframe
+pub(crate) struct SpatialPath {
+    points: [Vector2D; 4],
+}
+
+impl SpatialPath {
+    pub fn new(start: Vector2D, end: Vector2D, tangents: SpatialTangents) -> Self {
+        SpatialPath {
+            points: [start, start + tangents.0, end + tangents.1, end],
+        }
+    }
+
+    pub fn point(&self, u: f32) -> Vector2D {
+        let [p0, p1, p2, p3] = self.points;
+        let v = 1.0 - u;
+        p0 * (v * v * v) + p1 * (3.0 * v * v * u) + p2 * (3.0 * v * u * u) + p3 * (u * u * u)
+    }
+
+    pub fn derivative(&self, u: f32) -> Vector2D {
+        let [p0, p1, p2, p3] = self.points;
+        let v = 1.0 - u;
+        (p1 - p0) * (3.0 * v * v) + (p2 - p1) * (6.0 * v * u) + (p3 - p2) * (3.0 * u * u)
+    }
+
+    /// Bezier parameter of the point that covers `ratio` of the path length
+    pub fn parameter(&self, ratio: f32) -> f32 {
+        let mut lengths = [0.0; SPATIAL_SAMPLES + 1];
+        let mut prev = self.points[0];
+        for i in 1..=SPATIAL_SAMPLES {
+            let point = self.point(i as f32 / SPATIAL_SAMPLES as f32);
+            lengths[i] = lengths[i - 1] + (point - prev).length();
+            prev = point;
+        }
+        let total = lengths[SPATIAL_SAMPLES];
+        if total <= f32::EPSILON {
+            return ratio;
+        }
+        let target = ratio.clamp(0.0, 1.0) * total;
+        let index = lengths
+            .iter()
+            .skip(1)
+            .position(|length| *length >= target)
+            .unwrap_or(SPATIAL_SAMPLES - 1);
+        let segment = lengths[index + 1] - lengths[index];
+        let fraction = if segment > 0.0 {
+            (target - lengths[index]) / segment
+        } else {
+            0.0
+        };
+        (index as f32 + fraction) / SPATIAL_SAMPLES as f32
+    }
+
+    pub fn split(&self, u: f32) -> (SpatialPath, SpatialPath) {
+        let [p0, p1, p2, p3] = self.points;
+        let mix = |a: Vector2D, b: Vector2D| a + (b - a) * u;
+        let (q0, q1, q2) = (mix(p0, p1), mix(p1, p2), mix(p2, p3));
+        let (r0, r1) = (mix(q0, q1), mix(q1, q2));
+        let mid = mix(r0, r1);
+        (
+            SpatialPath {
+                points: [p0, q0, r0, mid],
+            },
+            SpatialPath {
+                points: [mid, r1, q2, p3],
+            },
+        )
+    }
+
+    pub fn tangents(&self) -> SpatialTangents {
+        let [p0, p1, p2, p3] = self.points;
+        (p1 - p0, p2 - p3)
+    }
 }
 
 impl Lerp for f32 {
@@ -28,13 +143,13 @@ impl Lerp for Vec<Bezier> {
         let mut result = self.clone();
         for (bezier, other) in result.iter_mut().zip(other.iter()) {
             for (v, other_v) in bezier.verticies.iter_mut().zip(other.verticies.iter()) {
-                *v = v.lerp(*other_v, t);
+                *v = Lerp::lerp(v, other_v, t);
             }
             for (v, other_v) in bezier.in_tangent.iter_mut().zip(other.in_tangent.iter()) {
-                *v = v.lerp(*other_v, t);
+                *v = Lerp::lerp(v, other_v, t);
             }
             for (v, other_v) in bezier.out_tangent.iter_mut().zip(other.out_tangent.iter()) {
-                *v = v.lerp(*other_v, t);
+                *v = Lerp::lerp(v, other_v, t);
             }
         }
         result
@@ -52,10 +167,26 @@ impl Lerp for Rgb {
     }
 }
 
+impl Lerp for Rgba {
+    type Target = Rgba;
+
+    fn lerp(&self, other: &Self, t: f32) -> Self::Target {
+        let r = other.r as f32 + (self.r as f32 - other.r as f32) * t;
+        let g = other.g as f32 + (self.g as f32 - other.g as f32) * t;
+        let b = other.b as f32 + (self.b as f32 - other.b as f32) * t;
+        let a = other.a as f32 + (self.a as f32 - other.a as f32) * t;
+        Rgba::new_u8(r as u8, g as u8, b as u8, a as u8)
+    }
+}
+
 impl Lerp for Vec<GradientColor> {
     type Target = Vec<GradientColor>;
 
     fn lerp(&self, other: &Self, t: f32) -> Self::Target {
+        if self.len() != other.len() {
+            let (stops, other) = GradientColor::align(self, other);
+            return stops.lerp(&other, t);
+        }
         self.iter()
             .zip(other)
             .map(|(x, y)| {
@@ -1,4 +1,5 @@
 use std::io::Read;
+use std::ops::Range;
 
 use crate::model::Model;
 pub use error::Error;
@@ -10,6 +11,7 @@ pub use renderer::*;
 use timeline::Timeline;
 
 mod error;
+mod expression;
 mod font;
 mod layer;
 mod lerp;
@@ -18,10 +20,14 @@ mod renderer;
 mod timeline;
 
 pub mod prelude {
+    pub use crate::expression::{Expression, ExpressionValue};
+    pub use crate::layer::effect::{apply_effects, LayerEffect};
     pub use crate::layer::frame::*;
     pub use crate::layer::hierarchy::*;
+    pub use crate::layer::modifier::modify_path;
     pub use crate::layer::shape::{
-        AnyFill, AnyStroke, PathFactory, StyledShape, StyledShapeIterator, TrimInfo,
+        apply_path_operations, dash_path, trim_path, AnyFill, AnyStroke, PathFactory,
+        PathOperation, StyledShape, StyledShapeIterator, TrimInfo,
     };
     pub use crate::layer::staged::{RenderableContent, StagedLayer};
     pub use crate::model::*;
@@ -40,6 +46,12 @@ impl Lottie {
     /// management, and a root path.Root path will be used to resolve relative
     /// paths of media files in this lottie model
     pub fn new(model: Model, fontkit: FontKit, root_path: &str) -> Result<Self, Error> {
+        #[cfg(feature = "expressions")]
+        let model = {
+            let mut model = model;
+            model.bind_expressions();
+            model
+        };
         let mut fontdb = FontDB::new(fontkit);
         fontdb.load_fonts_from_model(&model)?;
 
@@ -70,4 +82,23 @@ impl Lottie {
     pub fn fontdb(&self) -> &FontDB {
         &self.fontdb
     }
+
+    /// Frames of the segment starting at the marker named `name`. Markers
+    /// without a duration last until the next marker, or the end of the
+    /// animation
+    pub fn segment(&self, name: &str) -> Option<Range<f32>> {
+        let marker = self.model.marker(name)?;
+        let end_frame = if marker.duration > 0.0 {
+            marker.end_frame()
+        } else {
+            self.model
+                .markers
+                .iter()
+                .map(|m| m.start_frame)
+                .filter(|frame| *frame > marker.start_frame)
+                .fold(self.model.end_frame, f32::min)
+        };
+        let start_frame = marker.start_frame.max(self.model.start_frame);
+        Some(start_frame..end_frame.min(self.model.end_frame))
+    }
 }
@@ -15,9 +15,9 @@ use helpers::*;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Model {
-    #[serde(rename = "nm")]
+    #[serde(rename = "nm", skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
-    #[serde(rename = "v", default)]
+    #[serde(rename = "v", default, skip_serializing_if = "Option::is_none")]
     version: Option<String>,
     #[serde(rename = "ip")]
     pub start_frame: f32,
@@ -25,15 +25,21 @@ pub struct Model {
     pub end_frame: f32,
     #[serde(rename = "fr")]
     pub frame_rate: f32,
-    #[serde(rename = "w")]
+    #[serde(rename = "w", deserialize_with = "u32_from_number")]
     pub width: u32,
-    #[serde(rename = "h")]
+    #[serde(rename = "h", deserialize_with = "u32_from_number")]
     pub height: u32,
     pub layers: Vec<Layer>,
     #[serde(default)]
     pub assets: Vec<Asset>,
     #[serde(default)]
     pub fonts: FontList,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<Marker>,
+    /// Compositions the expressions of the model are bound to
+    #[cfg(feature = "expressions")]
+    #[serde(skip)]
+    pub(crate) expression_scopes: Vec<std::sync::Arc<crate::expression::CompScope>>,
 }
 
 impl Model {
@@ -41,6 +47,14 @@ impl Model {
         serde_json::from_reader(r)
     }
 
+    pub fn to_writer<W: std::io::Write>(&self, w: W) -> Result<(), serde_json::Error> {
+        serde_json::to_writer(w, self)
+    }
+
+    pub fn to_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
     pub fn duration(&self) -> f32 {
         (self.end_frame - self.start_frame) as f32 / self.frame_rate as f32
     }
@@ -48,6 +62,29 @@ impl Model {
     pub fn font(&self, name: &str) -> Option<&Font> {
         self.fonts.list.iter().find(|f| f.name == name)
     }
+
+    pub fn marker(&self, name: &str) -> Option<&Marker> {
+        self.markers.iter().find(|m| m.name == name)
+    }
+}
+
+/// A named point or range of the timeline, usually marking a segment to be
+/// played on its own
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Marker {
+    #[serde(rename = "cm")]
+    pub name: String,
+    #[serde(rename = "tm")]
+    pub start_frame: f32,
+    /// Duration in frames, `0` for markers of a single point
+    #[serde(rename = "dr", default)]
+    pub duration: f32,
+}
+
+impl Marker {
+    pub fn end_frame(&self) -> f32 {
+        self.start_frame + self.duration
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -61,9 +98,9 @@ pub struct Layer {
     is_3d: bool,
     #[serde(rename = "hd", default)]
     pub hidden: bool,
-    #[serde(rename = "ind", default)]
+    #[serde(rename = "ind", default, skip_serializing_if = "Option::is_none")]
     pub index: Option<u32>,
-    #[serde(rename = "parent", default)]
+    #[serde(rename = "parent", default, skip_serializing_if = "Option::is_none")]
     pub parent_index: Option<u32>,
     #[serde(skip)]
     pub id: u32,
@@ -80,20 +117,33 @@ pub struct Layer {
     pub end_frame: f32,
     #[serde(rename = "st")]
     pub start_time: f32,
-    #[serde(rename = "nm")]
+    #[serde(rename = "nm", skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
-    #[serde(rename = "ks", default)]
+    #[serde(rename = "ks", default, skip_serializing_if = "Option::is_none")]
     pub transform: Option<Transform>,
     #[serde(flatten)]
     pub content: LayerContent,
-    #[serde(rename = "tt", default)]
+    #[serde(rename = "tt", default, skip_serializing_if = "Option::is_none")]
     pub matte_mode: Option<MatteMode>,
-    #[serde(rename = "bm", default)]
+    /// Index of the layer used as track matte, the layer above otherwise
+    #[serde(rename = "tp", default, skip_serializing_if = "Option::is_none")]
+    pub matte_parent: Option<u32>,
+    /// Whether this layer is only used as a track matte
+    #[serde(
+        rename = "td",
+        deserialize_with = "bool_from_int",
+        serialize_with = "int_from_bool",
+        default
+    )]
+    pub is_track_matte: bool,
+    #[serde(rename = "bm", default, skip_serializing_if = "Option::is_none")]
     pub blend_mode: Option<BlendMode>,
     #[serde(default, rename = "hasMask")]
     pub has_mask: bool,
     #[serde(default, rename = "masksProperties")]
     pub masks_properties: Vec<Mask>,
+    #[serde(rename = "ef", default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: Vec<Effect>,
 }
 
 impl Layer {
@@ -120,9 +170,12 @@ impl Layer {
             transform: None,
             content,
             matte_mode: None,
+            matte_parent: None,
+            is_track_matte: false,
             blend_mode: None,
             has_mask: false,
             masks_properties: vec![],
+            effects: vec![],
         }
     }
 }
@@ -146,6 +199,9 @@ pub enum LayerContent {
 pub struct MediaRef {
     #[serde(rename = "refId")]
     pub ref_id: String,
+    /// Whether this is an audio layer (`ty` 6) rather than an image layer
+    #[serde(skip)]
+    pub audio: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -156,16 +212,16 @@ pub struct PreCompositionRef {
     width: u32,
     #[serde(rename = "h")]
     height: u32,
-    #[serde(rename = "tm")]
+    #[serde(rename = "tm", skip_serializing_if = "Option::is_none")]
     pub time_remapping: Option<Animated<f32>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Transform {
-    #[serde(rename = "a", default)]
+    #[serde(rename = "a", default, skip_serializing_if = "Option::is_none")]
     pub anchor: Option<Animated<Vector2D>>,
-    #[serde(rename = "p", default)]
-    pub position: Option<Animated<Vector2D>>,
+    #[serde(rename = "p", default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<Position>,
     #[serde(rename = "s", default = "default_vec2_100")]
     pub scale: Animated<Vector2D>,
     #[serde(rename = "r", default)]
@@ -174,10 +230,14 @@ pub struct Transform {
     pub auto_orient: bool,
     #[serde(rename = "o", default = "default_number_100")]
     pub opacity: Animated<f32>,
-    #[serde(rename = "sk", default)]
+    #[serde(rename = "sk", default, skip_serializing_if = "Option::is_none")]
     pub skew: Option<Animated<f32>>,
-    #[serde(rename = "sa", default)]
+    #[serde(rename = "sa", default, skip_serializing_if = "Option::is_none")]
     pub skew_axis: Option<Animated<f32>>,
+    /// Set on the transform of a copy made by a repeater, which replaces the
+    /// other properties
+    #[serde(skip)]
+    pub repeater: Option<Box<RepeaterCopy>>,
 }
 
 impl Default for Transform {
@@ -191,6 +251,7 @@ impl Default for Transform {
             skew: Default::default(),
             skew_axis: Default::default(),
             auto_orient: false,
+            repeater: None,
         }
     }
 }
@@ -207,17 +268,15 @@ impl Transform {
             .as_ref()
             .and_then(|a| Some(a.keyframes.last()?.end_frame))
             .unwrap_or(0.0);
-        let pos_frames = self
-            .position
-            .as_ref()
-            .and_then(|a| Some(a.keyframes.last()?.end_frame))
-            .unwrap_or(0.0);
+        let pos_frames = self.position.as_ref().map(|p| p.frames()).unwrap_or(0.0);
         let scale_frames = self.scale.keyframes.last().unwrap().end_frame;
         let rotation_frames = self.rotation.keyframes.last().unwrap().end_frame;
+        let repeater_frames = self.repeater.as_ref().map(|r| r.frames()).unwrap_or(0.0);
         anchor_frames
             .max(pos_frames)
             .max(scale_frames)
             .max(rotation_frames)
+            .max(repeater_frames)
     }
 
     pub fn initial_value(&self) -> Mat4 {
@@ -225,21 +284,13 @@ impl Transform {
     }
 
     pub fn value(&self, frame: f32) -> Mat4 {
+        if let Some(repeater) = self.repeater.as_ref() {
+            return repeater.value(frame);
+        }
         let mut angle = 0.0;
         if let Some(position) = self.position.as_ref() {
             if self.auto_orient && position.is_animated() {
-                let len = position.keyframes.len() - 1;
-                let mut frame = position.keyframes[0].start_frame.max(frame);
-                frame = position.keyframes[len].start_frame.min(frame);
-                if let Some(keyframe) = position
-                    .keyframes
-                    .iter()
-                    .find(|keyframe| frame >= keyframe.start_frame && frame < keyframe.end_frame)
-                {
-                    angle = (keyframe.end_value - keyframe.start_value)
-                        .angle_from_x_axis()
-                        .to_degrees();
-                }
+                angle = position.direction(frame).angle_from_x_axis().to_degrees();
             }
         }
         let anchor = self
@@ -276,27 +327,224 @@ impl Transform {
                 .unwrap_or(false)
             || self.scale.is_animated()
             || self.rotation.is_animated()
+            || self.repeater.as_ref().is_some_and(|r| r.is_animated())
+    }
+
+    /// Opacity in percent at `frame`
+    pub fn opacity_value(&self, frame: f32) -> f32 {
+        match self.repeater.as_ref() {
+            Some(repeater) => repeater.opacity(frame),
+            None => self.opacity.value(frame),
+        }
+    }
+
+    pub fn is_opacity_animated(&self) -> bool {
+        match self.repeater.as_ref() {
+            Some(repeater) => repeater.is_animated(),
+            None => self.opacity.is_animated(),
+        }
+    }
+}
+
+/// Transform position, either animated as a whole or as separately animated
+/// x and y components
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Position {
+    Split(SplitPosition),
+    Combined(Animated<Vector2D>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SplitPosition {
+    #[serde(rename = "s")]
+    split: bool,
+    pub x: Animated<f32>,
+    pub y: Animated<f32>,
+}
+
+impl Position {
+    pub fn initial_value(&self) -> Vector2D {
+        self.value(0.0)
+    }
+
+    pub fn value(&self, frame: f32) -> Vector2D {
+        match self {
+            Position::Split(p) => Vector2D::new(p.x.value(frame), p.y.value(frame)),
+            Position::Combined(p) => p.value(frame),
+        }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        match self {
+            Position::Split(p) => p.x.is_animated() || p.y.is_animated(),
+            Position::Combined(p) => p.is_animated(),
+        }
+    }
+
+    pub fn frames(&self) -> f32 {
+        let end_frame = match self {
+            Position::Split(p) => {
+                p.x.keyframes
+                    .last()
+                    .zip(p.y.keyframes.last())
+                    .map(|(x, y)| x.end_frame.max(y.end_frame))
+            }
+            Position::Combined(p) => p.keyframes.last().map(|keyframe| keyframe.end_frame),
+        };
+        end_frame.unwrap_or(0.0)
+    }
+
+    /// Direction of motion at `frame`, used to auto-orient layers
+    fn direction(&self, frame: f32) -> Vector2D {
+        let position = match self {
+            Position::Split(_) => {
+                // Split components have no spatial tangents to follow
+                return self.value(frame + 0.1) - self.value(frame);
+            }
+            Position::Combined(p) => p,
+        };
+        let len = position.keyframes.len() - 1;
+        let mut frame = position.keyframes[0].start_frame.max(frame);
+        frame = position.keyframes[len].start_frame.min(frame);
+        match position
+            .keyframes
+            .iter()
+            .find(|keyframe| frame >= keyframe.start_frame && frame < keyframe.end_frame)
+        {
+            Some(keyframe) => {
+                let t =
+                    (frame - keyframe.start_frame) / (keyframe.end_frame - keyframe.start_frame);
+                keyframe.direction(t)
+            }
+            None => Vector2D::zero(),
+        }
+    }
+}
+
+impl From<Animated<Vector2D>> for Position {
+    fn from(position: Animated<Vector2D>) -> Self {
+        Position::Combined(position)
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RepeaterTransform {
     #[serde(rename = "a", default)]
-    anchor: Animated<Vector2D>,
+    pub anchor: Animated<Vector2D>,
     #[serde(rename = "p")]
-    position: Animated<Vector2D>,
+    pub position: Animated<Vector2D>,
     #[serde(rename = "s")]
-    scale: Animated<Vector2D>,
+    pub scale: Animated<Vector2D>,
     #[serde(rename = "r")]
-    rotation: Animated<f32>,
+    pub rotation: Animated<f32>,
     #[serde(rename = "so")]
-    start_opacity: Animated<f32>,
+    pub start_opacity: Animated<f32>,
     #[serde(rename = "eo")]
-    end_opacity: Animated<f32>,
-    #[serde(rename = "sk", default)]
-    skew: Option<Animated<Vector2D>>,
-    #[serde(rename = "sa", default)]
-    skew_axis: Option<Animated<Vector2D>>,
+    pub end_opacity: Animated<f32>,
+    #[serde(rename = "sk", default, skip_serializing_if = "Option::is_none")]
+    pub skew: Option<Animated<f32>>,
+    #[serde(rename = "sa", default, skip_serializing_if = "Option::is_none")]
+    pub skew_axis: Option<Animated<f32>>,
+}
+
+/// The `index`th copy of the shapes before a repeater. The copy is
+/// transformed `index + offset` times by the repeater transform, where the
+/// copy count and the offset can be animated and the offset can be fractional
+/// or negative
+#[derive(Debug, Clone)]
+pub struct RepeaterCopy {
+    pub copies: Animated<f32>,
+    pub offset: Animated<f32>,
+    pub transform: RepeaterTransform,
+    pub index: usize,
+}
+
+impl RepeaterCopy {
+    pub fn value(&self, frame: f32) -> Mat4 {
+        let steps = self.index as f32 + self.offset.value(frame);
+        let anchor = self.transform.anchor.value(frame);
+        let position = self.transform.position.value(frame) * steps;
+        let rotation = self.transform.rotation.value(frame) * steps;
+        let scale = self.transform.scale.value(frame) / 100.0;
+        let scale = Vec3::new(
+            repeated_scale(scale.x, steps),
+            repeated_scale(scale.y, steps),
+            1.0,
+        );
+        let anchor = Vec3::new(anchor.x, anchor.y, 0.0);
+        // Like lottie-web, positions add up while rotation and scale are
+        // applied around the anchor
+        Mat4::from_translation(Vec3::new(position.x, position.y, 0.0))
+            * Mat4::from_translation(anchor)
+            * Mat4::from_scale(scale)
+            * Mat4::from_rotation_z(rotation.to_radians())
+            * Mat4::from_translation(-anchor)
+    }
+
+    /// Opacity in percent at `frame`, going from the start opacity of the
+    /// first copy to the end opacity of the last one. Copies beyond the
+    /// current number of copies are hidden
+    pub fn opacity(&self, frame: f32) -> f32 {
+        let count = self.copies.value(frame).ceil();
+        if self.index as f32 >= count {
+            return 0.0;
+        }
+        let start = self.transform.start_opacity.value(frame);
+        let end = self.transform.end_opacity.value(frame);
+        if count > 1.0 {
+            start + (end - start) * self.index as f32 / (count - 1.0)
+        } else {
+            start
+        }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.copies.is_animated()
+            || self.offset.is_animated()
+            || self.transform.anchor.is_animated()
+            || self.transform.position.is_animated()
+            || self.transform.scale.is_animated()
+            || self.transform.rotation.is_animated()
+            || self.transform.start_opacity.is_animated()
+            || self.transform.end_opacity.is_animated()
+    }
+
+    pub fn frames(&self) -> f32 {
+        let transform = &self.transform;
+        let scalars = [
+            &self.copies,
+            &self.offset,
+            &transform.rotation,
+            &transform.start_opacity,
+            &transform.end_opacity,
+        ];
+        let vectors = [&transform.anchor, &transform.position, &transform.scale];
+        scalars
+            .iter()
+            .flat_map(|a| a.keyframes.last())
+            .map(|k| k.end_frame)
+            .chain(
+                vectors
+                    .iter()
+                    .flat_map(|a| a.keyframes.last())
+                    .map(|k| k.end_frame),
+            )
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Scale after `steps` repetitions of `scale`, where a fractional step scales
+/// linearly and negative steps undo the scale
+fn repeated_scale(scale: f32, steps: f32) -> f32 {
+    let scale = if scale == 0.0 { f32::EPSILON } else { scale };
+    let whole = steps.trunc();
+    let value = scale.powf(whole.abs()) * (1.0 + (scale - 1.0) * (steps - whole).abs());
+    if steps < 0.0 {
+        1.0 / value
+    } else {
+        value
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -306,7 +554,7 @@ pub struct FontList {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Font {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     ascent: Option<f32>,
     #[serde(rename = "fFamily")]
     pub family: String,
@@ -314,19 +562,19 @@ pub struct Font {
     pub name: String,
     #[serde(rename = "fStyle")]
     style: String,
-    #[serde(rename = "fPath", default)]
+    #[serde(rename = "fPath", default, skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
-    #[serde(rename = "fWeight")]
+    #[serde(rename = "fWeight", skip_serializing_if = "Option::is_none")]
     weight: Option<String>,
     #[serde(default)]
     pub origin: FontPathOrigin,
-    #[serde(rename = "fClass", default)]
+    #[serde(rename = "fClass", default, skip_serializing_if = "Option::is_none")]
     class: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ShapeLayer {
-    #[serde(rename = "nm", default)]
+    #[serde(rename = "nm", default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     #[serde(rename = "hd", default)]
     pub hidden: bool,
@@ -407,6 +655,9 @@ pub enum Shape {
     Merge {
         #[serde(rename = "mm")]
         mode: MergeMode,
+        /// Shapes listed before this item, combined into a single path
+        #[serde(skip)]
+        shapes: Vec<ShapeLayer>,
     },
     #[serde(rename = "op")]
     OffsetPath {
@@ -414,17 +665,19 @@ pub enum Shape {
         amount: Animated<f32>,
         #[serde(rename = "lj")]
         line_join: LineJoin,
-        #[serde(rename = "ml")]
-        miter_limit: f32,
+        #[serde(rename = "ml", default, skip_serializing_if = "Option::is_none")]
+        miter_limit: Option<Animated<f32>>,
     },
     #[serde(rename = "zz")]
     ZigZag {
+        /// Number of ridges per segment
         #[serde(rename = "r")]
-        radius: Animated<f32>,
+        frequency: Animated<f32>,
         #[serde(rename = "s")]
-        distance: Animated<f32>,
+        amplitude: Animated<f32>,
+        /// 1 for corner points, 2 for smooth ones
         #[serde(rename = "pt")]
-        ridges: Animated<f32>,
+        point_type: Animated<f32>,
     },
 }
 
@@ -469,9 +722,9 @@ pub enum LineJoin {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StrokeDash {
     #[serde(rename = "v")]
-    length: Animated<f32>,
+    pub length: Animated<f32>,
     #[serde(rename = "n")]
-    ty: StrokeDashType,
+    pub ty: StrokeDashType,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
@@ -505,13 +758,22 @@ pub enum TrimMultipleShape {
     Simultaneously = 2,
 }
 
-#[derive(
-    serde_repr::Serialize_repr, serde_repr::Deserialize_repr, Debug, Clone, Copy, PartialEq,
-)]
+#[derive(serde_repr::Serialize_repr, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(from = "u8")]
 #[repr(u8)]
 pub enum ShapeDirection {
     Clockwise = 1,
-    CounterClockwise = 2,
+    CounterClockwise = 3,
+}
+
+impl From<u8> for ShapeDirection {
+    fn from(value: u8) -> Self {
+        // Like lottie-web, anything other than 3 is drawn clockwise
+        match value {
+            3 => ShapeDirection::CounterClockwise,
+            _ => ShapeDirection::Clockwise,
+        }
+    }
 }
 
 impl Default for ShapeDirection {
@@ -524,7 +786,11 @@ impl Default for ShapeDirection {
 #[repr(u8)]
 pub enum MergeMode {
     #[serde(other)]
-    Unsupported = 1,
+    Normal = 1,
+    Add = 2,
+    Subtract = 3,
+    Intersect = 4,
+    ExcludeIntersections = 5,
 }
 
 #[derive(
@@ -609,7 +875,14 @@ pub enum MatteMode {
 }
 
 #[derive(
-    serde_repr::Serialize_repr, serde_repr::Deserialize_repr, Debug, Clone, Copy, PartialEq,
+    serde_repr::Serialize_repr,
+    serde_repr::Deserialize_repr,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
 )]
 #[repr(u8)]
 pub enum BlendMode {
@@ -661,10 +934,12 @@ impl Fill {
             opacity: Animated {
                 animated: false,
                 keyframes: vec![KeyFrame::from_value(0.0)],
+                expression: None,
             },
             color: Animated {
                 animated: false,
                 keyframes: vec![KeyFrame::from_value(Rgb::new_u8(0, 0, 0))],
+                expression: None,
             },
             fill_rule: FillRule::NonZero,
         }
@@ -676,11 +951,13 @@ impl From<Rgba> for Fill {
         Fill {
             opacity: Animated {
                 animated: false,
-                keyframes: vec![KeyFrame::from_value(color.a as f32 / 255.0)],
+                keyframes: vec![KeyFrame::from_value(color.a as f32 / 255.0 * 100.0)],
+                expression: None,
             },
             color: Animated {
                 animated: false,
                 keyframes: vec![KeyFrame::from_value(Rgb::new_u8(color.r, color.g, color.b))],
+                expression: None,
             },
             fill_rule: FillRule::NonZero,
         }
@@ -693,14 +970,14 @@ pub struct Stroke {
     pub line_cap: LineCap,
     #[serde(rename = "lj")]
     pub line_join: LineJoin,
-    #[serde(rename = "ml", default)]
-    miter_limit: f32,
+    #[serde(rename = "ml", default = "default_miter_limit")]
+    pub miter_limit: f32,
     #[serde(rename = "o")]
     pub opacity: Animated<f32>,
     #[serde(rename = "w")]
     pub width: Animated<f32>,
     #[serde(rename = "d", default)]
-    dashes: Vec<StrokeDash>,
+    pub dashes: Vec<StrokeDash>,
     #[serde(rename = "c")]
     pub color: Animated<Rgb>,
 }
@@ -718,6 +995,22 @@ pub struct GradientColor {
     pub color: Rgba,
 }
 
+impl GradientColor {
+    /// Sample two lists of sorted stops at the offsets of both, so that they
+    /// pair up stop by stop
+    pub(crate) fn align(
+        stops: &[GradientColor],
+        other: &[GradientColor],
+    ) -> (Vec<GradientColor>, Vec<GradientColor>) {
+        let offsets = |stops: &[GradientColor]| stops.iter().map(|stop| stop.offset).collect();
+        let offsets = merge_offsets([offsets(stops), offsets(other)].into_iter());
+        (
+            resample_gradient_colors(stops, &offsets),
+            resample_gradient_colors(other, &offsets),
+        )
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Gradient {
     #[serde(rename = "s")]
@@ -728,13 +1021,65 @@ pub struct Gradient {
     pub gradient_ty: GradientType,
     #[serde(rename = "g")]
     pub colors: ColorList,
+    /// Highlight length of radial gradients, in percent of the radius
+    #[serde(rename = "h", default, skip_serializing_if = "Option::is_none")]
+    pub highlight_length: Option<Animated<f32>>,
+    /// Highlight angle of radial gradients in degrees, relative to the line
+    /// from start to end
+    #[serde(rename = "a", default, skip_serializing_if = "Option::is_none")]
+    pub highlight_angle: Option<Animated<f32>>,
+}
+
+impl Gradient {
+    pub fn is_animated(&self) -> bool {
+        self.start.is_animated()
+            || self.end.is_animated()
+            || self.colors.colors.is_animated()
+            || self
+                .highlight_length
+                .as_ref()
+                .map(|h| h.is_animated())
+                .unwrap_or(false)
+            || self
+                .highlight_angle
+                .as_ref()
+                .map(|a| a.is_animated())
+                .unwrap_or(false)
+    }
+
+    /// Focal point of a radial gradient, moved away from the center at
+    /// `start` by the highlight
+    pub fn focal_point(&self, frame: f32) -> Vector2D {
+        let start = self.start.value(frame);
+        let end = self.end.value(frame);
+        let length = self
+            .highlight_length
+            .as_ref()
+            .map(|length| length.value(frame) / 100.0)
+            .unwrap_or(0.0)
+            // a focal point on the circle degenerates the gradient
+            .clamp(-0.99, 0.99);
+        if length == 0.0 {
+            return start;
+        }
+        let angle = self
+            .highlight_angle
+            .as_ref()
+            .map(|angle| angle.value(frame))
+            .unwrap_or(0.0)
+            .to_radians();
+        let direction = end - start;
+        let angle = direction.y.atan2(direction.x) + angle;
+        let distance = direction.length() * length;
+        start + Vector2D::new(angle.cos(), angle.sin()) * distance
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GradientFill {
     #[serde(rename = "o")]
     pub opacity: Animated<f32>,
-    #[serde(rename = "r")]
+    #[serde(rename = "r", default)]
     pub fill_rule: FillRule,
     #[serde(flatten)]
     pub gradient: Gradient,
@@ -746,14 +1091,14 @@ pub struct GradientStroke {
     pub line_cap: LineCap,
     #[serde(rename = "lj")]
     pub line_join: LineJoin,
-    #[serde(rename = "ml")]
-    miter_limit: f32,
+    #[serde(rename = "ml", default = "default_miter_limit")]
+    pub miter_limit: f32,
     #[serde(rename = "o")]
     pub opacity: Animated<f32>,
     #[serde(rename = "w")]
     pub width: Animated<f32>,
     #[serde(rename = "d", default)]
-    dashes: Vec<StrokeDash>,
+    pub dashes: Vec<StrokeDash>,
     #[serde(flatten)]
     pub gradient: Gradient,
 }
@@ -790,9 +1135,9 @@ pub struct PolyStar {
     pub outer_radius: Animated<f32>,
     #[serde(rename = "os")]
     pub outer_roundness: Animated<f32>,
-    #[serde(rename = "ir", default)]
+    #[serde(rename = "ir", default, skip_serializing_if = "Option::is_none")]
     pub inner_radius: Option<Animated<f32>>,
-    #[serde(rename = "is")]
+    #[serde(rename = "is", skip_serializing_if = "Option::is_none")]
     pub inner_roundness: Option<Animated<f32>>,
     #[serde(rename = "r")]
     pub rotation: Animated<f32>,
@@ -834,11 +1179,11 @@ pub struct Media {
     )]
     pub embedded: bool,
     id: String,
-    #[serde(rename = "nm", default)]
+    #[serde(rename = "nm", default, skip_serializing_if = "Option::is_none")]
     name: Option<String>,
-    #[serde(rename = "w", default)]
+    #[serde(rename = "w", default, skip_serializing_if = "Option::is_none")]
     pub width: Option<u32>,
-    #[serde(rename = "h", default)]
+    #[serde(rename = "h", default, skip_serializing_if = "Option::is_none")]
     pub height: Option<u32>,
 }
 
@@ -846,9 +1191,9 @@ pub struct Media {
 pub struct Precomposition {
     pub id: String,
     pub layers: Vec<Layer>,
-    #[serde(rename = "nm")]
+    #[serde(rename = "nm", skip_serializing_if = "Option::is_none")]
     name: Option<String>,
-    #[serde(rename = "fr")]
+    #[serde(rename = "fr", skip_serializing_if = "Option::is_none")]
     pub frame_rate: Option<f32>,
 }
 
@@ -881,7 +1226,7 @@ pub struct Bezier {
     pub out_tangent: Vec<Vector2D>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TextAnimationData {
     #[serde(rename = "a")]
     pub ranges: Vec<TextRange>,
@@ -893,51 +1238,55 @@ pub struct TextAnimationData {
     follow_path: TextFollowPath,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TextStyle {
-    #[serde(rename = "sw", default)]
+    #[serde(rename = "sw", default, skip_serializing_if = "Option::is_none")]
     stroke_width: Option<Animated<f32>>,
-    #[serde(rename = "sc", default)]
+    #[serde(rename = "sc", default, skip_serializing_if = "Option::is_none")]
     stroke_color: Option<Animated<Rgb>>,
-    #[serde(rename = "sh", default)]
+    #[serde(rename = "sh", default, skip_serializing_if = "Option::is_none")]
     stroke_hue: Option<Animated<f32>>,
-    #[serde(rename = "ss", default)]
+    #[serde(rename = "ss", default, skip_serializing_if = "Option::is_none")]
     stroke_saturation: Option<Animated<f32>>,
-    #[serde(rename = "sb", default)]
+    #[serde(rename = "sb", default, skip_serializing_if = "Option::is_none")]
     stroke_brightness: Option<Animated<f32>>,
-    #[serde(rename = "so", default)]
+    #[serde(rename = "so", default, skip_serializing_if = "Option::is_none")]
     stroke_opacity: Option<Animated<f32>>,
-    #[serde(rename = "fc", default)]
+    #[serde(rename = "fc", default, skip_serializing_if = "Option::is_none")]
     fill_color: Option<Animated<Rgb>>,
-    #[serde(rename = "fh", default)]
+    #[serde(rename = "fh", default, skip_serializing_if = "Option::is_none")]
     fill_hue: Option<Animated<f32>>,
-    #[serde(rename = "fs", default)]
+    #[serde(rename = "fs", default, skip_serializing_if = "Option::is_none")]
     fill_saturation: Option<Animated<f32>>,
-    #[serde(rename = "fb", default)]
+    #[serde(rename = "fb", default, skip_serializing_if = "Option::is_none")]
     fill_brightness: Option<Animated<f32>>,
-    #[serde(rename = "t", default)]
+    #[serde(rename = "t", default, skip_serializing_if = "Option::is_none")]
     pub letter_spacing: Option<Animated<f32>>,
-    #[serde(rename = "bl", default)]
+    #[serde(rename = "bl", default, skip_serializing_if = "Option::is_none")]
     blur: Option<Animated<f32>>,
-    #[serde(rename = "ls", default)]
+    #[serde(rename = "ls", default, skip_serializing_if = "Option::is_none")]
     pub line_spacing: Option<Animated<f32>>,
-    #[serde(flatten)]
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
     transform: Option<Transform>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TextRange {
-    #[serde(rename = "nm", default)]
+    #[serde(rename = "nm", default, skip_serializing_if = "Option::is_none")]
     name: Option<String>,
-    #[serde(rename = "a", default)]
+    #[serde(rename = "a", default, skip_serializing_if = "Option::is_none")]
     pub style: Option<TextStyle>,
     #[serde(rename = "s")]
     pub selector: TextRangeSelector,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TextRangeSelector {
-    #[serde(rename = "t", deserialize_with = "bool_from_int")]
+    #[serde(
+        rename = "t",
+        deserialize_with = "bool_from_int",
+        serialize_with = "int_from_bool"
+    )]
     expressible: bool,
     #[serde(rename = "xe")]
     max_ease: Animated<f32>,
@@ -947,25 +1296,29 @@ pub struct TextRangeSelector {
     max_amount: Animated<f32>,
     #[serde(rename = "b")]
     based_on: TextBased,
-    #[serde(rename = "rn", deserialize_with = "bool_from_int")]
+    #[serde(
+        rename = "rn",
+        deserialize_with = "bool_from_int",
+        serialize_with = "int_from_bool"
+    )]
     randomize: bool,
     #[serde(rename = "sh")]
     shape: TextShape,
-    #[serde(rename = "o", default)]
+    #[serde(rename = "o", default, skip_serializing_if = "Option::is_none")]
     offset: Option<Animated<f32>>,
     #[serde(rename = "r")]
     pub range_units: TextBased,
-    #[serde(rename = "sm", default)]
+    #[serde(rename = "sm", default, skip_serializing_if = "Option::is_none")]
     selector_smoothness: Option<Animated<f32>>,
-    #[serde(rename = "s", default)]
+    #[serde(rename = "s", default, skip_serializing_if = "Option::is_none")]
     pub start: Option<Animated<f32>>,
-    #[serde(rename = "e", default)]
+    #[serde(rename = "e", default, skip_serializing_if = "Option::is_none")]
     pub end: Option<Animated<f32>>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TextData {
-    #[serde(rename = "x", default)]
+    #[serde(rename = "x", default, skip_serializing_if = "Option::is_none")]
     expression: Option<String>,
     #[serde(
         deserialize_with = "keyframes_from_array",
@@ -1007,7 +1360,7 @@ pub struct TextDocument {
     stroke_width: f32,
     #[serde(rename = "of", default)]
     stroke_above_fill: bool,
-    #[serde(rename = "lh", default)]
+    #[serde(rename = "lh", default, skip_serializing_if = "Option::is_none")]
     line_height: Option<f32>,
     #[serde(rename = "j", default)]
     pub justify: TextJustify,
@@ -1048,33 +1401,187 @@ pub struct Mask {
     pub name: String,
     #[serde(rename = "mn", default)]
     match_name: String,
+    /// Whether the mask covers the area outside of its path instead
     #[serde(rename = "inv", default)]
-    inverted: bool,
+    pub inverted: bool,
     #[serde(rename = "pt")]
     pub points: Animated<Vec<Bezier>>,
     #[serde(rename = "o")]
     pub opacity: Animated<f32>,
     pub mode: MaskMode,
-    #[serde(rename = "e", default)]
-    expand: Option<Animated<f32>>,
+    /// Distance the mask path is moved outwards by
+    #[serde(rename = "e", default, skip_serializing_if = "Option::is_none")]
+    pub expand: Option<Animated<f32>>,
+    /// Width of the soft edge, horizontally and vertically
+    #[serde(rename = "f", default, skip_serializing_if = "Option::is_none")]
+    pub feather: Option<Animated<Vector2D>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum MaskMode {
     #[serde(rename = "n")]
-    None,
+    None = 0,
     #[serde(rename = "a")]
-    Add,
+    Add = 1,
     #[serde(rename = "s")]
-    Subtract,
+    Subtract = 2,
     #[serde(rename = "i")]
-    Intersect,
+    Intersect = 3,
     #[serde(rename = "l")]
-    Lighten,
+    Lighten = 4,
     #[serde(rename = "d")]
-    Darken,
+    Darken = 5,
     #[serde(rename = "f")]
-    Difference,
+    Difference = 6,
+}
+
+/// An effect applied to the pixels of a layer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Effect {
+    #[serde(rename = "ty")]
+    pub ty: EffectType,
+    #[serde(rename = "nm", default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "mn", default, skip_serializing_if = "Option::is_none")]
+    match_name: Option<String>,
+    #[serde(
+        rename = "en",
+        deserialize_with = "bool_from_int",
+        serialize_with = "int_from_bool",
+        default = "default_enabled"
+    )]
+    pub enabled: bool,
+    #[serde(rename = "ef", default)]
+    pub values: Vec<EffectValue>,
+}
+
+impl Effect {
+    /// Numeric value at `index`, which sliders, angles, checkboxes and
+    /// dropdowns all have
+    pub fn number(&self, index: usize) -> Option<&Animated<f32>> {
+        match &self.values.get(index)?.value {
+            EffectValueType::Slider(v)
+            | EffectValueType::Angle(v)
+            | EffectValueType::Checkbox(v)
+            | EffectValueType::DropDown(v)
+            | EffectValueType::Layer(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn color(&self, index: usize) -> Option<&Animated<Rgba>> {
+        match &self.values.get(index)?.value {
+            EffectValueType::Color(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn point(&self, index: usize) -> Option<&Animated<Vector2D>> {
+        match &self.values.get(index)?.value {
+            EffectValueType::Point(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum EffectType {
+    /// Expression controls and other effects made of arbitrary values
+    Custom,
+    Tint,
+    Fill,
+    Stroke,
+    Tritone,
+    ProLevels,
+    DropShadow,
+    RadialWipe,
+    DisplacementMap,
+    Matte,
+    GaussianBlur,
+    Twirl,
+    MeshWarp,
+    Wavy,
+    Spherize,
+    Puppet,
+    Unknown(u8),
+}
+
+impl From<u8> for EffectType {
+    fn from(ty: u8) -> Self {
+        match ty {
+            5 => EffectType::Custom,
+            20 => EffectType::Tint,
+            21 => EffectType::Fill,
+            22 => EffectType::Stroke,
+            23 => EffectType::Tritone,
+            24 => EffectType::ProLevels,
+            25 => EffectType::DropShadow,
+            26 => EffectType::RadialWipe,
+            27 => EffectType::DisplacementMap,
+            28 => EffectType::Matte,
+            29 => EffectType::GaussianBlur,
+            30 => EffectType::Twirl,
+            31 => EffectType::MeshWarp,
+            32 => EffectType::Wavy,
+            33 => EffectType::Spherize,
+            34 => EffectType::Puppet,
+            ty => EffectType::Unknown(ty),
+        }
+    }
+}
+
+impl From<EffectType> for u8 {
+    fn from(ty: EffectType) -> Self {
+        match ty {
+            EffectType::Custom => 5,
+            EffectType::Tint => 20,
+            EffectType::Fill => 21,
+            EffectType::Stroke => 22,
+            EffectType::Tritone => 23,
+            EffectType::ProLevels => 24,
+            EffectType::DropShadow => 25,
+            EffectType::RadialWipe => 26,
+            EffectType::DisplacementMap => 27,
+            EffectType::Matte => 28,
+            EffectType::GaussianBlur => 29,
+            EffectType::Twirl => 30,
+            EffectType::MeshWarp => 31,
+            EffectType::Wavy => 32,
+            EffectType::Spherize => 33,
+            EffectType::Puppet => 34,
+            EffectType::Unknown(ty) => ty,
+        }
+    }
+}
+
+/// A named parameter of an [Effect]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EffectValue {
+    #[serde(rename = "nm", default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub value: EffectValueType,
+}
+
+#[derive(Debug, Clone)]
+pub enum EffectValueType {
+    Slider(Animated<f32>),
+    /// Angle in degrees
+    Angle(Animated<f32>),
+    Color(Animated<Rgba>),
+    Point(Animated<Vector2D>),
+    /// Zero when unchecked
+    Checkbox(Animated<f32>),
+    /// One-based index of the selected option
+    DropDown(Animated<f32>),
+    /// Index of a layer
+    Layer(Animated<f32>),
+    /// Values which are not understood, kept as they are
+    Other {
+        ty: u64,
+        value: Option<serde_json::Value>,
+    },
 }
 
 fn mat4(anchor: Vector2D, position: Vector2D, scale: Vector2D, rotation: f32) -> Mat4 {
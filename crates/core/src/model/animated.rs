@@ -4,9 +4,12 @@ use flo_curves::bezier::{curve_intersects_line, Curve};
 use flo_curves::{BezierCurve, BezierCurveFactory, Coord2};
 use serde::{Deserialize, Serialize};
 
-use crate::Lerp;
+use crate::expression::{Expression, ExpressionValue};
+use crate::lerp::SpatialPath;
+use crate::{Lerp, SpatialTangents};
 
 use super::helpers::{self, *};
+use super::Vector2D;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Animated<T> {
@@ -20,13 +23,18 @@ pub struct Animated<T> {
     #[serde(
         deserialize_with = "keyframes_from_array",
         serialize_with = "array_from_keyframes",
-        bound = "T: FromTo<helpers::Value>",
+        bound(
+            serialize = "T: FromTo<helpers::Value> + Clone",
+            deserialize = "T: FromTo<helpers::Value>"
+        ),
         rename = "k"
     )]
     pub keyframes: Vec<KeyFrame<T>>,
+    #[serde(rename = "x", default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<Expression>,
 }
 
-impl<T: Clone + Lerp<Target = T>> Animated<T> {
+impl<T: Clone + Lerp<Target = T> + ExpressionValue> Animated<T> {
     pub fn from_value(value: T) -> Self {
         Animated {
             animated: false,
@@ -37,7 +45,11 @@ impl<T: Clone + Lerp<Target = T>> Animated<T> {
                 end_frame: 0.0,
                 easing_out: None,
                 easing_in: None,
+                hold: false,
+                out_tangent: None,
+                in_tangent: None,
             }],
+            expression: None,
         }
     }
 
@@ -45,8 +57,22 @@ impl<T: Clone + Lerp<Target = T>> Animated<T> {
         self.keyframes[0].start_value.clone()
     }
 
+    /// Value at `frame`, computed by the expression of the property if there
+    /// is one it can be evaluated
     pub fn value(&self, frame: f32) -> T {
-        if !self.is_animated() {
+        match self
+            .expression
+            .as_ref()
+            .and_then(|expression| expression.evaluate(self, frame))
+        {
+            Some(value) => value,
+            None => self.keyframe_value(frame),
+        }
+    }
+
+    /// Value at `frame` from the keyframes alone
+    pub fn keyframe_value(&self, frame: f32) -> T {
+        if !self.is_keyframed() {
             return self.initial_value();
         }
         let len = self.keyframes.len() - 1;
@@ -65,7 +91,18 @@ impl<T: Clone + Lerp<Target = T>> Animated<T> {
         }
     }
 
+    /// Whether the value changes over time, through its keyframes or a bound
+    /// expression
     pub fn is_animated(&self) -> bool {
+        self.is_keyframed()
+            || self
+                .expression
+                .as_ref()
+                .is_some_and(|expression| expression.is_bound())
+    }
+
+    /// Whether the keyframes alone change the value over time
+    pub fn is_keyframed(&self) -> bool {
         self.keyframes.len() > 1 || self.keyframes[0].easing_in.is_some()
     }
 
@@ -81,6 +118,7 @@ impl<T: Clone + Lerp<Target = T>> Animated<T> {
                 if keyframe.end_frame <= frame {
                     keyframes.push(keyframe);
                 } else if keyframe.start_frame >= frame {
+                    original_keyframes.push_front(keyframe);
                     break;
                 } else {
                     let (a, b) = keyframe.split(frame);
@@ -93,6 +131,7 @@ impl<T: Clone + Lerp<Target = T>> Animated<T> {
                 }
             }
         }
+        keyframes.extend(original_keyframes);
         self.keyframes = keyframes;
     }
 
@@ -111,6 +150,7 @@ where
         Self {
             animated: false,
             keyframes: vec![KeyFrame::default()],
+            expression: None,
         }
     }
 }
@@ -130,6 +170,23 @@ pub struct KeyFrame<T> {
     pub easing_out: Option<Easing>,
     #[serde(rename = "i", default)]
     pub easing_in: Option<Easing>,
+    /// Hold keyframes keep `start_value` until `end_frame` instead of
+    /// interpolating towards `end_value`
+    #[serde(
+        rename = "h",
+        default,
+        deserialize_with = "bool_from_int",
+        serialize_with = "int_from_bool"
+    )]
+    pub hold: bool,
+    /// Spatial out tangent (`to`) of a position keyframe, relative to
+    /// `start_value`
+    #[serde(skip)]
+    pub out_tangent: Option<Vector2D>,
+    /// Spatial in tangent (`ti`) of a position keyframe, relative to
+    /// `end_value`
+    #[serde(skip)]
+    pub in_tangent: Option<Vector2D>,
 }
 
 impl<T: Clone> KeyFrame<T> {
@@ -141,6 +198,9 @@ impl<T: Clone> KeyFrame<T> {
             end_frame: 0.0,
             easing_out: None,
             easing_in: None,
+            hold: false,
+            out_tangent: None,
+            in_tangent: None,
         }
     }
 
@@ -152,12 +212,28 @@ impl<T: Clone> KeyFrame<T> {
             end_frame: self.end_frame,
             easing_out: self.easing_out.clone(),
             easing_in: self.easing_in.clone(),
+            hold: self.hold,
+            out_tangent: self.out_tangent,
+            in_tangent: self.in_tangent,
         }
     }
-}
 
-impl<T: Clone + Lerp<Target = T>> KeyFrame<T> {
-    pub fn value(&self, t: f32) -> T {
+    /// Spatial tangents bending the motion path, `None` for a straight path
+    pub fn spatial_tangents(&self) -> Option<SpatialTangents> {
+        let tangents = (
+            self.out_tangent.unwrap_or_default(),
+            self.in_tangent.unwrap_or_default(),
+        );
+        if tangents == (Vector2D::zero(), Vector2D::zero()) {
+            None
+        } else {
+            Some(tangents)
+        }
+    }
+
+    /// Map linear progress `t` of this keyframe to the eased progress of its
+    /// value
+    fn eased_ratio(&self, t: f32) -> f32 {
         let ease_out = self.easing_out.clone().unwrap_or_else(|| Easing {
             x: vec![0.0],
             y: vec![0.0],
@@ -177,12 +253,49 @@ impl<T: Clone + Lerp<Target = T>> KeyFrame<T> {
         );
         let intersection =
             curve_intersects_line(&curve, &(Coord2(t as f64, 0.0), Coord2(t as f64, 1.0)));
-        let ratio = if intersection.is_empty() {
+        if intersection.is_empty() {
             t
         } else {
             intersection[0].2 .1 as f32
+        }
+    }
+}
+
+impl KeyFrame<Vector2D> {
+    /// Direction of motion at linear progress `t`, following the spatial
+    /// tangents if any
+    pub fn direction(&self, t: f32) -> Vector2D {
+        let chord = self.end_value - self.start_value;
+        let tangents = match self.spatial_tangents() {
+            Some(tangents) => tangents,
+            None => return chord,
         };
-        self.end_value.lerp(&self.start_value, ratio)
+        let path = SpatialPath::new(self.start_value, self.end_value, tangents);
+        let direction = path.derivative(path.parameter(self.eased_ratio(t)));
+        if direction.square_length() > f32::EPSILON {
+            direction
+        } else {
+            chord
+        }
+    }
+}
+
+impl<T: Clone + Lerp<Target = T>> KeyFrame<T> {
+    pub fn value(&self, t: f32) -> T {
+        if self.hold {
+            return if t < 1.0 {
+                self.start_value.clone()
+            } else {
+                self.end_value.clone()
+            };
+        }
+        let ratio = self.eased_ratio(t);
+        match self.spatial_tangents() {
+            Some(tangents) => self
+                .end_value
+                .lerp_spatial(&self.start_value, ratio, tangents),
+            None => self.end_value.lerp(&self.start_value, ratio),
+        }
     }
 
     pub fn split(&self, frame: f32) -> (Option<Self>, Option<Self>) {
@@ -192,6 +305,19 @@ impl<T: Clone + Lerp<Target = T>> KeyFrame<T> {
             return (Some(self.clone()), None);
         }
 
+        if self.hold {
+            let keyframe_a = KeyFrame {
+                end_value: self.start_value.clone(),
+                end_frame: frame,
+                ..self.clone()
+            };
+            let keyframe_b = KeyFrame {
+                start_frame: frame,
+                ..self.clone()
+            };
+            return (Some(keyframe_a), Some(keyframe_b));
+        }
+
         let ease_out = self.easing_out.clone().unwrap_or_else(|| Easing {
             x: vec![0.0],
             y: vec![0.0],
@@ -212,13 +338,26 @@ impl<T: Clone + Lerp<Target = T>> KeyFrame<T> {
         );
         let intersection =
             curve_intersects_line(&curve, &(Coord2(x as f64, 0.0), Coord2(x as f64, 1.0)));
-        let ratio = if intersection.is_empty() {
-            x
+        // The curve is split where it crosses `x`, which is not the curve
+        // parameter `x` unless the easing is linear
+        let (t, ratio) = if intersection.is_empty() {
+            (x as f64, x)
         } else {
-            intersection[0].2 .1 as f32
+            (intersection[0].0, intersection[0].2 .1 as f32)
+        };
+        let (value, tangents_a, tangents_b) = match self.spatial_tangents() {
+            Some(tangents) => {
+                let value = self
+                    .end_value
+                    .lerp_spatial(&self.start_value, ratio, tangents);
+                let (a, b) = self
+                    .end_value
+                    .split_spatial(&self.start_value, ratio, tangents);
+                (value, Some(a), Some(b))
+            }
+            None => (self.end_value.lerp(&self.start_value, ratio), None, None),
         };
-        let value = self.end_value.lerp(&self.start_value, ratio);
-        let (mut curve_a, mut curve_b): (Curve<Coord2>, _) = curve.subdivide(x as f64);
+        let (mut curve_a, mut curve_b): (Curve<Coord2>, _) = curve.subdivide(t);
         scale_curve(&mut curve_a);
         scale_curve(&mut curve_b);
         let keyframe_a = KeyFrame {
@@ -234,6 +373,9 @@ impl<T: Clone + Lerp<Target = T>> KeyFrame<T> {
             }),
             start_frame: self.start_frame,
             end_frame: frame,
+            hold: false,
+            out_tangent: tangents_a.map(|t| t.0),
+            in_tangent: tangents_a.map(|t| t.1),
         };
         let keyframe_b = KeyFrame {
             start_value: value,
@@ -248,6 +390,9 @@ impl<T: Clone + Lerp<Target = T>> KeyFrame<T> {
             }),
             start_frame: frame,
             end_frame: self.end_frame,
+            hold: false,
+            out_tangent: tangents_b.map(|t| t.0),
+            in_tangent: tangents_b.map(|t| t.1),
         };
         (Some(keyframe_a), Some(keyframe_b))
     }
@@ -273,6 +418,5 @@ fn scale_curve(curve: &mut Curve<Coord2>) {
     curve.control_points.0 .1 *= y_scale;
     curve.control_points.1 .0 *= x_scale;
     curve.control_points.1 .1 *= y_scale;
-    curve.control_points.0 .0 *= x_scale;
     curve.end_point = (1.0, 1.0).into()
 }
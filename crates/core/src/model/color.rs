@@ -1,6 +1,7 @@
+use std::fmt;
 use std::str::FromStr;
 
-use super::helpers::{FromTo, Value};
+use super::helpers::{numbers, FromTo, Value};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Rgba {
@@ -13,10 +14,10 @@ pub struct Rgba {
 impl Rgba {
     pub fn new_f32(r: f32, g: f32, b: f32, a: f32) -> Rgba {
         Rgba {
-            r: (r * 255.0) as u8,
-            g: (g * 255.0) as u8,
-            b: (b * 255.0) as u8,
-            a: (a * 255.0) as u8,
+            r: (r * 255.0).round() as u8,
+            g: (g * 255.0).round() as u8,
+            b: (b * 255.0).round() as u8,
+            a: (a * 255.0).round() as u8,
         }
     }
 
@@ -44,14 +45,18 @@ impl FromStr for Rgba {
         if s.starts_with("#") {
             chars.next();
         }
-        let (rgb, a) = read_color::rgb_maybe_a(&mut chars).unwrap();
+        let (rgb, a) = read_color::rgb_maybe_a(&mut chars).ok_or(())?;
         Ok(Rgba::new_u8(rgb[0], rgb[1], rgb[2], a.unwrap_or(255)))
     }
 }
 
-impl ToString for Rgba {
-    fn to_string(&self) -> String {
-        todo!()
+impl fmt::Display for Rgba {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a != 255 {
+            write!(f, "{:02x}", self.a)?;
+        }
+        Ok(())
     }
 }
 
@@ -65,9 +70,9 @@ pub struct Rgb {
 impl Rgb {
     pub fn new_f32(r: f32, g: f32, b: f32) -> Rgb {
         Rgb {
-            r: (r * 255.0) as u8,
-            g: (g * 255.0) as u8,
-            b: (b * 255.0) as u8,
+            r: (r * 255.0).round() as u8,
+            g: (g * 255.0).round() as u8,
+            b: (b * 255.0).round() as u8,
         }
     }
 
@@ -77,9 +82,9 @@ impl Rgb {
 }
 
 impl FromTo<Value> for Rgba {
-    fn from(v: Value) -> Self {
-        let v = v.as_f32_vec().unwrap();
-        if v[0] > 1.0 && v[0] <= 255.0 {
+    fn from(v: Value) -> Result<Self, &'static str> {
+        let v = numbers(v, 3, "an RGBA color")?;
+        Ok(if v[0] > 1.0 && v[0] <= 255.0 {
             Rgba::new_u8(
                 v[0] as u8,
                 v[1] as u8,
@@ -88,7 +93,7 @@ impl FromTo<Value> for Rgba {
             )
         } else {
             Rgba::new_f32(v[0], v[1], v[2], v.get(3).cloned().unwrap_or(1.0))
-        }
+        })
     }
 
     fn to(self) -> Value {
@@ -3,7 +3,7 @@ mod keyframe;
 
 use std::fmt;
 
-pub(crate) use self::convert::FromTo;
+pub(crate) use self::convert::{numbers, FromTo};
 use self::keyframe::AnimatedHelper;
 
 use super::*;
@@ -31,6 +31,11 @@ impl Value {
     }
 }
 
+/// Message of a value which could not be converted to what was `expected`
+fn expected_value(expected: &str) -> String {
+    format!("invalid value, expected {}", expected)
+}
+
 pub fn bool_from_int<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
     D: Deserializer<'de>,
@@ -57,7 +62,7 @@ where
     D: Deserializer<'de>,
 {
     let s = Value::deserialize(deserializer)?;
-    Ok(<Rgba as FromTo<Value>>::from(s))
+    <Rgba as FromTo<Value>>::from(s).map_err(|expected| D::Error::custom(expected_value(expected)))
 }
 
 pub fn str_to_rgba<'de, D>(deserializer: D) -> Result<Rgba, D::Error>
@@ -65,7 +70,8 @@ where
     D: Deserializer<'de>,
 {
     let s = String::deserialize(deserializer)?;
-    Ok(s.parse().unwrap())
+    s.parse()
+        .map_err(|_| D::Error::custom(expected_value("a hex color")))
 }
 
 pub fn str_from_rgba<S>(b: &Rgba, serializer: S) -> Result<S::Ok, S::Error>
@@ -79,12 +85,15 @@ pub fn array_from_rgba<S>(b: &Rgba, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let a = [b.r as f32, b.g as f32, b.b as f32, b.a as f32];
+    let mut a = b.to().as_f32_vec().unwrap_or_default();
+    // Opaque colors are written as rgb, like bodymovin does
+    if b.a == 255 {
+        a.truncate(3);
+    }
     let mut seq = serializer.serialize_seq(Some(a.len()))?;
-    seq.serialize_element(&a[0])?;
-    seq.serialize_element(&a[1])?;
-    seq.serialize_element(&a[2])?;
-    seq.serialize_element(&a[3])?;
+    for c in a {
+        seq.serialize_element(&c)?;
+    }
     seq.end()
 }
 
@@ -119,8 +128,10 @@ impl<'de> serde::Deserialize<'de> for LayerContent {
                         width: color.width,
                     }
                 }
-                2 | 6 => {
-                    LayerContent::MediaRef(MediaRef::deserialize(value).map_err(D::Error::custom)?)
+                ty @ (2 | 6) => {
+                    let mut media = MediaRef::deserialize(value).map_err(D::Error::custom)?;
+                    media.audio = ty == 6;
+                    LayerContent::MediaRef(media)
                 }
                 3 => LayerContent::Empty,
                 4 => {
@@ -152,9 +163,24 @@ impl Serialize for LayerContent {
         #[derive(Serialize)]
         #[serde(untagged)]
         enum LayerContent_<'a> {
-            // T1(&'a Type1),
-            SolidColor { sc: String, sh: f32, sw: f32 },
-            Shape { shapes: &'a Vec<ShapeLayer> },
+            PreCompositionRef(&'a PreCompositionRef),
+            SolidColor {
+                sc: String,
+                sh: f32,
+                sw: f32,
+            },
+            MediaRef(&'a MediaRef),
+            Media {
+                #[serde(rename = "refId")]
+                ref_id: &'a str,
+            },
+            Empty {},
+            Shape {
+                shapes: &'a Vec<ShapeLayer>,
+            },
+            Text {
+                t: &'a TextAnimationData,
+            },
         }
 
         #[derive(Serialize)]
@@ -166,6 +192,10 @@ impl Serialize for LayerContent {
         }
 
         let msg = match self {
+            LayerContent::PreCompositionRef(r) => TypedLayerContent {
+                t: 0,
+                content: LayerContent_::PreCompositionRef(r),
+            },
             LayerContent::Shape(ShapeGroup { shapes }) => TypedLayerContent {
                 t: 4,
                 content: LayerContent_::Shape { shapes },
@@ -182,43 +212,124 @@ impl Serialize for LayerContent {
                     sw: *width,
                 },
             },
-            _ => unimplemented!(),
+            LayerContent::MediaRef(media) => TypedLayerContent {
+                t: if media.audio { 6 } else { 2 },
+                content: LayerContent_::MediaRef(media),
+            },
+            LayerContent::Media(media) => TypedLayerContent {
+                t: 2,
+                content: LayerContent_::Media { ref_id: &media.id },
+            },
+            LayerContent::Empty => TypedLayerContent {
+                t: 3,
+                content: LayerContent_::Empty {},
+            },
+            LayerContent::Text(t) => TypedLayerContent {
+                t: 5,
+                content: LayerContent_::Text { t },
+            },
         };
         msg.serialize(serializer)
     }
 }
 
+impl<'de> serde::Deserialize<'de> for EffectValueType {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let mut value = serde_json::Value::deserialize(d)?;
+        let ty = value
+            .get("ty")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| D::Error::missing_field("ty"))?;
+        let v = value.get_mut("v").map(serde_json::Value::take);
+        // exporters are not consistent about the value types, so anything
+        // failing to parse is kept as it is
+        let typed = v.clone().and_then(|v| {
+            Some(match ty {
+                0 => EffectValueType::Slider(Animated::deserialize(v).ok()?),
+                1 => EffectValueType::Angle(Animated::deserialize(v).ok()?),
+                2 => {
+                    let color = Animated::<Vec<f32>>::deserialize(v.clone()).ok()?;
+                    if color.keyframes.iter().any(|k| k.start_value.len() < 3) {
+                        return None;
+                    }
+                    EffectValueType::Color(Animated::deserialize(v).ok()?)
+                }
+                3 => EffectValueType::Point(Animated::deserialize(v).ok()?),
+                4 => EffectValueType::Checkbox(Animated::deserialize(v).ok()?),
+                7 => EffectValueType::DropDown(Animated::deserialize(v).ok()?),
+                10 => EffectValueType::Layer(Animated::deserialize(v).ok()?),
+                _ => return None,
+            })
+        });
+        Ok(typed.unwrap_or(EffectValueType::Other { ty, value: v }))
+    }
+}
+
+impl Serialize for EffectValueType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum EffectValue_<'a> {
+            Number(&'a Animated<f32>),
+            Color(&'a Animated<Rgba>),
+            Point(&'a Animated<Vector2D>),
+            Other(&'a serde_json::Value),
+        }
+
+        #[derive(Serialize)]
+        struct TypedEffectValue<'a> {
+            ty: u64,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            v: Option<EffectValue_<'a>>,
+        }
+
+        let (ty, v) = match self {
+            EffectValueType::Slider(v) => (0, EffectValue_::Number(v)),
+            EffectValueType::Angle(v) => (1, EffectValue_::Number(v)),
+            EffectValueType::Color(v) => (2, EffectValue_::Color(v)),
+            EffectValueType::Point(v) => (3, EffectValue_::Point(v)),
+            EffectValueType::Checkbox(v) => (4, EffectValue_::Number(v)),
+            EffectValueType::DropDown(v) => (7, EffectValue_::Number(v)),
+            EffectValueType::Layer(v) => (10, EffectValue_::Number(v)),
+            EffectValueType::Other { ty, value } => {
+                let msg = TypedEffectValue {
+                    ty: *ty,
+                    v: value.as_ref().map(EffectValue_::Other),
+                };
+                return msg.serialize(serializer);
+            }
+        };
+        TypedEffectValue { ty, v: Some(v) }.serialize(serializer)
+    }
+}
+
 pub(crate) fn keyframes_from_array<'de, D, T>(deserializer: D) -> Result<Vec<KeyFrame<T>>, D::Error>
 where
     D: Deserializer<'de>,
     T: FromTo<Value>,
 {
     let result = AnimatedHelper::deserialize(deserializer)?;
-    Ok(result.into())
+    result
+        .try_into()
+        .map_err(|expected| D::Error::custom(expected_value(expected)))
 }
 
 pub fn array_from_keyframes<S, T>(b: &Vec<KeyFrame<T>>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
+    T: FromTo<Value> + Clone,
 {
-    todo!()
-    // let animated = AnimatedHelper::from(b);
-    // match animated {
-    //     AnimatedHelper::Plain(data) => data.serialize(serializer),
-    //     AnimatedHelper::AnimatedHelper(data) => {
-    //         let mut seq = serializer.serialize_seq(Some(data.len()))?;
-    //         for keyframe in data {
-    //             seq.serialize_element(&keyframe)?;
-    //         }
-    //         seq.end()
-    //     }
-    // }
+    AnimatedHelper::from(b).serialize(serializer)
 }
 
 pub fn default_vec2_100() -> Animated<Vector2D> {
     Animated {
         animated: false,
         keyframes: vec![KeyFrame::from_value(Vector2D::new(100.0, 100.0))],
+        expression: None,
     }
 }
 
@@ -226,9 +337,18 @@ pub fn default_number_100() -> Animated<f32> {
     Animated {
         animated: false,
         keyframes: vec![KeyFrame::from_value(100.0)],
+        expression: None,
     }
 }
 
+pub fn default_miter_limit() -> f32 {
+    4.0
+}
+
+pub fn default_enabled() -> bool {
+    true
+}
+
 struct NumberVistor;
 
 impl<'de> Visitor<'de> for NumberVistor {
@@ -262,6 +382,17 @@ impl<'de> Visitor<'de> for NumberVistor {
     }
 }
 
+/// Sizes are whole pixels, but some exporters write fractional ones
+pub(crate) fn u32_from_number<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match deserializer.deserialize_any(NumberVistor)? {
+        Some(number) => Ok(number),
+        None => Err(D::Error::custom("expected a number")),
+    }
+}
+
 pub(crate) fn vec_from_array<'de, D>(deserializer: D) -> Result<Vec<Vector2D>, D::Error>
 where
     D: Deserializer<'de>,
@@ -304,46 +435,185 @@ pub(crate) struct ColorListHelper {
 impl From<ColorListHelper> for ColorList {
     fn from(helper: ColorListHelper) -> Self {
         let color_count = helper.color_count;
+        let own_offsets = |data: &[f32]| {
+            let (colors, alphas) = split_gradient_stops(data, color_count);
+            merge_offsets([stop_offsets(&colors), stop_offsets(&alphas)].into_iter())
+        };
+        let keyframe_offsets = helper
+            .colors
+            .keyframes
+            .iter()
+            .flat_map(|keyframe| {
+                [
+                    own_offsets(&keyframe.start_value),
+                    own_offsets(&keyframe.end_value),
+                ]
+            })
+            .collect::<Vec<_>>();
+        // Keyframes whose stops pair up keep their own offsets, so that stops
+        // can move. Otherwise, as with alpha stops at different offsets in
+        // different keyframes, every keyframe is sampled at the offsets of all
+        let shared = match keyframe_offsets.first() {
+            Some(first) if keyframe_offsets.iter().any(|o| o.len() != first.len()) => {
+                Some(merge_offsets(keyframe_offsets.iter().cloned()))
+            }
+            _ => None,
+        };
+        let mut keyframe_offsets = keyframe_offsets.into_iter();
+        let mut to_colors = |data: &[f32]| {
+            let own = keyframe_offsets.next().unwrap_or_default();
+            f32_to_gradient_colors(data, color_count, shared.as_ref().unwrap_or(&own))
+        };
+        let colors = Animated {
+            animated: helper.colors.animated,
+            keyframes: helper
+                .colors
+                .keyframes
+                .into_iter()
+                .map(|keyframe| {
+                    let start = to_colors(&keyframe.start_value);
+                    let end = to_colors(&keyframe.end_value);
+                    keyframe.alter_value(start, end)
+                })
+                .collect(),
+            expression: helper.colors.expression,
+        };
+        // Stops merged from separate alpha stops are written back as color
+        // stops with alpha at the same offsets
+        let color_count = colors
+            .keyframes
+            .first()
+            .map(|keyframe| keyframe.start_value.len())
+            .unwrap_or(color_count);
         ColorList {
             color_count,
-            colors: Animated {
-                animated: helper.colors.animated,
-                keyframes: helper
-                    .colors
-                    .keyframes
-                    .into_iter()
-                    .map(|keyframe| {
-                        let start = f32_to_gradient_colors(&keyframe.start_value, color_count);
-                        let end = f32_to_gradient_colors(&keyframe.end_value, color_count);
-                        keyframe.alter_value(start, end)
-                    })
-                    .collect(),
-            },
+            colors,
         }
     }
 }
 
-fn f32_to_gradient_colors(data: &Vec<f32>, color_count: usize) -> Vec<GradientColor> {
-    if data.len() == color_count * 4 {
-        // Rgb color
-        data.chunks(4)
-            .map(|chunk| GradientColor {
-                offset: chunk[0],
-                color: Rgba::new_f32(chunk[1], chunk[2], chunk[3], 1.0),
-            })
-            .collect()
-    } else if data.len() == color_count * 4 + color_count * 2 {
-        // Rgba color
-        (&data[0..(color_count * 4)])
-            .chunks(4)
-            .zip((&data[(color_count * 4)..]).chunks(2))
-            .map(|(chunk, opacity)| GradientColor {
-                offset: chunk[0],
-                color: Rgba::new_f32(chunk[1], chunk[2], chunk[3], opacity[1]),
-            })
-            .collect()
-    } else {
-        unimplemented!()
+type Stops<const N: usize> = Vec<(f32, [f32; N])>;
+
+/// Gradient data starts with `color_count` stops of offset and rgb, optionally
+/// followed by alpha stops of offset and opacity
+fn split_gradient_stops(data: &[f32], color_count: usize) -> (Stops<3>, Stops<1>) {
+    let split = (color_count * 4).min(data.len());
+    let colors = data[..split]
+        .chunks_exact(4)
+        .map(|chunk| (chunk[0], [chunk[1], chunk[2], chunk[3]]))
+        .collect();
+    let alphas = data[split..]
+        .chunks_exact(2)
+        .map(|chunk| (chunk[0], [chunk[1]]))
+        .collect();
+    (colors, alphas)
+}
+
+fn stop_offsets<const N: usize>(stops: &[(f32, [f32; N])]) -> Vec<f32> {
+    stops.iter().map(|(offset, _)| *offset).collect()
+}
+
+/// Color and alpha stops sampled at `offsets`, as alpha stops may sit at
+/// other offsets than color stops
+fn f32_to_gradient_colors(data: &[f32], color_count: usize, offsets: &[f32]) -> Vec<GradientColor> {
+    let (colors, alphas) = split_gradient_stops(data, color_count);
+    offsets
+        .iter()
+        .enumerate()
+        .map(|(index, &offset)| {
+            let occurrence = occurrence(offsets, index);
+            let [r, g, b] = sample_stops(&colors, offset, occurrence).unwrap_or([0.0; 3]);
+            let [a] = sample_stops(&alphas, offset, occurrence).unwrap_or([1.0]);
+            GradientColor {
+                offset,
+                color: Rgba::new_f32(r, g, b, a),
+            }
+        })
+        .collect()
+}
+
+/// Sample sorted gradient `stops` at `offsets`
+pub(crate) fn resample_gradient_colors(
+    stops: &[GradientColor],
+    offsets: &[f32],
+) -> Vec<GradientColor> {
+    let stops = stops
+        .iter()
+        .map(|stop| {
+            let color = stop.color;
+            let channels = [color.r, color.g, color.b, color.a].map(|c| c as f32 / 255.0);
+            (stop.offset, channels)
+        })
+        .collect::<Vec<_>>();
+    offsets
+        .iter()
+        .enumerate()
+        .map(|(index, &offset)| {
+            let [r, g, b, a] = sample_stops(&stops, offset, occurrence(offsets, index))
+                .unwrap_or([0.0, 0.0, 0.0, 1.0]);
+            GradientColor {
+                offset,
+                color: Rgba::new_f32(r, g, b, a),
+            }
+        })
+        .collect()
+}
+
+/// Sorted union of sets of sorted offsets. An offset repeated for a hard edge
+/// is kept as many times as the set that repeats it most
+pub(crate) fn merge_offsets(sets: impl Iterator<Item = Vec<f32>>) -> Vec<f32> {
+    let mut counts: Vec<(f32, usize)> = vec![];
+    for offsets in sets {
+        for index in 0..offsets.len() {
+            let count = occurrence(&offsets, index) + 1;
+            match counts
+                .iter_mut()
+                .find(|(offset, _)| *offset == offsets[index])
+            {
+                Some((_, max)) => *max = (*max).max(count),
+                None => counts.push((offsets[index], count)),
+            }
+        }
+    }
+    counts.sort_by(|a, b| a.0.total_cmp(&b.0));
+    counts
+        .into_iter()
+        .flat_map(|(offset, count)| std::iter::repeat_n(offset, count))
+        .collect()
+}
+
+/// How many offsets before `index` equal the one at it
+fn occurrence(offsets: &[f32], index: usize) -> usize {
+    offsets[..index]
+        .iter()
+        .filter(|offset| **offset == offsets[index])
+        .count()
+}
+
+/// Linearly interpolate sorted gradient stops at `offset`. Of several stops
+/// right at `offset`, the one at `occurrence` is taken, or the last one
+fn sample_stops<const N: usize>(
+    stops: &[(f32, [f32; N])],
+    offset: f32,
+    occurrence: usize,
+) -> Option<[f32; N]> {
+    let next = stops.iter().position(|(o, _)| *o >= offset);
+    match next {
+        Some(index) if stops[index].0 == offset => {
+            let same = stops[index..]
+                .iter()
+                .take_while(|(o, _)| *o == offset)
+                .count();
+            Some(stops[index + occurrence.min(same - 1)].1)
+        }
+        Some(0) => Some(stops[0].1),
+        Some(index) => {
+            let (start, from) = stops[index - 1];
+            let (end, to) = stops[index];
+            let t = (offset - start) / (end - start);
+            Some(std::array::from_fn(|i| from[i] + (to[i] - from[i]) * t))
+        }
+        None => stops.last().map(|(_, value)| *value),
     }
 }
 
@@ -363,12 +633,13 @@ impl From<ColorList> for ColorListHelper {
                         keyframe.alter_value(start, end)
                     })
                     .collect(),
+                expression: list.colors.expression,
             },
         }
     }
 }
 
-fn gradient_colors_to_f32(data: &Vec<GradientColor>) -> Vec<f32> {
+fn gradient_colors_to_f32(data: &[GradientColor]) -> Vec<f32> {
     let mut start = data
         .iter()
         .flat_map(|color| {
@@ -1,25 +1,38 @@
 use super::{Bezier, Rgb, TextDocument, Value, Vector2D};
 
-pub trait FromTo<T> {
-    fn from(v: T) -> Self;
+pub trait FromTo<T>: Sized {
+    /// Convert `v`, failing with a description of the value which was
+    /// expected instead
+    fn from(v: T) -> Result<Self, &'static str>;
     fn to(self) -> T;
 }
 
+/// Numbers of `v`, which must have at least `len` of them
+pub(crate) fn numbers(
+    v: Value,
+    len: usize,
+    expected: &'static str,
+) -> Result<Vec<f32>, &'static str> {
+    match v.as_f32_vec() {
+        Some(v) if v.len() >= len => Ok(v),
+        _ => Err(expected),
+    }
+}
+
 impl FromTo<Value> for Vector2D {
-    fn from(v: Value) -> Self {
-        let v = v.as_f32_vec().unwrap();
-        Vector2D::new(v[0], v.get(1).cloned().unwrap_or(0.0))
+    fn from(v: Value) -> Result<Self, &'static str> {
+        let v = numbers(v, 1, "a vector")?;
+        Ok(Vector2D::new(v[0], v.get(1).cloned().unwrap_or(0.0)))
     }
 
     fn to(self) -> Value {
-        todo!()
+        Value::List(vec![self.x, self.y])
     }
 }
 
 impl FromTo<Value> for f32 {
-    fn from(v: Value) -> Self {
-        let v = v.as_f32_vec().unwrap();
-        v[0]
+    fn from(v: Value) -> Result<Self, &'static str> {
+        Ok(numbers(v, 1, "a number")?[0])
     }
 
     fn to(self) -> Value {
@@ -28,13 +41,13 @@ impl FromTo<Value> for f32 {
 }
 
 impl FromTo<Value> for Rgb {
-    fn from(v: Value) -> Self {
-        let v = v.as_f32_vec().unwrap();
-        if v[0] > 1.0 && v[0] <= 255.0 {
+    fn from(v: Value) -> Result<Self, &'static str> {
+        let v = numbers(v, 3, "an RGB color")?;
+        Ok(if v[0] > 1.0 && v[0] <= 255.0 {
             Rgb::new_u8(v[0] as u8, v[1] as u8, v[2] as u8)
         } else {
             Rgb::new_f32(v[0], v[1], v[2])
-        }
+        })
     }
 
     fn to(self) -> Value {
@@ -47,11 +60,11 @@ impl FromTo<Value> for Rgb {
 }
 
 impl FromTo<Value> for Vec<Bezier> {
-    fn from(v: Value) -> Self {
+    fn from(v: Value) -> Result<Self, &'static str> {
         match v {
-            Value::ComplexBezier(b) => b,
-            Value::Bezier(b) => vec![b],
-            _ => todo!(),
+            Value::ComplexBezier(b) => Ok(b),
+            Value::Bezier(b) => Ok(vec![b]),
+            _ => Err("a bezier path"),
         }
     }
 
@@ -61,11 +74,11 @@ impl FromTo<Value> for Vec<Bezier> {
 }
 
 impl FromTo<Value> for Vec<f32> {
-    fn from(v: Value) -> Self {
+    fn from(v: Value) -> Result<Self, &'static str> {
         match v {
-            Value::Primitive(f) => vec![f],
-            Value::List(l) => l,
-            _ => todo!(),
+            Value::Primitive(f) => Ok(vec![f]),
+            Value::List(l) => Ok(l),
+            _ => Err("a list of numbers"),
         }
     }
 
@@ -75,10 +88,10 @@ impl FromTo<Value> for Vec<f32> {
 }
 
 impl FromTo<Value> for TextDocument {
-    fn from(v: Value) -> Self {
+    fn from(v: Value) -> Result<Self, &'static str> {
         match v {
-            Value::TextDocument(t) => t,
-            _ => todo!(),
+            Value::TextDocument(t) => Ok(t),
+            _ => Err("a text document"),
         }
     }
 
@@ -1,14 +1,14 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use super::{Easing, FromTo, KeyFrame, Value};
+use super::{Easing, FromTo, KeyFrame, Value, Vector2D};
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(transparent)]
 pub(super) struct AnimatedHelper {
     data: TolerantAnimatedHelper,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(untagged)]
 enum TolerantAnimatedHelper {
     Plain(Value),
@@ -19,51 +19,154 @@ fn default_none<T>() -> Option<T> {
     None
 }
 
-#[derive(Deserialize, Default, Debug, Clone)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 struct LegacyKeyFrame<T> {
     #[serde(rename = "s")]
     start_value: T,
-    #[serde(rename = "e", default = "default_none")]
+    #[serde(
+        rename = "e",
+        default = "default_none",
+        skip_serializing_if = "Option::is_none"
+    )]
     end_value: Option<T>,
     #[serde(rename = "t", default)]
     start_frame: f32,
     #[serde(skip)]
     end_frame: f32,
-    #[serde(rename = "o", default)]
+    #[serde(rename = "o", default, skip_serializing_if = "Option::is_none")]
     easing_out: Option<Easing>,
-    #[serde(rename = "i", default)]
+    #[serde(rename = "i", default, skip_serializing_if = "Option::is_none")]
     easing_in: Option<Easing>,
-    #[serde(rename = "h", default, deserialize_with = "super::bool_from_int")]
+    #[serde(
+        rename = "h",
+        default,
+        deserialize_with = "super::bool_from_int",
+        serialize_with = "super::int_from_bool",
+        skip_serializing_if = "std::ops::Not::not"
+    )]
     hold: bool,
+    #[serde(rename = "to", default, skip_serializing_if = "Option::is_none")]
+    out_tangent: Option<Vec<f32>>,
+    #[serde(rename = "ti", default, skip_serializing_if = "Option::is_none")]
+    in_tangent: Option<Vec<f32>>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(untagged)]
 enum LegacyTolerantKeyFrame {
     LegacyKeyFrame(LegacyKeyFrame<Value>),
     TOnly { t: f32 },
 }
 
-impl<'a, T> From<&'a Vec<KeyFrame<T>>> for AnimatedHelper {
-    fn from(_: &'a Vec<KeyFrame<T>>) -> Self {
-        todo!()
+impl<'a, T> From<&'a Vec<KeyFrame<T>>> for AnimatedHelper
+where
+    T: FromTo<Value> + Clone,
+{
+    fn from(keyframes: &'a Vec<KeyFrame<T>>) -> Self {
+        if let [keyframe] = keyframes.as_slice() {
+            if keyframe.easing_in.is_none()
+                && keyframe.easing_out.is_none()
+                && !keyframe.hold
+                && keyframe.start_frame == 0.0
+                && keyframe.end_frame == 0.0
+            {
+                match keyframe.start_value.clone().to() {
+                    // Text documents are always keyframed
+                    Value::TextDocument(_) => {}
+                    Value::ComplexBezier(mut b) if b.len() == 1 => {
+                        return AnimatedHelper {
+                            data: TolerantAnimatedHelper::Plain(Value::Bezier(b.remove(0))),
+                        }
+                    }
+                    v => {
+                        return AnimatedHelper {
+                            data: TolerantAnimatedHelper::Plain(v),
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut result = vec![];
+        for (index, keyframe) in keyframes.iter().enumerate() {
+            let start_value = keyframed_value(keyframe.start_value.clone().to());
+            let end_value = keyframed_value(keyframe.end_value.clone().to());
+            let next = keyframes.get(index + 1);
+            // A trailing `t`-only keyframe drops the last keyframe unless it
+            // has an explicit end value
+            let has_end_frame = next.is_none() && keyframe.end_frame > keyframe.start_frame;
+            let implied_end = match next {
+                Some(next) => keyframed_value(next.start_value.clone().to()),
+                None => start_value.clone(),
+            };
+            let end_value = if has_end_frame || !same_value(&end_value, &implied_end) {
+                Some(end_value)
+            } else {
+                None
+            };
+            result.push(LegacyTolerantKeyFrame::LegacyKeyFrame(LegacyKeyFrame {
+                start_value,
+                end_value,
+                start_frame: keyframe.start_frame,
+                end_frame: keyframe.end_frame,
+                easing_out: keyframe.easing_out.clone(),
+                easing_in: keyframe.easing_in.clone(),
+                hold: keyframe.hold,
+                out_tangent: keyframe.out_tangent.map(|t| vec![t.x, t.y]),
+                in_tangent: keyframe.in_tangent.map(|t| vec![t.x, t.y]),
+            }));
+            if has_end_frame {
+                result.push(LegacyTolerantKeyFrame::TOnly {
+                    t: keyframe.end_frame,
+                });
+            }
+        }
+        AnimatedHelper {
+            data: TolerantAnimatedHelper::AnimatedHelper(result),
+        }
+    }
+}
+
+/// Keyframe values are always wrapped in arrays, even for scalars and single
+/// beziers
+fn keyframed_value(value: Value) -> Value {
+    match value {
+        Value::Primitive(p) => Value::List(vec![p]),
+        Value::Bezier(b) => Value::ComplexBezier(vec![b]),
+        v => v,
+    }
+}
+
+fn tangent(t: Vec<f32>) -> Option<Vector2D> {
+    Some(Vector2D::new(*t.first()?, t.get(1).cloned().unwrap_or(0.0)))
+}
+
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (serde_json::to_value(a), serde_json::to_value(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
     }
 }
 
-impl<T> From<AnimatedHelper> for Vec<KeyFrame<T>>
+impl<T> TryFrom<AnimatedHelper> for Vec<KeyFrame<T>>
 where
     T: FromTo<Value>,
 {
-    fn from(animated: AnimatedHelper) -> Self {
-        match animated.data {
+    type Error = &'static str;
+
+    fn try_from(animated: AnimatedHelper) -> Result<Self, Self::Error> {
+        Ok(match animated.data {
             TolerantAnimatedHelper::Plain(v) => {
                 vec![KeyFrame {
-                    start_value: T::from(v.clone()),
-                    end_value: T::from(v),
+                    start_value: T::from(v.clone())?,
+                    end_value: T::from(v)?,
                     start_frame: 0.0,
                     end_frame: 0.0,
                     easing_in: None,
                     easing_out: None,
+                    hold: false,
+                    out_tangent: None,
+                    in_tangent: None,
                 }]
             }
             TolerantAnimatedHelper::AnimatedHelper(v) => {
@@ -73,13 +176,10 @@ where
                 let mut has_t_only_frame = false;
                 for k in v {
                     match k {
-                        LegacyTolerantKeyFrame::LegacyKeyFrame(mut k) => {
+                        LegacyTolerantKeyFrame::LegacyKeyFrame(k) => {
                             if let Some(prev) = result.last_mut() {
                                 prev.end_frame = k.start_frame;
                             }
-                            if k.hold {
-                                k.end_value = Some(k.start_value.clone());
-                            }
                             result.push(k)
                         }
                         LegacyTolerantKeyFrame::TOnly { t } => {
@@ -101,27 +201,32 @@ where
                 if has_t_only_frame
                     && result
                         .last()
-                        .map(|keyframe| keyframe.end_value.is_none())
+                        .map(|keyframe| keyframe.end_value.is_none() && !keyframe.hold)
                         .unwrap_or(false)
                 {
                     result.pop();
                 }
                 result
                     .into_iter()
-                    .map(|keyframe| KeyFrame {
-                        end_value: T::from(
-                            keyframe
-                                .end_value
-                                .unwrap_or_else(|| keyframe.start_value.clone()),
-                        ),
-                        start_value: T::from(keyframe.start_value),
-                        start_frame: keyframe.start_frame,
-                        end_frame: keyframe.end_frame.max(keyframe.start_frame),
-                        easing_in: keyframe.easing_in,
-                        easing_out: keyframe.easing_out,
+                    .map(|keyframe| {
+                        Ok(KeyFrame {
+                            end_value: T::from(
+                                keyframe
+                                    .end_value
+                                    .unwrap_or_else(|| keyframe.start_value.clone()),
+                            )?,
+                            start_value: T::from(keyframe.start_value)?,
+                            start_frame: keyframe.start_frame,
+                            end_frame: keyframe.end_frame.max(keyframe.start_frame),
+                            easing_in: keyframe.easing_in,
+                            easing_out: keyframe.easing_out,
+                            hold: keyframe.hold,
+                            out_tangent: keyframe.out_tangent.and_then(tangent),
+                            in_tangent: keyframe.in_tangent.and_then(tangent),
+                        })
                     })
-                    .collect()
+                    .collect::<Result<_, Self::Error>>()?
             }
-        }
+        })
     }
 }
@@ -17,6 +17,15 @@ pub struct HeadlessConfig {
     pub frame: Option<u32>,
 }
 
+/// A rendered frame, in RGBA8 format without premultiplied alpha
+pub struct FrameData {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Timestamp of this frame, in milliseconds
+    pub timestamp: i32,
+}
+
 pub enum Config {
     Window(WindowConfig),
     Headless(HeadlessConfig),
@@ -28,4 +37,8 @@ pub trait Renderer {
     fn load_lottie(&mut self, lottie: Lottie, config: Config);
     /// Render the lottie file, possibly mutating self
     fn render(&mut self);
+    /// Render exactly the given `frame` of the loaded [Lottie] synchronously.
+    /// Returns `None` if no lottie is loaded or the renderer is not able to
+    /// produce frames on demand with current [Config]
+    fn render_frame(&mut self, frame: f32) -> Option<FrameData>;
 }
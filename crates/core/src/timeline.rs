@@ -74,6 +74,21 @@ impl Timeline {
         self.store.get(id)
     }
 
+    /// Closest composited precomposition or media layer `layer` is part of the
+    /// content of. Parents in the same composition only share their transform
+    pub fn compositing_layer(&self, layer: &StagedLayer) -> Option<&StagedLayer> {
+        let mut current = layer;
+        while let Some(parent) = current.parent.and_then(|id| self.item(id)) {
+            let is_content =
+                matches!(current.target, TargetRef::Asset(_)) && parent.target != current.target;
+            if is_content && parent.is_composited() {
+                return Some(parent);
+            }
+            current = parent;
+        }
+        None
+    }
+
     pub(crate) fn new(model: &Model, fontdb: &FontDB, root_path: &str) -> Result<Self, Error> {
         let mut timeline = Timeline {
             start_frame: 0.0,
@@ -102,6 +117,7 @@ impl Timeline {
             .collect::<VecDeque<_>>();
         let default_frame_rate = model.frame_rate;
         let mut previous = None;
+        let mut pending_mattes = vec![];
         while !layers.is_empty() {
             let LayerInfo {
                 layer,
@@ -170,50 +186,48 @@ impl Timeline {
             }
 
             let content = ContentInfo::from_layer(layer.clone(), model, fontdb, root_path)?;
+            let (content, masks) = match content {
+                ContentInfo::ContentWithMasks { content, masks } => (*content, masks),
+                content => (content, vec![]),
+            };
             let mut ids = vec![];
-            match content {
-                ContentInfo::Simple(c) => ids.push(timeline.add_item(c.into_stage_layer(&layer))),
+            let mut mask_stack = vec![];
+            for (mask, composition) in masks {
+                let mut mask = mask.into_stage_layer(&layer);
+                mask.is_mask = true;
+                mask.matte_mode = None;
+                mask.effects.clear();
+                let id = timeline.add_item(mask);
+                ids.push(id);
+                mask_stack.push(StagedLayerMask {
+                    id,
+                    mode: MatteMode::Alpha,
+                    composition: Some(composition),
+                });
+            }
+            let staged_layers = match content {
+                ContentInfo::Simple(c) => vec![c.into_stage_layer(&layer)],
                 ContentInfo::TextKeyframes(mut keyframes) => {
                     if let Some(end) = keyframes.last_mut().map(|info| &mut info.end_frame) {
                         *end = layer.end_frame;
                     }
-                    for keyframe in keyframes {
-                        let mut layer = keyframe.content.into_stage_layer(&layer);
-                        layer.start_frame = keyframe.start_frame;
-                        layer.end_frame = keyframe.end_frame;
-                        ids.push(timeline.add_item(layer))
-                    }
-                }
-                ContentInfo::ContentWithMasks { content, masks } => {
-                    let (layers, matte_modes): (Vec<StagedLayer>, Vec<MatteMode>) = masks
-                        .into_iter()
-                        .map(|m| (m.0.into_stage_layer(&layer), m.1))
-                        .unzip();
-                    let mut target_layer = content.into_stage_layer(&layer);
-                    ids = layers
+                    keyframes
                         .into_iter()
-                        .map(|mut layer| {
-                            layer.is_mask = true;
-                            timeline.add_item(layer)
+                        .map(|keyframe| {
+                            let mut layer = keyframe.content.into_stage_layer(&layer);
+                            layer.start_frame = keyframe.start_frame;
+                            layer.end_frame = keyframe.end_frame;
+                            layer
                         })
-                        .collect();
-                    for (id, matte_mode) in ids.iter().zip(matte_modes.into_iter()) {
-                        target_layer.mask_hierarchy.stack.push(StagedLayerMask {
-                            id: *id,
-                            mode: matte_mode,
-                        })
-                    }
-                    ids.push(timeline.add_item(target_layer));
+                        .collect()
                 }
+                ContentInfo::ContentWithMasks { .. } => unreachable!(),
+            };
+            for mut staged in staged_layers {
+                staged.mask_hierarchy.stack = mask_stack.clone();
+                ids.push(timeline.add_item(staged));
             }
-            for id in &ids {
-                let matte_mode = timeline.store.get(*id).unwrap().matte_mode;
-                if let (Some(id), Some(mode)) = (previous, matte_mode) {
-                    if mode != MatteMode::Normal {
-                        let prev = timeline.store.get_mut(id).unwrap();
-                        prev.is_mask = true;
-                    }
-                }
+            for (index, id) in ids.iter().enumerate() {
                 let staged = timeline.store.get_mut(*id).unwrap();
                 staged.target = target_ref.clone();
                 staged.parent = parent;
@@ -221,12 +235,29 @@ impl Timeline {
                 staged.frame_rate = default_frame_rate;
                 staged.frame_transform.time_remapping = time_remapping.clone();
                 staged.frame_transform.frame_rate = default_frame_rate;
-                if let (Some(id), Some(mode)) = (previous, matte_mode) {
-                    if mode != MatteMode::Normal {
-                        staged
-                            .mask_hierarchy
-                            .stack
-                            .push(StagedLayerMask { id, mode });
+                // mask layers are never used as track mattes
+                if index < mask_stack.len() {
+                    continue;
+                }
+                if layer.is_track_matte {
+                    staged.is_mask = true;
+                }
+                match (layer.matte_parent, staged.matte_mode) {
+                    (_, None | Some(MatteMode::Normal)) => {}
+                    // resolved once every layer of the composition is known
+                    (Some(matte_parent), Some(mode)) => {
+                        pending_mattes.push((*id, matte_parent, mode, parent_map.clone()))
+                    }
+                    // without a matte parent, the layer above is the matte
+                    (None, Some(mode)) => {
+                        if let Some(previous) = previous {
+                            staged.mask_hierarchy.stack.push(StagedLayerMask {
+                                id: previous,
+                                mode,
+                                composition: None,
+                            });
+                            timeline.store.get_mut(previous).unwrap().is_mask = true;
+                        }
                     }
                 }
                 previous = Some(*id);
@@ -266,6 +297,19 @@ impl Timeline {
                 }
             }
         }
+        for (id, matte_parent, mode, parent_map) in pending_mattes {
+            let matte = match parent_map.borrow().get(&matte_parent) {
+                Some(matte) => *matte,
+                None => continue,
+            };
+            timeline.store.get_mut(matte).unwrap().is_mask = true;
+            let staged = timeline.store.get_mut(id).unwrap();
+            staged.mask_hierarchy.stack.push(StagedLayerMask {
+                id: matte,
+                mode,
+                composition: None,
+            });
+        }
         timeline.fix_zindex(force_zindex_ids);
         timeline.build_opacity_hierarchy();
         timeline.build_frame_hierarchy();
@@ -354,11 +398,18 @@ impl Timeline {
         for id in ids {
             let mut layer = self.store.get(id);
             let mut info = vec![];
+            let mut inherit = true;
             while let Some(l) = layer {
-                for mask in l.mask_hierarchy.masks() {
-                    info.push(*mask);
+                if inherit {
+                    info.extend(l.mask_hierarchy.masks().iter().cloned());
                 }
-                layer = l.parent.and_then(|id| self.store.get(id));
+                let parent = l.parent.and_then(|id| self.store.get(id));
+                // Masks of a precomposition or media layer apply to all of
+                // its content, which lives in another asset. Parents in the
+                // same composition only share their transform
+                inherit = matches!(l.target, TargetRef::Asset(_))
+                    && parent.is_some_and(|parent| parent.target != l.target);
+                layer = parent;
             }
             self.store.get_mut(id).unwrap().mask_hierarchy.stack = info;
         }
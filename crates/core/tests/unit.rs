@@ -1,7 +1,16 @@
 use std::fs;
 use std::io::Error;
+use std::path::{Path, PathBuf};
 
-use lottie_core::prelude::{Animated, GradientFill, Stroke, TextRange, Transform, Vector2D};
+use glam::{Mat4, Vec3};
+use lottie_core::prelude::{
+    apply_effects, apply_path_operations, dash_path, modify_path, Animated, Bezier, ColorList,
+    Effect, EffectType, EffectValueType, Fill, GradientColor, GradientFill, LayerEffect, Model,
+    PathFactory, PathOperation, Position, Rectangle, RenderableContent, Rgba, Shape, ShapeGroup,
+    Stroke, StyledShapeIterator, TextRange, Transform, Vector2D,
+};
+use lottie_core::Lottie;
+use lyon_algorithms::measure::PathMeasurements;
 
 #[test]
 fn test_transform_complex() -> Result<(), Error> {
@@ -39,6 +48,224 @@ fn test_gradient_fill() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_gradient_alpha_stops() -> Result<(), Error> {
+    let json = r#"{
+        "ty": "gf", "o": { "a": 0, "k": 100 }, "r": 1, "t": 2,
+        "s": { "a": 0, "k": [0, 0] }, "e": { "a": 0, "k": [10, 0] },
+        "h": { "a": 0, "k": 50 }, "a": { "a": 0, "k": 90 },
+        "g": {
+            "p": 2,
+            "k": { "a": 0, "k": [0, 1, 0, 0, 1, 0, 0, 1, 0, 1, 0.5, 0, 1, 1] }
+        }
+    }"#;
+    let fill: GradientFill = serde_json::from_str(json)?;
+    let stops = fill.gradient.colors.colors.initial_value();
+    // color and alpha stops are sampled at the offsets of both
+    let offsets = stops.iter().map(|stop| stop.offset).collect::<Vec<_>>();
+    assert_eq!(offsets, vec![0.0, 0.5, 1.0]);
+    let middle = stops[1].color;
+    assert_eq!((middle.r, middle.g, middle.b, middle.a), (128, 0, 128, 0));
+    assert_eq!(stops[2].color.a, 255);
+    // the highlight moves the focal point by half the radius, at a right
+    // angle to the gradient line
+    let focal = fill.gradient.focal_point(0.0);
+    assert!((focal - Vector2D::new(0.0, 5.0)).length() < 1e-4);
+    // merged stops survive a round trip
+    let json = serde_json::to_value(&fill)?;
+    let round_tripped: GradientFill = serde_json::from_value(json)?;
+    let colors = |stops: &[GradientColor]| {
+        stops
+            .iter()
+            .map(|stop| (stop.offset, stop.color.r, stop.color.b, stop.color.a))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        colors(&round_tripped.gradient.colors.colors.initial_value()),
+        colors(&stops)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_gradient_alpha_stop_keyframes() -> Result<(), Error> {
+    let json = r#"{
+        "p": 2,
+        "k": {
+            "a": 1,
+            "k": [
+                {
+                    "t": 0, "s": [0, 1, 0, 0, 1, 0, 0, 1, 0, 1, 1, 1],
+                    "o": { "x": [0], "y": [0] }, "i": { "x": [1], "y": [1] }
+                },
+                { "t": 10, "s": [0, 1, 0, 0, 1, 0, 0, 1, 0, 1, 0.5, 0, 1, 1] }
+            ]
+        }
+    }"#;
+    let list: ColorList = serde_json::from_str(json)?;
+    // both keyframes are sampled at the alpha offsets of either
+    for keyframe in &list.colors.keyframes {
+        let offsets = keyframe
+            .start_value
+            .iter()
+            .map(|stop| stop.offset)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![0.0, 0.5, 1.0]);
+    }
+    let stops = list.colors.value(5.0);
+    assert_eq!(stops.len(), 3);
+    let middle = stops[1].color;
+    assert_eq!((middle.r, middle.b, middle.a), (128, 128, 127));
+    assert_eq!(stops[2].color.a, 255);
+    Ok(())
+}
+
+#[test]
+fn test_effects() -> Result<(), Error> {
+    let json = r#"[
+        {
+            "ty": 21, "nm": "Fill", "en": 1,
+            "ef": [
+                { "ty": 10, "nm": "Fill Mask", "v": { "a": 0, "k": 0 } },
+                { "ty": 7, "nm": "All Masks", "v": { "a": 0, "k": 0 } },
+                { "ty": 2, "nm": "Color", "v": { "a": 0, "k": [0, 0, 1, 1] } },
+                { "ty": 7, "nm": "Invert", "v": { "a": 0, "k": 0 } },
+                { "ty": 0, "nm": "Horizontal Feather", "v": { "a": 0, "k": 0 } },
+                { "ty": 0, "nm": "Vertical Feather", "v": { "a": 0, "k": 0 } },
+                { "ty": 0, "nm": "Opacity", "v": { "a": 0, "k": 0.5 } }
+            ]
+        },
+        {
+            "ty": 5, "nm": "Controls", "en": 1,
+            "ef": [
+                { "ty": 6, "nm": "Group", "v": 0 },
+                { "ty": 0, "nm": "Slider", "v": { "a": 0, "k": 10 } }
+            ]
+        },
+        { "ty": 29, "nm": "Blur", "en": 0, "ef": [] }
+    ]"#;
+    let effects: Vec<Effect> = serde_json::from_str(json)?;
+    assert_eq!(effects[1].ty, EffectType::Custom);
+    assert!(matches!(
+        effects[1].values[0].value,
+        EffectValueType::Other { ty: 6, .. }
+    ));
+    assert_eq!(effects[1].number(1).map(|v| v.initial_value()), Some(10.0));
+    let round_tripped: Vec<Effect> = serde_json::from_value(serde_json::to_value(&effects)?)?;
+    assert_eq!(round_tripped[0].values.len(), 7);
+    // only the fill is rendered, custom and disabled effects are skipped
+    let layer_effects = effects
+        .iter()
+        .filter_map(LayerEffect::from_effect)
+        .collect::<Vec<_>>();
+    assert_eq!(layer_effects.len(), 1);
+    // premultiplied pixels, the transparent one stays untouched
+    let mut pixels = [255, 0, 0, 255, 0, 0, 0, 0];
+    apply_effects(&layer_effects, &mut pixels, 2, 1, 0.0, 1.0);
+    assert_eq!(pixels, [0, 0, 128, 128, 0, 0, 0, 0]);
+    Ok(())
+}
+
+#[test]
+fn test_effect_blur_and_shadow() -> Result<(), Error> {
+    let json = r#"[
+        {
+            "ty": 25, "nm": "Drop Shadow",
+            "ef": [
+                { "ty": 2, "nm": "Shadow Color", "v": { "a": 0, "k": [0, 0, 0, 1] } },
+                { "ty": 0, "nm": "Opacity", "v": { "a": 0, "k": 255 } },
+                { "ty": 1, "nm": "Direction", "v": { "a": 0, "k": 90 } },
+                { "ty": 0, "nm": "Distance", "v": { "a": 0, "k": 2 } },
+                { "ty": 0, "nm": "Softness", "v": { "a": 0, "k": 0 } },
+                { "ty": 7, "nm": "Shadow Only", "v": { "a": 0, "k": 0 } }
+            ]
+        },
+        {
+            "ty": 29, "nm": "Gaussian Blur",
+            "ef": [
+                { "ty": 0, "nm": "Blurriness", "v": { "a": 1, "k": [
+                    { "t": 0, "s": [0], "o": { "x": [0], "y": [0] }, "i": { "x": [1], "y": [1] } },
+                    { "t": 10, "s": [10] }
+                ] } },
+                { "ty": 7, "nm": "Blur Dimensions", "v": { "a": 0, "k": 2 } }
+            ]
+        }
+    ]"#;
+    let effects: Vec<Effect> = serde_json::from_str(json)?;
+    let effects = effects
+        .iter()
+        .filter_map(LayerEffect::from_effect)
+        .collect::<Vec<_>>();
+    assert_eq!(effects.len(), 2);
+    assert!(effects[1].is_animated());
+    // a shadow two pixels to the right of a single white pixel
+    let mut pixels = vec![0; 5 * 4];
+    pixels[4..8].copy_from_slice(&[255; 4]);
+    effects[0].apply(&mut pixels, 5, 1, 0.0, 1.0);
+    assert_eq!(&pixels[4..8], &[255; 4]);
+    assert_eq!(&pixels[12..16], &[0, 0, 0, 255]);
+    assert_eq!(pixels[3], 0);
+    // horizontal blur spreads the pixels sideways only
+    let (sigma_x, sigma_y) = effects[1].blur_sigma(10.0);
+    assert!(sigma_x > 0.0 && sigma_y == 0.0);
+    let mut pixels = vec![0; 5 * 3 * 4];
+    pixels[28..32].copy_from_slice(&[255; 4]);
+    effects[1].apply(&mut pixels, 5, 3, 10.0, 1.0);
+    assert!(pixels[27] > 0 && pixels[35] > 0);
+    assert!(pixels[28 - 20 + 3] == 0 && pixels[28 + 20 + 3] == 0);
+    Ok(())
+}
+
+#[test]
+fn test_effect_order() -> Result<(), Error> {
+    let json = r#"[
+        {
+            "ty": 25, "nm": "Drop Shadow",
+            "ef": [
+                { "ty": 2, "nm": "Shadow Color", "v": { "a": 0, "k": [0, 0, 0, 1] } },
+                { "ty": 0, "nm": "Opacity", "v": { "a": 0, "k": 255 } },
+                { "ty": 1, "nm": "Direction", "v": { "a": 0, "k": 90 } },
+                { "ty": 0, "nm": "Distance", "v": { "a": 0, "k": 1 } },
+                { "ty": 0, "nm": "Softness", "v": { "a": 0, "k": 0 } },
+                { "ty": 7, "nm": "Shadow Only", "v": { "a": 0, "k": 0 } }
+            ]
+        },
+        {
+            "ty": 21, "nm": "Fill",
+            "ef": [
+                { "ty": 10, "nm": "Fill Mask", "v": { "a": 0, "k": 0 } },
+                { "ty": 7, "nm": "All Masks", "v": { "a": 0, "k": 0 } },
+                { "ty": 2, "nm": "Color", "v": { "a": 0, "k": [0, 0, 1, 1] } },
+                { "ty": 7, "nm": "Invert", "v": { "a": 0, "k": 0 } },
+                { "ty": 0, "nm": "Horizontal Feather", "v": { "a": 0, "k": 0 } },
+                { "ty": 0, "nm": "Vertical Feather", "v": { "a": 0, "k": 0 } },
+                { "ty": 0, "nm": "Opacity", "v": { "a": 0, "k": 1 } }
+            ]
+        }
+    ]"#;
+    let effects: Vec<Effect> = serde_json::from_str(json)?;
+    let mut effects = effects
+        .iter()
+        .filter_map(LayerEffect::from_effect)
+        .collect::<Vec<_>>();
+    // a white pixel with its shadow one pixel to the right
+    let white = || {
+        let mut pixels = vec![0; 2 * 4];
+        pixels[..4].copy_from_slice(&[255; 4]);
+        pixels
+    };
+    // effects apply in stack order: a fill after the shadow recolors it
+    let mut pixels = white();
+    apply_effects(&effects, &mut pixels, 2, 1, 0.0, 1.0);
+    assert_eq!(pixels, [0, 0, 255, 255, 0, 0, 255, 255]);
+    // while the shadow of a filled layer keeps its own color
+    effects.reverse();
+    let mut pixels = white();
+    apply_effects(&effects, &mut pixels, 2, 1, 0.0, 1.0);
+    assert_eq!(pixels, [0, 0, 255, 255, 0, 0, 0, 255]);
+    Ok(())
+}
+
 #[test]
 fn test_text_range() -> Result<(), Error> {
     let file = fs::File::open("../../fixtures/segments/text_range.json")?;
@@ -56,3 +283,937 @@ fn test_legacy_animated_position() -> Result<(), Error> {
     println!("{:?}", d);
     Ok(())
 }
+
+#[test]
+fn test_legacy_animated_position_round_trip() -> Result<(), Error> {
+    let file = fs::File::open("../../fixtures/segments/animated_position_legacy.json")?;
+    let animated: Animated<Vector2D> = serde_json::from_reader(file)?;
+    let json = serde_json::to_value(&animated)?;
+    assert_eq!(json["k"].as_array().unwrap().len(), 3);
+    assert_eq!(json["k"][2], serde_json::json!({ "t": 14.0 }));
+    let round_tripped: Animated<Vector2D> = serde_json::from_value(json)?;
+    assert_eq!(format!("{:?}", round_tripped), format!("{:?}", animated));
+    Ok(())
+}
+
+/// Values of the wrong kind are reported as errors instead of panicking
+#[test]
+fn test_malformed_values() {
+    let error = serde_json::from_str::<Animated<Vector2D>>(r#"{ "a": 0, "k": [] }"#)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("expected a vector"), "{}", error);
+    let bezier = r#"{ "a": 0, "k": { "c": false, "i": [], "o": [], "v": [] } }"#;
+    assert!(serde_json::from_str::<Animated<f32>>(bezier).is_err());
+    let keyframes = r#"{ "a": 1, "k": [{ "t": 0, "s": [1] }, { "t": 10, "s": [2] }] }"#;
+    assert!(serde_json::from_str::<Animated<Vec<Bezier>>>(keyframes).is_err());
+    let fill = r#"{ "o": { "a": 0, "k": 100 }, "c": { "a": 0, "k": [1, 0] } }"#;
+    assert!(serde_json::from_str::<Fill>(fill).is_err());
+    let model = |position: &str| {
+        let json = format!(
+            r#"{{
+                "fr": 30, "ip": 0, "op": 60, "w": 100, "h": 100, "layers": [{{
+                    "ty": 3, "ip": 0, "op": 60, "st": 0,
+                    "ks": {{ "p": {{ "a": 0, "k": {} }} }}
+                }}]
+            }}"#,
+            position
+        );
+        Model::from_reader(json.as_bytes())
+    };
+    assert!(model("[10, 10]").is_ok());
+    assert!(model("[]").is_err());
+}
+
+#[test]
+fn test_split_position() -> Result<(), Error> {
+    let json = r#"{
+        "p": {
+            "s": true,
+            "x": { "a": 1, "k": [
+                { "t": 0, "s": [0], "o": { "x": [0], "y": [0] }, "i": { "x": [1], "y": [1] } },
+                { "t": 10, "s": [100] }
+            ] },
+            "y": { "a": 0, "k": 20 }
+        }
+    }"#;
+    let transform: Transform = serde_json::from_str(json)?;
+    let position = transform.position.as_ref().unwrap();
+    assert!(matches!(position, Position::Split(_)));
+    assert!(position.is_animated());
+    assert_eq!(position.value(5.0), Vector2D::new(50.0, 20.0));
+    assert_eq!(transform.frames(), 10.0);
+    let json = serde_json::to_value(&transform)?;
+    assert_eq!(json["p"]["s"], true);
+    assert_eq!(json["p"]["y"]["k"], 20.0);
+    Ok(())
+}
+
+#[test]
+fn test_hold_keyframes() -> Result<(), Error> {
+    let json = r#"{
+        "a": 1,
+        "k": [
+            { "t": 0, "s": [0], "h": 1 },
+            { "t": 10, "s": [100], "o": { "x": 0.5, "y": 0 }, "i": { "x": 0.5, "y": 1 } },
+            { "t": 20, "s": [50], "h": 1 },
+            { "t": 30 }
+        ]
+    }"#;
+    let mut animated: Animated<f32> = serde_json::from_str(json)?;
+    assert_eq!(animated.keyframes.len(), 3);
+    assert!(animated.keyframes[0].hold && !animated.keyframes[1].hold);
+    assert_eq!(animated.value(5.0), 0.0);
+    assert_eq!(animated.value(9.9), 0.0);
+    assert_eq!(animated.value(10.0), 100.0);
+    assert!(animated.value(15.0) < 100.0 && animated.value(15.0) > 50.0);
+    assert_eq!(animated.value(25.0), 50.0);
+    assert_eq!(animated.value(35.0), 50.0);
+
+    let (a, b) = animated.keyframes[0].split(4.0);
+    let (a, b) = (a.unwrap(), b.unwrap());
+    assert!(a.hold && b.hold);
+    assert_eq!((a.start_frame, a.end_frame, b.end_frame), (0.0, 4.0, 10.0));
+    assert_eq!(
+        (a.value(1.0), b.value(0.5), b.value(1.0)),
+        (0.0, 0.0, 100.0)
+    );
+
+    let json = serde_json::to_value(&animated)?;
+    assert_eq!(json["k"][0]["h"], 1);
+    assert!(json["k"][1].get("h").is_none());
+    let round_tripped: Animated<f32> = serde_json::from_value(json)?;
+    assert_eq!(format!("{:?}", round_tripped), format!("{:?}", animated));
+
+    animated.align_to_sorted_frames([4.0, 15.0, 25.0].into_iter());
+    assert_eq!(animated.keyframes.len(), 6);
+    assert_eq!(animated.value(5.0), 0.0);
+    assert_eq!(animated.value(10.0), 100.0);
+    assert_eq!(animated.value(27.0), 50.0);
+    Ok(())
+}
+
+#[test]
+fn test_split_eased_keyframe() -> Result<(), Error> {
+    let json = r#"{
+        "a": 1,
+        "k": [
+            { "t": 0, "s": [0], "o": { "x": [0.6], "y": [0] }, "i": { "x": [0.2], "y": [1] } },
+            { "t": 10, "s": [100] }
+        ]
+    }"#;
+    let animated: Animated<f32> = serde_json::from_str(json)?;
+    let (a, b) = animated.keyframes[0].split(3.0);
+    let split = Animated {
+        animated: true,
+        keyframes: vec![a.unwrap(), b.unwrap()],
+        expression: None,
+    };
+    // Both halves keep the easing of the original keyframe
+    for frame in [1.0, 2.0, 3.0, 4.5, 6.0, 8.0, 9.5] {
+        let (expected, actual) = (animated.value(frame), split.value(frame));
+        assert!((expected - actual).abs() < 0.5, "{} at {}", actual, frame);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_spatial_keyframes() -> Result<(), Error> {
+    let json = r#"{
+        "a": 1,
+        "k": [
+            { "t": 0, "s": [0, 0, 0], "to": [0, 50, 0], "ti": [0, 50, 0] },
+            { "t": 10, "s": [100, 0, 0], "to": [90, 0, 0], "ti": [0, 0, 0] },
+            { "t": 20, "s": [200, 0, 0] }
+        ]
+    }"#;
+    let animated: Animated<Vector2D> = serde_json::from_str(json)?;
+    let close = |a: Vector2D, b: Vector2D| (a - b).length() < 0.5;
+    // Symmetric arc bending towards +y
+    assert!(close(animated.value(5.0), Vector2D::new(50.0, 37.5)));
+    let direction = animated.keyframes[0].direction(0.0);
+    assert!((direction.angle_from_x_axis().to_degrees() - 90.0).abs() < 0.01);
+    // Straight path with uneven control points still moves at constant speed
+    assert!(close(animated.value(15.0), Vector2D::new(150.0, 0.0)));
+
+    let (a, b) = animated.keyframes[0].split(5.0);
+    let (a, b) = (a.unwrap(), b.unwrap());
+    assert!(close(a.value(0.5), animated.value(2.5)));
+    assert!(close(b.value(0.5), animated.value(7.5)));
+
+    let json = serde_json::to_value(&animated)?;
+    assert_eq!(json["k"][0]["to"], serde_json::json!([0.0, 50.0]));
+    let round_tripped: Animated<Vector2D> = serde_json::from_value(json)?;
+    assert_eq!(format!("{:?}", round_tripped), format!("{:?}", animated));
+    Ok(())
+}
+
+#[test]
+fn test_path_keyframes() -> Result<(), Error> {
+    let json = r#"{
+        "a": 1,
+        "k": [
+            { "t": 0, "s": [{ "c": true, "v": [[0, 0]], "i": [[0, 0]], "o": [[0, 0]] }] },
+            { "t": 10, "s": [{ "c": true, "v": [[100, 0]], "i": [[0, 0]], "o": [[0, 0]] }] }
+        ]
+    }"#;
+    let animated: Animated<Vec<Bezier>> = serde_json::from_str(json)?;
+    assert_eq!(
+        animated.value(1.0)[0].verticies[0],
+        Vector2D::new(10.0, 0.0)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_rounded_rectangle() -> Result<(), Error> {
+    let rect = |d: u8, r: f32| -> Result<Rectangle, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({
+            "d": d,
+            "p": { "a": 0, "k": [50, 50] },
+            "s": { "a": 0, "k": [100, 40] },
+            "r": { "a": 1, "k": [{ "t": 0, "s": [0] }, { "t": 10, "s": [r] }] }
+        }))
+    };
+    let points = |path: lyon_path::Path| {
+        path.iter()
+            .skip(1)
+            .map(|event| event.from())
+            .map(|p| (p.x.round(), p.y.round()))
+            .collect::<Vec<_>>()
+    };
+
+    let square = points(rect(1, 10.0)?.path(0.0));
+    assert_eq!(
+        square,
+        vec![(100.0, 30.0), (100.0, 70.0), (0.0, 70.0), (0.0, 30.0)]
+    );
+
+    let rounded = rect(1, 10.0)?;
+    assert!(rounded.is_animated());
+    let cw = points(rounded.path(10.0));
+    assert_eq!(cw.len(), 9);
+    assert_eq!(cw[0], (100.0, 40.0));
+    assert_eq!(cw[1], (100.0, 60.0));
+    assert_eq!(cw[2], (90.0, 70.0));
+    let ccw = points(rect(3, 10.0)?.path(10.0));
+    assert_eq!(ccw[0], (100.0, 40.0));
+    assert_eq!(ccw[1], (90.0, 30.0));
+
+    // Radius is clamped to half of the smaller side
+    let pill = points(rect(1, 100.0)?.path(10.0));
+    assert_eq!(pill[0], (100.0, 50.0));
+    assert_eq!(pill[2], (80.0, 70.0));
+    Ok(())
+}
+
+#[test]
+fn test_simultaneous_trim() -> Result<(), Error> {
+    let line = |y: f32, length: f32| {
+        serde_json::json!({
+            "ty": "sh",
+            "ks": { "a": 0, "k": {
+                "c": false,
+                "v": [[0, y], [length, y]],
+                "i": [[0, 0], [0, 0]],
+                "o": [[0, 0], [0, 0]]
+            }}
+        })
+    };
+    let trim = |m: u8, o: f32| {
+        serde_json::json!({
+            "ty": "tm",
+            "s": { "a": 0, "k": 0 },
+            "e": { "a": 0, "k": 50 },
+            "o": { "a": 0, "k": o },
+            "m": m
+        })
+    };
+    let stroke = serde_json::json!({
+        "ty": "st", "lc": 2, "lj": 2,
+        "o": { "a": 0, "k": 100 },
+        "w": { "a": 0, "k": 2 },
+        "c": { "a": 0, "k": [0, 0, 0] }
+    });
+    // Length of every drawn path, descending into groups
+    fn lengths(group: &ShapeGroup, operations: &[PathOperation]) -> Vec<f32> {
+        let mut result = vec![];
+        for shape in group.styled_shapes().with_operations(operations) {
+            match &shape.shape.shape {
+                Shape::Group { shapes } => {
+                    let group = ShapeGroup {
+                        shapes: shapes.clone(),
+                    };
+                    result.extend(lengths(&group, &shape.operations));
+                }
+                s => {
+                    let path = apply_path_operations(s.path(0.0), &shape.operations, 0.0);
+                    let length = PathMeasurements::from_path(&path, 1e-3).length();
+                    result.push(length.round());
+                }
+            }
+        }
+        result
+    }
+    let group = |shapes: Vec<serde_json::Value>| -> Result<ShapeGroup, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({ "shapes": shapes }))
+    };
+
+    let individually = group(vec![
+        line(0.0, 100.0),
+        line(10.0, 300.0),
+        trim(1, 0.0),
+        stroke.clone(),
+    ])?;
+    assert_eq!(lengths(&individually, &[]), vec![50.0, 150.0]);
+
+    let simultaneously = group(vec![
+        line(0.0, 100.0),
+        line(10.0, 300.0),
+        trim(2, 0.0),
+        stroke.clone(),
+    ])?;
+    assert_eq!(lengths(&simultaneously, &[]), vec![100.0, 100.0]);
+
+    // Offset by half a turn wraps the trimmed range around
+    let wrapped = group(vec![
+        line(0.0, 100.0),
+        line(10.0, 300.0),
+        trim(2, 180.0),
+        stroke.clone(),
+    ])?;
+    assert_eq!(lengths(&wrapped, &[]), vec![0.0, 200.0]);
+
+    // Shapes listed after the trim are not affected by it
+    let partial = group(vec![
+        line(0.0, 100.0),
+        trim(2, 0.0),
+        line(10.0, 300.0),
+        stroke.clone(),
+    ])?;
+    assert_eq!(lengths(&partial, &[]), vec![50.0, 300.0]);
+
+    // Nested groups share the length of the outer trim
+    let nested = group(vec![
+        serde_json::json!({ "ty": "gr", "it": [line(0.0, 100.0), stroke.clone()] }),
+        serde_json::json!({ "ty": "gr", "it": [line(10.0, 300.0), stroke.clone()] }),
+        trim(2, 0.0),
+    ])?;
+    assert_eq!(lengths(&nested, &[]), vec![100.0, 100.0]);
+
+    // Trims and modifiers apply in the order they are listed
+    let square = serde_json::json!({
+        "ty": "rc",
+        "p": { "a": 0, "k": [0, 0] },
+        "s": { "a": 0, "k": [100, 100] },
+        "r": { "a": 0, "k": 0 }
+    });
+    let rounded = serde_json::json!({ "ty": "rd", "r": { "a": 0, "k": 20 } });
+    let round_then_trim = group(vec![
+        square.clone(),
+        rounded.clone(),
+        trim(1, 0.0),
+        stroke.clone(),
+    ])?;
+    assert_eq!(lengths(&round_then_trim, &[]), vec![183.0]);
+    let trim_then_round = group(vec![square, trim(1, 0.0), rounded, stroke.clone()])?;
+    assert_eq!(lengths(&trim_then_round, &[]), vec![191.0]);
+    Ok(())
+}
+
+#[test]
+fn test_dash_path() -> Result<(), Error> {
+    let stroke: Stroke = serde_json::from_value(serde_json::json!({
+        "lc": 2, "lj": 2,
+        "o": { "a": 0, "k": 100 },
+        "w": { "a": 0, "k": 2 },
+        "c": { "a": 0, "k": [0, 0, 0] },
+        "d": [
+            { "n": "d", "v": { "a": 0, "k": 10 } },
+            { "n": "g", "v": { "a": 0, "k": 5 } },
+            { "n": "o", "v": { "a": 1, "k": [
+                { "t": 0, "s": [0], "o": { "x": [0], "y": [0] }, "i": { "x": [1], "y": [1] } },
+                { "t": 10, "s": [20] }
+            ] } }
+        ]
+    }))?;
+    // two lines of 50
+    let mut builder = lyon_path::Path::builder();
+    for y in [0.0, 10.0] {
+        builder.begin(lyon_path::math::point(0.0, y));
+        builder.line_to(lyon_path::math::point(50.0, y));
+        builder.end(false);
+    }
+    let path = builder.build();
+    // start and end of every dash as (y, x0, x1)
+    let dashes = |frame: f32| {
+        let dashed = dash_path(path.clone(), &stroke.dashes, frame);
+        let mut ranges = vec![];
+        let mut start = 0.0;
+        for event in dashed.iter() {
+            match event {
+                lyon_path::Event::Begin { at } => start = at.x,
+                lyon_path::Event::End { last, .. } => {
+                    ranges.push((last.y, start.round(), last.x.round()))
+                }
+                _ => {}
+            }
+        }
+        ranges
+    };
+    // every subpath starts the pattern anew
+    let pattern = [(0.0, 10.0), (15.0, 25.0), (30.0, 40.0), (45.0, 50.0)];
+    let expected = [0.0, 10.0]
+        .into_iter()
+        .flat_map(|y| pattern.map(|(x0, x1)| (y, x0, x1)))
+        .collect::<Vec<_>>();
+    assert_eq!(dashes(0.0), expected);
+    // an offset of 5 at frame 2.5 shifts the pattern back
+    let shifted = dashes(2.5)
+        .into_iter()
+        .filter(|(y, _, _)| *y == 0.0)
+        .map(|(_, x0, x1)| (x0, x1))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        shifted,
+        vec![(0.0, 5.0), (10.0, 20.0), (25.0, 35.0), (40.0, 50.0)]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_repeater() -> Result<(), Error> {
+    let group: ShapeGroup = serde_json::from_value(serde_json::json!({ "shapes": [
+        {
+            "ty": "rc",
+            "p": { "a": 0, "k": [0, 0] },
+            "s": { "a": 0, "k": [10, 10] },
+            "r": { "a": 0, "k": 0 }
+        },
+        {
+            "ty": "rp",
+            "c": { "a": 1, "k": [{ "t": 0, "s": [3] }, { "t": 10, "s": [2] }] },
+            "o": { "a": 0, "k": 0 },
+            "m": 2,
+            "tr": {
+                "p": { "a": 0, "k": [20, 0] },
+                "a": { "a": 0, "k": [0, 0] },
+                "s": { "a": 0, "k": [100, 100] },
+                "r": { "a": 0, "k": 0 },
+                "so": { "a": 0, "k": 100 },
+                "eo": { "a": 0, "k": 50 }
+            }
+        },
+        {
+            "ty": "fl",
+            "o": { "a": 0, "k": 100 },
+            "c": { "a": 0, "k": [1, 0, 0] }
+        }
+    ]}))?;
+    // Position and opacity of every drawn shape, descending into groups
+    fn copies(group: &ShapeGroup, frame: f32, matrix: Mat4, opacity: f32) -> Vec<(f32, f32)> {
+        let mut result = vec![];
+        for shape in group.styled_shapes() {
+            let matrix = matrix * shape.transform.value(frame);
+            let opacity = opacity * shape.transform.opacity_value(frame) / 100.0;
+            match &shape.shape.shape {
+                Shape::Group { shapes } => {
+                    let group = ShapeGroup {
+                        shapes: shapes.clone(),
+                    };
+                    result.extend(copies(&group, frame, matrix, opacity));
+                }
+                _ => result.push((matrix.transform_point3(Vec3::ZERO).x, opacity)),
+            }
+        }
+        result
+    }
+
+    let first = copies(&group, 0.0, Mat4::IDENTITY, 1.0);
+    assert_eq!(first, vec![(0.0, 1.0), (20.0, 0.75), (40.0, 0.5)]);
+    // Copies beyond the animated number of copies are hidden
+    let last = copies(&group, 10.0, Mat4::IDENTITY, 1.0);
+    assert_eq!(last, vec![(0.0, 1.0), (20.0, 0.5), (40.0, 0.0)]);
+    Ok(())
+}
+
+#[test]
+fn test_repeater_offset() -> Result<(), Error> {
+    let repeater = |transform: serde_json::Value| -> Result<ShapeGroup, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({ "shapes": [
+            {
+                "ty": "rc",
+                "p": { "a": 0, "k": [0, 0] },
+                "s": { "a": 0, "k": [10, 10] },
+                "r": { "a": 0, "k": 0 }
+            },
+            {
+                "ty": "rp",
+                "c": { "a": 0, "k": 2 },
+                "o": { "a": 1, "k": [{ "t": 0, "s": [-1] }, { "t": 10, "s": [1.5] }] },
+                "m": 2,
+                "tr": transform
+            },
+            { "ty": "fl", "o": { "a": 0, "k": 100 }, "c": { "a": 0, "k": [1, 0, 0] } }
+        ]}))
+    };
+    // Transform of the rectangle in every copy
+    let matrices = |group: &ShapeGroup, frame: f32| {
+        group
+            .styled_shapes()
+            .flat_map(|copy| match copy.shape.shape {
+                Shape::Group { shapes } => ShapeGroup { shapes }.styled_shapes().next(),
+                _ => None,
+            })
+            .map(|shape| shape.transform.value(frame))
+            .collect::<Vec<_>>()
+    };
+    let group = repeater(serde_json::json!({
+        "p": { "a": 0, "k": [20, 0] },
+        "s": { "a": 0, "k": [200, 200] },
+        "r": { "a": 0, "k": 0 },
+        "so": { "a": 0, "k": 100 },
+        "eo": { "a": 0, "k": 100 }
+    }))?;
+    // A negative offset moves the copies back, undoing the scale
+    let first = matrices(&group, 0.0);
+    assert_eq!(first[0].transform_point3(Vec3::ZERO).x, -20.0);
+    assert_eq!(first[0].transform_vector3(Vec3::X).x, 0.5);
+    assert_eq!(first[1].transform_point3(Vec3::ZERO).x, 0.0);
+    // A fractional offset moves the copies partway, with the fractional step
+    // scaling linearly
+    let last = matrices(&group, 10.0);
+    assert_eq!(last[0].transform_point3(Vec3::ZERO).x, 30.0);
+    assert_eq!(last[0].transform_vector3(Vec3::X).x, 3.0);
+    assert_eq!(last[1].transform_point3(Vec3::ZERO).x, 50.0);
+    assert_eq!(last[1].transform_vector3(Vec3::X).x, 6.0);
+
+    // Copies rotate around the anchor
+    let group = repeater(serde_json::json!({
+        "a": { "a": 0, "k": [10, 0] },
+        "p": { "a": 0, "k": [0, 0] },
+        "s": { "a": 0, "k": [100, 100] },
+        "r": { "a": 0, "k": 90 },
+        "so": { "a": 0, "k": 100 },
+        "eo": { "a": 0, "k": 100 }
+    }))?;
+    let point = matrices(&group, 10.0)[0].transform_point3(Vec3::ZERO);
+    // 1.5 turns of 90 degrees around (10, 0)
+    let expected = Vec3::new(10.0 + 10.0 * 0.5f32.sqrt(), -10.0 * 0.5f32.sqrt(), 0.0);
+    assert!((point - expected).length() < 1e-4, "{}", point);
+    Ok(())
+}
+
+#[test]
+fn test_path_modifiers() -> Result<(), Error> {
+    let square = serde_json::from_value::<Rectangle>(serde_json::json!({
+        "d": 1,
+        "p": { "a": 0, "k": [50, 50] },
+        "s": { "a": 0, "k": [100, 100] },
+        "r": { "a": 0, "k": 0 }
+    }))?
+    .path(0.0);
+    let modify = |modifier: serde_json::Value| -> Result<lyon_path::Path, serde_json::Error> {
+        let modifier: Shape = serde_json::from_value(modifier)?;
+        Ok(modify_path(square.clone(), &[modifier], 0.0))
+    };
+    let bounds = |path: &lyon_path::Path| {
+        let rect = lyon_algorithms::aabb::bounding_box(path.iter());
+        (
+            rect.min.x.round(),
+            rect.min.y.round(),
+            rect.max.x.round(),
+            rect.max.y.round(),
+        )
+    };
+    let vertices = |path: &lyon_path::Path| path.iter().count() - 2;
+
+    let rounded = modify(serde_json::json!({ "ty": "rd", "r": { "a": 0, "k": 10 } }))?;
+    assert_eq!(vertices(&rounded), 8);
+    assert_eq!(bounds(&rounded), (0.0, 0.0, 100.0, 100.0));
+
+    let offset = modify(serde_json::json!({
+        "ty": "op",
+        "a": { "a": 0, "k": 10 },
+        "lj": 1,
+        "ml": { "a": 0, "k": 4 }
+    }))?;
+    assert_eq!(bounds(&offset), (-10.0, -10.0, 110.0, 110.0));
+
+    let bloat = modify(serde_json::json!({ "ty": "pb", "a": { "a": 0, "k": 50 } }))?;
+    let corner = bloat.iter().next().map(|e| e.from()).unwrap();
+    assert_eq!((corner.x.round(), corner.y.round()), (75.0, 25.0));
+
+    let zig_zag = modify(serde_json::json!({
+        "ty": "zz",
+        "r": { "a": 0, "k": 3 },
+        "s": { "a": 0, "k": 5 },
+        "pt": { "a": 0, "k": 1 }
+    }))?;
+    assert_eq!(vertices(&zig_zag), 16);
+    assert_eq!(bounds(&zig_zag), (-5.0, -5.0, 105.0, 105.0));
+    Ok(())
+}
+
+#[test]
+fn test_merge_paths() -> Result<(), Error> {
+    let merged = |mode: u8| -> Result<lyon_path::Path, serde_json::Error> {
+        let group: ShapeGroup = serde_json::from_value(serde_json::json!({ "shapes": [
+            {
+                "ty": "rc",
+                "p": { "a": 0, "k": [100, 50] },
+                "s": { "a": 0, "k": [100, 100] },
+                "r": { "a": 0, "k": 0 }
+            },
+            {
+                "ty": "rc",
+                "p": { "a": 0, "k": [50, 50] },
+                "s": { "a": 0, "k": [100, 100] },
+                "r": { "a": 0, "k": 0 }
+            },
+            { "ty": "mm", "mm": mode },
+            {
+                "ty": "fl",
+                "o": { "a": 0, "k": 100 },
+                "c": { "a": 0, "k": [1, 0, 0] }
+            }
+        ]}))?;
+        let shapes = group.styled_shapes().collect::<Vec<_>>();
+        assert_eq!(shapes.len(), 1);
+        Ok(shapes[0].shape.shape.path(0.0))
+    };
+    let bounds = |path: &lyon_path::Path| {
+        let rect = lyon_algorithms::aabb::bounding_box(path.iter());
+        (
+            rect.min.x.round(),
+            rect.min.y.round(),
+            rect.max.x.round(),
+            rect.max.y.round(),
+        )
+    };
+    let area = |path: &lyon_path::Path| {
+        lyon_algorithms::area::approximate_signed_area(0.1, path.iter())
+            .abs()
+            .round()
+    };
+
+    assert_eq!(bounds(&merged(2)?), (0.0, 0.0, 150.0, 100.0));
+    assert_eq!(area(&merged(2)?), 15000.0);
+    assert_eq!(bounds(&merged(3)?), (0.0, 0.0, 50.0, 100.0));
+    assert_eq!(bounds(&merged(4)?), (50.0, 0.0, 100.0, 100.0));
+    assert_eq!(area(&merged(4)?), 5000.0);
+    // both halves outside of the overlap are kept, with the same winding
+    assert_eq!(area(&merged(5)?), 10000.0);
+    Ok(())
+}
+
+/// Markers are parsed, and segments last until the next marker when they have
+/// no duration
+#[test]
+fn test_markers() -> Result<(), Error> {
+    let file = fs::File::open("../../fixtures/ui/lottie-ios-samples/TwitterHeartButton.json")?;
+    let lottie = Lottie::from_reader(file, "").unwrap();
+    assert_eq!(lottie.model.markers.len(), 4);
+    let marker = lottie.model.marker("touchDownStart").unwrap();
+    assert_eq!((marker.start_frame, marker.duration), (33.0, 0.0));
+    assert!(lottie.model.marker("intro").is_none());
+    assert!(lottie.segment("intro").is_none());
+    // Markers without duration last until the next one
+    assert_eq!(lottie.segment("touchDownStart"), Some(33.0..38.0));
+    assert_eq!(lottie.segment("touchUpEnd"), Some(104.0..116.0));
+
+    let json = r#"{
+        "fr": 30, "ip": 0, "op": 60, "w": 100, "h": 100, "layers": [],
+        "markers": [{ "cm": "intro", "tm": 10, "dr": 20 }]
+    }"#;
+    let model = Model::from_reader(json.as_bytes())?;
+    assert_eq!(model.marker("intro").unwrap().end_frame(), 30.0);
+    let json = serde_json::to_value(&model)?;
+    assert_eq!(json["markers"][0]["cm"], "intro");
+    Ok(())
+}
+
+/// Fixtures whose values change when read. They are only checked for a stable
+/// second round trip
+const LOSSY: [&str; 7] = [
+    // Fractional composition size is rounded
+    "Issues/issue_1460.json",
+    // Shape direction 2 is read as clockwise
+    "Issues/issue_1725.json",
+    // Alpha stops between color stops are resampled at every offset
+    "Issues/issue_769.json",
+    "Issues/issue_1636.json",
+    "Issues/issue_1717.json",
+    "Issues/issue_1732.json",
+    "simple/gradients.json",
+];
+
+/// Ends of key paths that the model doesn't keep, `*` standing for any key or
+/// index
+const NOT_MODELLED: [&str; 25] = [
+    // Editor metadata
+    "ix",
+    "mn",
+    "n",
+    "cix",
+    "np",
+    "hix",
+    "ct",
+    "cl",
+    "l",
+    "meta",
+    "ks/ty",
+    "tr/ty",
+    "fillEnabled",
+    // Indices and names of shape items, dashes and repeater transforms
+    "shapes/*/ind",
+    "it/*/ind",
+    "d/*/nm",
+    "tr/nm",
+    // Non-standard layer visibility, layers are hidden with `hd`
+    "layers/*/hidden",
+    // Unsupported features
+    "sr",
+    "or",
+    "ml2",
+    "chars",
+    "s/tr",
+    "m/a",
+    "m/g",
+];
+
+/// Whether the end of `path` matches one of [NOT_MODELLED]
+fn not_modelled(path: &str) -> bool {
+    let keys = path.split('/').collect::<Vec<_>>();
+    NOT_MODELLED.iter().any(|pattern| {
+        let pattern = pattern.split('/').collect::<Vec<_>>();
+        keys.len() > pattern.len()
+            && keys[keys.len() - pattern.len()..]
+                .iter()
+                .zip(&pattern)
+                .all(|(key, pattern)| *pattern == "*" || key == pattern)
+    })
+}
+
+fn json_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            json_files(&path, files)?;
+        } else if path.extension().map(|ext| ext == "json").unwrap_or(false) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Values left out of the output because they are the defaults
+fn is_default(value: &serde_json::Value) -> bool {
+    use serde_json::Value;
+    match value {
+        Value::Null => true,
+        Value::Bool(b) => !b,
+        Value::Number(n) => n.as_f64() == Some(0.0),
+        Value::String(s) => s.is_empty(),
+        Value::Array(a) => a.is_empty(),
+        // An unanimated property holding a default
+        Value::Object(o) => {
+            o.keys().all(|key| ["a", "k", "ix"].contains(&key.as_str()))
+                && o.get("a").map(is_default).unwrap_or(true)
+                && o.get("k").map(is_default).unwrap_or(true)
+        }
+    }
+}
+
+/// Compare a fixture with its serialization, normalizing what the model
+/// writes differently: booleans as 0 or 1, scalars as single element arrays,
+/// 8 bit colors, 2D vectors, defaults and metadata that isn't kept
+fn assert_same(path: &str, original: &serde_json::Value, written: &serde_json::Value) {
+    use serde_json::Value;
+    let number = |value: &Value| match value {
+        Value::Bool(b) => Some(*b as u8 as f64),
+        Value::Number(n) => n.as_f64(),
+        _ => None,
+    };
+    let numbers = |value: &Value| match value {
+        Value::Array(a) => a.iter().map(number).collect::<Option<Vec<_>>>(),
+        value => number(value).map(|n| vec![n]),
+    };
+    if let (Some(original), Some(written)) = (numbers(original), numbers(written)) {
+        // Missing components are zero, extra ones like z or alpha are dropped
+        let len = original.len().max(written.len());
+        assert!(written.len() <= len);
+        for i in 0..len {
+            let (a, b) = (
+                original.get(i).cloned().unwrap_or(0.0),
+                written.get(i).cloned().unwrap_or(0.0),
+            );
+            if i >= written.len() {
+                continue;
+            }
+            let tolerance = (a.abs() * 1e-6).max(0.5 / 255.0 + 1e-6);
+            assert!((a - b).abs() <= tolerance, "{}: {} != {}", path, a, b);
+        }
+        return;
+    }
+    match (original, written) {
+        (Value::Array(original), Value::Array(written)) => {
+            assert_eq!(original.len(), written.len(), "{}", path);
+            for (i, (a, b)) in original.iter().zip(written).enumerate() {
+                assert_same(&format!("{}/{}", path, i), a, b);
+            }
+        }
+        (Value::Object(original), Value::Object(written)) => {
+            for (key, a) in original {
+                let path = format!("{}/{}", path, key);
+                match written.get(key) {
+                    Some(b) => assert_same(&path, a, b),
+                    None => assert!(
+                        is_default(a)
+                            // End values are implied by the next keyframe
+                            || key == "e"
+                            || not_modelled(&path),
+                        "{} dropped",
+                        path
+                    ),
+                }
+            }
+        }
+        (a, b) => assert_eq!(a, b, "{}", path),
+    }
+}
+
+#[test]
+fn test_round_trip() -> Result<(), Error> {
+    let mut files = vec![];
+    json_files(Path::new("../../fixtures/ui"), &mut files)?;
+    assert!(!files.is_empty());
+    for path in files {
+        let original: serde_json::Value = serde_json::from_reader(fs::File::open(&path)?)?;
+        let model = Model::from_reader(fs::File::open(&path)?)
+            .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        let mut data = vec![];
+        model.to_writer(&mut data)?;
+        let json: serde_json::Value = serde_json::from_slice(&data)?;
+        if !LOSSY.iter().any(|name| path.ends_with(name)) {
+            assert_same(&path.display().to_string(), &original, &json);
+        }
+        let model = Model::from_reader(data.as_slice())?;
+        let round_tripped: serde_json::Value = serde_json::from_str(&model.to_string()?)?;
+        assert!(json == round_tripped, "{} changed", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "expressions")]
+#[test]
+fn test_expressions() -> Result<(), Error> {
+    let linear = r#""o": { "x": 0, "y": 0 }, "i": { "x": 1, "y": 1 }"#;
+    let json = format!(
+        r#"{{
+        "fr": 30, "ip": 0, "op": 60, "w": 100, "h": 100,
+        "layers": [
+            {{ "ty": 3, "ind": 1, "nm": "Target", "ip": 0, "op": 60, "st": 0,
+               "ks": {{ "p": {{ "a": 0, "k": [30, 40] }} }} }},
+            {{ "ty": 3, "ind": 2, "nm": "Follower", "ip": 0, "op": 60, "st": 0,
+               "ks": {{
+                   "p": {{ "a": 0, "k": [0, 0],
+                          "x": "thisComp.layer('Target').transform.position + [10, 5]" }},
+                   "r": {{ "a": 1, "k": [{{ "t": 0, "s": [0], {linear} }}, {{ "t": 10, "s": [100] }}],
+                          "x": "loopOut('cycle')" }},
+                   "o": {{ "a": 0, "k": 100, "x": "var t = time; linear(t, 0, 1, 0, 100)" }},
+                   "s": {{ "a": 0, "k": [100, 100], "x": "wiggle(2, 10)" }}
+               }} }}
+        ]
+    }}"#
+    );
+    let mut model: Model = serde_json::from_str(&json)?;
+    model.bind_expressions();
+    let transform = model.layers[1].transform.as_ref().unwrap();
+    let position = match transform.position.as_ref() {
+        Some(Position::Combined(position)) => position,
+        _ => panic!("position is not combined"),
+    };
+    assert_eq!(position.value(0.0), Vector2D::new(40.0, 45.0));
+    assert_eq!(position.keyframe_value(0.0), Vector2D::new(0.0, 0.0));
+    assert!((transform.rotation.value(15.0) - 50.0).abs() < 0.01);
+    assert!((transform.rotation.value(27.0) - 70.0).abs() < 0.01);
+    assert!((transform.opacity.value(15.0) - 50.0).abs() < 0.01);
+    let (a, b) = (transform.scale.value(7.0), transform.scale.value(8.0));
+    assert_eq!(a, transform.scale.value(7.0));
+    assert_ne!(a, b);
+    assert!((90.0..=110.0).contains(&a.x) && (90.0..=110.0).contains(&a.y));
+
+    let json = serde_json::to_value(&model)?;
+    assert_eq!(json["layers"][1]["ks"]["r"]["x"], "loopOut('cycle')");
+    Ok(())
+}
+
+#[test]
+fn test_opacity_percent() -> Result<(), Error> {
+    let fill = Fill::from(Rgba::new_u8(255, 0, 0, 51));
+    assert!((fill.opacity.initial_value() - 20.0).abs() < 0.01);
+
+    // Masks are filled with their opacity, in percent like any other fill
+    let json = r#"{
+        "fr": 30, "ip": 0, "op": 60, "w": 100, "h": 100,
+        "layers": [{
+            "ty": 4, "ind": 1, "ip": 0, "op": 60, "st": 0, "ks": {}, "shapes": [],
+            "hasMask": true,
+            "masksProperties": [{
+                "nm": "Mask 1", "mode": "a", "inv": false, "o": { "a": 0, "k": 50 },
+                "pt": { "a": 0, "k": { "c": true, "v": [[0, 0], [10, 0], [10, 10]],
+                                        "i": [[0, 0], [0, 0], [0, 0]], "o": [[0, 0], [0, 0], [0, 0]] } }
+            }]
+        }]
+    }"#;
+    let lottie = Lottie::from_reader(json.as_bytes(), "").unwrap();
+    let mask = lottie
+        .timeline()
+        .items()
+        .find(|layer| layer.is_mask)
+        .unwrap();
+    let opacity = match &mask.content {
+        RenderableContent::Shape(group) => {
+            group.shapes.iter().find_map(|shape| match &shape.shape {
+                Shape::Fill(fill) => Some(fill.opacity.initial_value()),
+                _ => None,
+            })
+        }
+        _ => None,
+    };
+    assert_eq!(opacity, Some(50.0));
+    Ok(())
+}
+
+#[test]
+fn test_mask_feather_step() -> Result<(), Error> {
+    let json = r#"{
+        "fr": 30, "ip": 0, "op": 60, "w": 100, "h": 100,
+        "layers": [{
+            "ty": 4, "ind": 1, "ip": 0, "op": 60, "st": 0, "ks": {}, "shapes": [],
+            "hasMask": true,
+            "masksProperties": [{
+                "nm": "Mask 1", "mode": "a", "inv": false, "o": { "a": 0, "k": 100 },
+                "f": { "a": 1, "k": [
+                    { "t": 0, "s": [8, 8], "o": { "x": [0], "y": [0] }, "i": { "x": [1], "y": [1] } },
+                    { "t": 10, "s": [24, 24] }
+                ] },
+                "pt": { "a": 0, "k": { "c": true, "v": [[0, 0], [10, 0], [10, 10]],
+                                        "i": [[0, 0], [0, 0], [0, 0]], "o": [[0, 0], [0, 0], [0, 0]] } }
+            }]
+        }]
+    }"#;
+    let lottie = Lottie::from_reader(json.as_bytes(), "").unwrap();
+    let layer = lottie
+        .timeline()
+        .items()
+        .find(|layer| !layer.is_mask)
+        .unwrap();
+    let composition = layer.mask_hierarchy.masks()[0].composition.clone().unwrap();
+    // Feather is spread over 8 bands and follows its keyframes
+    assert!((composition.feather_step(0.0) - 1.0).abs() < 1e-4);
+    assert!((composition.feather_step(5.0) - 2.0).abs() < 1e-4);
+    assert!((composition.feather_step(10.0) - 3.0).abs() < 1e-4);
+    Ok(())
+}
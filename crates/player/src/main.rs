@@ -6,7 +6,7 @@ use std::path::Path;
 // use bevy_prototype_debug_lines::{DebugLines, DebugLinesPlugin};
 use anyhow::Error;
 use clap::Parser;
-use lottie_core::{Config, HeadlessConfig, Lottie, Renderer, Target, WindowConfig};
+use lottie_core::{Config, FrameData, HeadlessConfig, Lottie, Renderer, Target, WindowConfig};
 use lottie_renderer_bevy::BevyRenderer;
 use smol::pin;
 use smol::stream::StreamExt;
@@ -93,6 +93,14 @@ fn main() -> Result<(), Error> {
         // renderer.add_plugin(DebugLinesPlugin::default());
         // renderer.add_system(axis_system);
         renderer.load_lottie(lottie, config);
+        if let (Some(target), Some(filename), false) = (target_frame, filename.as_ref(), all_frames)
+        {
+            // A single frame is requested, render it directly
+            if let Some(frame) = renderer.render_frame(target as f32) {
+                write_png(&format!("{}_{}.png", filename, target), &frame)?;
+            }
+            return Ok(());
+        }
         renderer.render();
         pin!(frame_stream);
         let mut i = 0;
@@ -102,19 +110,7 @@ fn main() -> Result<(), Error> {
         while let Some(frame) = frame_stream.next().await {
             if let (Some(target), Some(filename)) = (target_frame, filename.as_ref()) {
                 if target == i {
-                    let f = File::create(&format!("{}_{}.png", filename, i))?;
-                    let w = BufWriter::new(f);
-                    let mut encoder = png::Encoder::new(w, frame.width, frame.height);
-                    encoder.set_color(png::ColorType::Rgba);
-                    encoder.set_depth(png::BitDepth::Eight);
-                    encoder
-                        .write_header()
-                        .unwrap()
-                        .write_image_data(&frame.data)
-                        .unwrap();
-                    if !all_frames {
-                        break;
-                    }
+                    write_png(&format!("{}_{}.png", filename, i), &frame)?;
                 }
                 i += 1;
                 if all_frames {
@@ -140,3 +136,13 @@ fn main() -> Result<(), Error> {
     }
     Ok(())
 }
+
+fn write_png(path: &str, frame: &FrameData) -> Result<(), Error> {
+    let f = File::create(path)?;
+    let w = BufWriter::new(f);
+    let mut encoder = png::Encoder::new(w, frame.width, frame.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&frame.data)?;
+    Ok(())
+}
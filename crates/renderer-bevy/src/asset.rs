@@ -0,0 +1,42 @@
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+use lottie_core::Lottie;
+
+/// A loaded Lottie animation, played by the entities with a
+/// [LottieComp](crate::LottieComp) referring to it
+#[derive(TypeUuid, TypePath)]
+#[uuid = "3a6e2c1b-8d0f-4b59-9c47-5f1e0a2d7b68"]
+pub struct LottieAsset(pub Lottie);
+
+/// Loads Lottie JSON files, resolving the media they link to from the
+/// directory they are in. Only `.lottie.json` files are loaded, leaving other
+/// JSON assets to their own loaders. dotLottie archives are not supported
+#[cfg(not(all(target_os = "unknown", target_arch = "wasm32")))]
+#[derive(Default)]
+pub struct LottieLoader;
+
+#[cfg(not(all(target_os = "unknown", target_arch = "wasm32")))]
+impl AssetLoader for LottieLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let root_path = load_context
+                .path()
+                .parent()
+                .and_then(|path| path.to_str())
+                .unwrap_or_default()
+                .to_string();
+            let lottie = Lottie::from_reader(bytes, &root_path)?;
+            load_context.set_default_asset(LoadedAsset::new(LottieAsset(lottie)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lottie.json"]
+    }
+}
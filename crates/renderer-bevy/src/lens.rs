@@ -1,92 +1,49 @@
-use bevy::prelude::{Transform, Vec2};
+use bevy::prelude::{Color, Transform, Vec2};
 use bevy_tweening::Lens;
 use lottie_core::prelude::{
-    Animated, Bezier, OpacityHierarchy, PathFactory, TextBased, TextRangeInfo, TextRangeSelector,
-    Transform as LottieTransform, TransformHierarchy, TrimInfo,
+    apply_path_operations, Animated, OpacityHierarchy, PathFactory, PathOperation, Rgb, TextBased,
+    TextRangeInfo, TextRangeSelector, Transform as LottieTransform, TransformHierarchy,
 };
-use lyon::algorithms::measure::PathMeasurements;
-use lyon::algorithms::measure::SampleType::Normalized;
-use lyon::geom::euclid::approxeq::ApproxEq;
-use lyon::path::Path as LyonPath;
-
-use crate::shape::{DrawMode, Path};
-
-pub struct PathLens {
-    pub(crate) start: Vec<Bezier>,
-    pub(crate) end: Vec<Bezier>,
-    pub(crate) trims: Vec<TrimInfo>,
-    pub(crate) start_frame: f32,
-    pub(crate) end_frame: f32,
+
+use crate::shape::{DrawMode, Path, SolidOrGradient};
+
+pub struct StrokeWidthLens {
+    pub(crate) width: Animated<f32>,
+    pub(crate) frames: f32,
 }
 
-impl Lens<Path> for PathLens {
-    fn lerp(&mut self, target: &mut Path, ratio: f32) {
-        let frame = (self.end_frame - self.start_frame) * ratio + self.start_frame;
-        let beziers = self
-            .start
-            .iter()
-            .zip(self.end.iter())
-            .map(|(start, end)| {
-                let mut result = start.clone();
-                for index in 0..result.verticies.len() {
-                    result.verticies[index] +=
-                        (end.verticies[index] - start.verticies[index]) * ratio;
-                    result.in_tangent[index] +=
-                        (end.in_tangent[index] - start.in_tangent[index]) * ratio;
-                    result.out_tangent[index] +=
-                        (end.out_tangent[index] - start.out_tangent[index]) * ratio;
-                }
-                result
-            })
-            .collect::<Vec<_>>();
-        let path = beziers.path(0.0);
-        let mut start = 0.0f32;
-        let mut end = 1.0f32;
-        for trim in &self.trims {
-            let offset = norm(trim.trim.offset.value(frame) / 360.0);
-            let mut s = norm(trim.trim.start.value(frame) / 100.0 + offset);
-            let mut e = norm(trim.trim.end.value(frame) / 100.0 + offset);
-            if s < 0.0 {
-                s += 1.0;
-            }
-            if e < 0.0 {
-                e += 1.0;
-            }
-            let (s, e) = (s.min(e), s.max(e));
-            start = start.max(s);
-            end = end.min(e);
-        }
-        if start.approx_eq(&0.0) && end.approx_eq(&1.0) {
-            *target = Path(path);
-        } else {
-            let measures = PathMeasurements::from_path(&path, 1e-3);
-            let mut sampler = measures.create_sampler(&path, Normalized);
-            let mut builder = LyonPath::builder();
-            sampler.split_range(start..end, &mut builder);
-            let path = builder.build();
-            *target = Path(path);
+impl Lens<DrawMode> for StrokeWidthLens {
+    fn lerp(&mut self, target: &mut DrawMode, ratio: f32) {
+        let w = self.width.value(self.frames * ratio);
+        if let Some(stroke) = target.stroke.as_mut() {
+            stroke.options.line_width = w;
         }
     }
 }
 
-fn norm(value: f32) -> f32 {
-    if value > 0.0 && value.fract() == 0.0 {
-        1.0
-    } else {
-        value.fract()
+pub struct StrokeColorLens {
+    pub(crate) color: Animated<Rgb>,
+    pub(crate) frames: f32,
+}
+
+impl Lens<DrawMode> for StrokeColorLens {
+    fn lerp(&mut self, target: &mut DrawMode, ratio: f32) {
+        let color = self.color.value(self.frames * ratio);
+        if let Some(stroke) = target.stroke.as_mut() {
+            stroke.color = SolidOrGradient::Solid(Color::rgb_u8(color.r, color.g, color.b));
+        }
     }
 }
 
-pub struct StrokeWidthLens {
-    pub(crate) start: f32,
-    pub(crate) end: f32,
+/// Re-evaluate animated stroke dashes, which are applied when meshing
+pub struct DashLens {
+    pub(crate) frames: f32,
 }
 
-impl Lens<DrawMode> for StrokeWidthLens {
+impl Lens<DrawMode> for DashLens {
     fn lerp(&mut self, target: &mut DrawMode, ratio: f32) {
-        let w = self.start + (self.end - self.start) * ratio;
         if let Some(stroke) = target.stroke.as_mut() {
-            stroke.options.line_width = w;
+            stroke.dash_frame = self.frames * ratio;
         }
     }
 }
@@ -139,6 +96,8 @@ impl Lens<Transform> for TransformLens {
 
 pub struct OpacityLens {
     pub(crate) opacity: OpacityHierarchy,
+    /// Transform of the group holding the shape
+    pub(crate) transform: LottieTransform,
     pub(crate) frames: f32,
     pub(crate) fill_opacity: Animated<f32>,
     pub(crate) stroke_opacity: Option<Animated<f32>>,
@@ -147,7 +106,7 @@ pub struct OpacityLens {
 impl Lens<DrawMode> for OpacityLens {
     fn lerp(&mut self, target: &mut DrawMode, ratio: f32) {
         let frame = self.frames as f32 * ratio;
-        let value = self.opacity.value(frame);
+        let value = self.opacity.value(frame) * self.transform.opacity_value(frame) / 100.0;
         let fill_opacity = self.fill_opacity.value(frame) / 100.0;
 
         if let Some(fill) = target.fill.as_mut() {
@@ -192,14 +151,52 @@ fn lerp_index_in_text_range(
 }
 
 pub struct PathFactoryLens {
-    pub(crate) start_frame: f32,
-    pub(crate) end_frame: f32,
+    pub(crate) frames: f32,
     pub(crate) factory: Box<dyn PathFactory + Send + Sync>,
+    pub(crate) operations: Vec<PathOperation>,
 }
 
 impl Lens<Path> for PathFactoryLens {
     fn lerp(&mut self, target: &mut Path, ratio: f32) {
-        let frame = (self.end_frame - self.start_frame) * ratio + self.start_frame;
-        *target = Path(self.factory.path(frame));
+        let frame = self.frames * ratio;
+        let path = self.factory.path(frame);
+        *target = Path(apply_path_operations(path, &self.operations, frame));
+    }
+}
+
+#[cfg(all(test, feature = "expressions"))]
+mod tests {
+    use lottie_core::prelude::Model;
+
+    use super::*;
+
+    #[test]
+    fn expression_only_transform_changes() {
+        let json = r#"{
+            "fr": 30, "ip": 0, "op": 60, "w": 100, "h": 100,
+            "layers": [{
+                "ty": 3, "ind": 1, "ip": 0, "op": 60, "st": 0,
+                "ks": { "p": { "a": 0, "k": [0, 0], "x": "wiggle(5, 20)" } }
+            }]
+        }"#;
+        let mut model = Model::from_reader(json.as_bytes()).unwrap();
+        model.bind_expressions();
+        let data = model.layers[0].transform.clone().unwrap();
+        // Without keyframes the tween has to last as long as the layer
+        assert!(data.is_animated());
+        assert_eq!(data.frames(), 0.0);
+
+        let mut lens = TransformLens {
+            data,
+            frames: 60.0,
+            zindex: 0.0,
+            mask_offset: Vec2::ZERO,
+            transform_hierarchy: TransformHierarchy::default(),
+            text_range: None,
+        };
+        let (mut a, mut b) = (Transform::default(), Transform::default());
+        lens.lerp(&mut a, 0.1);
+        lens.lerp(&mut b, 0.6);
+        assert_ne!(a.translation, b.translation);
     }
 }
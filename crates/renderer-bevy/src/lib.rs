@@ -1,4 +1,4 @@
-use std::borrow::Cow;
+use std::ops::Range;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
@@ -15,7 +15,7 @@ use bevy::utils::HashMap;
 use bevy::window::{ExitCondition, PrimaryWindow};
 use bevy::winit::WinitPlugin;
 // use bevy_diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
-use bevy_tweening::{Animator, AnimatorState, TweenCompleted};
+use bevy_tweening::{Animator, AnimatorState};
 // use gradient::GradientManager;
 // use frame_capture::{
 //     CaptureCamera, Frame, FrameCapture, FrameCaptureEvent,
@@ -25,11 +25,12 @@ use bevy::prelude::Transform;
 use bevy::render::texture::{BevyDefault, Image};
 use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use lottie_core::prelude::{Id as TimelineItemId, StyledShape};
+pub use lottie_core::FrameData;
 use lottie_core::*;
 use shape::{DrawMode, Path};
-use system::component_animator_system;
 use wgpu::{Extent3d, TextureDescriptor, TextureDimension, TextureUsages};
 
+mod asset;
 mod frame_capture;
 // mod gradient;
 mod lens;
@@ -38,18 +39,46 @@ mod plugin;
 mod render;
 mod shape;
 mod system;
-mod tween;
 mod utils;
 
+pub use asset::LottieAsset;
+#[cfg(not(all(target_os = "unknown", target_arch = "wasm32")))]
+pub use asset::LottieLoader;
 use frame_capture::{ImageCopier, ImageCopyPlugin, ImageToSave};
-use material::LottieMaterial;
+use material::{EffectDataUniform, EffectMaterial, GradientDataUniform, LottieMaterial};
 use ordered_float::OrderedFloat;
-use plugin::LottiePlugin;
+pub use plugin::LottiePlugin;
 use render::*;
+#[cfg(feature = "bevy_egui")]
+pub use system::controls_system;
 
-#[derive(Component)]
+/// A Lottie animation, whose layers are spawned as children of its entity once
+/// its asset is loaded. Every entity plays its animation on its own, see
+/// [LottieAnimationInfo]
+#[derive(Component, Default, Clone)]
 pub struct LottieComp {
-    lottie: Lottie,
+    pub lottie: Handle<LottieAsset>,
+}
+
+/// Marks the Lottie instance the egui `controls_system` plays and headless
+/// mode captures, like [PrimaryWindow] does for windows. [BevyRenderer] adds it
+/// to the instance it spawns
+#[derive(Component, Default, Clone, Copy)]
+pub struct PrimaryLottie;
+
+#[derive(Bundle, Default)]
+pub struct LottieBundle {
+    pub lottie: LottieComp,
+    pub spatial: SpatialBundle,
+}
+
+/// Parent of the layers of a Lottie instance, mapping the y-down composition
+/// units to the space of the instance and fitting its layers in one unit of
+/// depth
+#[derive(Component)]
+struct LottieRoot {
+    instance: Entity,
+    scale: f32,
 }
 
 #[derive(Component)]
@@ -65,7 +94,9 @@ struct LottieGlobals {
     config: Config,
 }
 
-#[derive(Resource)]
+/// Playback state of a Lottie instance, inserted on its entity once its layers
+/// are spawned
+#[derive(Component)]
 pub struct LottieAnimationInfo {
     start_frame: f32,
     end_frame: f32,
@@ -75,7 +106,28 @@ pub struct LottieAnimationInfo {
     width: f32,
     height: f32,
     finished_once: bool,
+    /// When enabled, `current_time` is neither advanced nor looped by
+    /// `animate_system`, it only changes when seeking explicitly
+    manual: bool,
     entities: HashMap<TimelineItemId, Entity>,
+    /// Parent of the layers, see [LottieRoot]
+    root: Entity,
+    mask_texture: Handle<Image>,
+    /// Frames of the segments named by the markers of the animation
+    segments: HashMap<String, Range<f32>>,
+    /// Frames played instead of the whole animation
+    frame_range: Option<Range<f32>>,
+    /// Multiplier of the wall-clock time animations advance by
+    speed: f32,
+    reversed: bool,
+    /// Whether playback bounces between the ends of the played frames instead
+    /// of jumping back to the start
+    ping_pong: bool,
+    /// Times the frames are played before pausing, forever if `None`
+    loop_count: Option<u32>,
+    loops_done: u32,
+    /// Frame shown in the current update, see [LottieAnimationInfo::tick]
+    shown_frame: Option<f32>,
 }
 
 impl LottieAnimationInfo {
@@ -87,13 +139,233 @@ impl LottieAnimationInfo {
         self.paused
     }
 
+    /// Pause or resume playback. Resuming after all loops were played starts
+    /// over
     pub fn pause(&mut self, pause: bool) {
+        if !pause && self.finished() {
+            self.restart();
+        }
         self.paused = pause;
     }
 
     pub fn current_time(&self) -> f32 {
         self.current_time
     }
+
+    pub fn current_frame(&self) -> f32 {
+        self.current_time * self.frame_rate
+    }
+
+    /// Jump to `frame`, which is kept within the played frames
+    pub fn seek(&mut self, frame: f32) {
+        let frames = self.frame_range();
+        self.current_time = frame.clamp(frames.start, frames.end) / self.frame_rate;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Set how fast the animation plays, `1.0` being its own frame rate
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    pub fn reversed(&self) -> bool {
+        self.reversed
+    }
+
+    /// Play the animation backwards. If playback is on the frame it would stop
+    /// at in the new direction, it starts over from the other end
+    pub fn set_reversed(&mut self, reversed: bool) {
+        self.reversed = reversed;
+        let frames = self.frame_range();
+        let (start, end) = (frames.start / self.frame_rate, frames.end / self.frame_rate);
+        let backwards = self.backwards();
+        if (backwards && self.current_time <= start) || (!backwards && self.current_time >= end) {
+            self.current_time = self.first_time(&frames);
+        }
+    }
+
+    pub fn ping_pong(&self) -> bool {
+        self.ping_pong
+    }
+
+    /// Bounce between the ends of the played frames, each way counting as a
+    /// loop
+    pub fn set_ping_pong(&mut self, ping_pong: bool) {
+        self.ping_pong = ping_pong;
+        self.loops_done = 0;
+    }
+
+    pub fn loop_count(&self) -> Option<u32> {
+        self.loop_count
+    }
+
+    /// Set how many times the frames are played before pausing on the last
+    /// one, `None` looping forever
+    pub fn set_loop_count(&mut self, loop_count: Option<u32>) {
+        self.loop_count = loop_count;
+        self.loops_done = 0;
+    }
+
+    /// Frames played, either the active range or the whole animation
+    pub fn frame_range(&self) -> Range<f32> {
+        match self.frame_range.as_ref() {
+            Some(frames) => frames.clone(),
+            None => 0.0..self.end_frame,
+        }
+    }
+
+    /// Only play `frames`, which are clamped to the animation. `None` plays
+    /// the whole animation
+    pub fn set_frame_range(&mut self, frames: Option<Range<f32>>) {
+        self.frame_range = frames.map(|frames| {
+            let start = frames.start.clamp(0.0, self.end_frame);
+            start..frames.end.clamp(start, self.end_frame)
+        });
+        self.restart();
+    }
+
+    /// Names of the segments which could be played
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.segments.keys().map(|name| name.as_str())
+    }
+
+    /// Play the segment named `name` once, pausing on its last frame. Returns
+    /// `false` if there is no such segment
+    pub fn play_segment(&mut self, name: &str) -> bool {
+        self.start_segment(name, Some(1))
+    }
+
+    /// Play the segment named `name` repeatedly. Returns `false` if there is
+    /// no such segment
+    pub fn loop_segment(&mut self, name: &str) -> bool {
+        self.start_segment(name, None)
+    }
+
+    /// Jump to the start of the segment named `name`, its end when playing
+    /// backwards. If a range of frames is being played, the segment is played
+    /// instead
+    pub fn jump_to_segment(&mut self, name: &str) -> bool {
+        let frames = match self.segments.get(name) {
+            Some(frames) => frames.clone(),
+            None => return false,
+        };
+        if self.frame_range.is_some() {
+            self.set_frame_range(Some(frames));
+        } else {
+            self.current_time = self.first_time(&frames);
+        }
+        true
+    }
+
+    /// Go back to playing the whole animation forever
+    pub fn play_all(&mut self) {
+        self.loop_count = None;
+        self.set_frame_range(None);
+        self.paused = false;
+    }
+
+    fn start_segment(&mut self, name: &str, loop_count: Option<u32>) -> bool {
+        let frames = match self.segments.get(name) {
+            Some(frames) => frames.clone(),
+            None => return false,
+        };
+        self.loop_count = loop_count;
+        self.set_frame_range(Some(frames));
+        self.paused = false;
+        true
+    }
+
+    /// Whether time currently flows backwards, which alternates on every loop
+    /// when ping-ponging
+    fn backwards(&self) -> bool {
+        self.reversed != (self.ping_pong && self.loops_done % 2 == 1)
+    }
+
+    fn finished(&self) -> bool {
+        self.loop_count
+            .is_some_and(|count| self.loops_done >= count)
+    }
+
+    /// Time at which playing `frames` starts in the current direction
+    fn first_time(&self, frames: &Range<f32>) -> f32 {
+        let frame = if self.backwards() {
+            frames.end
+        } else {
+            frames.start
+        };
+        frame / self.frame_rate
+    }
+
+    /// Start playing the frames from the end playback starts at
+    fn restart(&mut self) {
+        self.loops_done = 0;
+        self.current_time = self.first_time(&self.frame_range());
+    }
+
+    /// Loop, bounce or pause once `current_time` went past the end of the
+    /// played frames
+    fn wrap(&mut self) {
+        let frames = self.frame_range();
+        let (start, end) = (frames.start / self.frame_rate, frames.end / self.frame_rate);
+        let backwards = self.backwards();
+        if (!backwards && self.current_time < end) || (backwards && self.current_time > start) {
+            return;
+        }
+        self.finished_once = true;
+        self.loops_done += 1;
+        let (first, last) = if backwards {
+            (end, start)
+        } else {
+            (start, end)
+        };
+        self.current_time = if self.finished() {
+            // Stay on the last frame played
+            self.paused = true;
+            last
+        } else if self.ping_pong {
+            last
+        } else {
+            first
+        };
+    }
+
+    /// Frame to show in this update, `None` if animations should stay as they
+    /// are, and advance the time for the next one. The frame is kept in
+    /// `shown_frame` for the systems running after `animate_system`
+    fn tick(&mut self, capturing: bool, elapsed: f32) -> Option<f32> {
+        self.shown_frame = self.advance(capturing, elapsed);
+        self.shown_frame
+    }
+
+    fn advance(&mut self, capturing: bool, elapsed: f32) -> Option<f32> {
+        if self.paused && !self.manual {
+            return None;
+        }
+        let delta = if self.manual {
+            0.0
+        } else if capturing {
+            1.0 / self.frame_rate
+        } else {
+            elapsed * self.speed
+        };
+        if !self.manual && !capturing {
+            self.wrap();
+        } else if !self.manual && self.current_time >= self.end_frame / self.frame_rate {
+            // Captures go through the whole animation once, at its own pace
+            self.finished_once = true;
+            self.current_time += delta;
+            return None;
+        }
+        let frame = self.current_frame();
+        if !self.paused {
+            let backwards = !capturing && self.backwards();
+            self.current_time += if backwards { -delta } else { delta };
+        }
+        Some(frame)
+    }
 }
 
 #[derive(Resource)]
@@ -113,16 +385,13 @@ impl FrameSender {
     }
 }
 
-pub struct FrameData {
-    pub data: Vec<u8>,
-    pub width: u32,
-    pub height: u32,
-    pub timestamp: i32,
-}
+/// Updates needed before a rendered frame is copied back to CPU side
+const CAPTURE_LATENCY: u32 = 3;
 
 pub struct BevyRenderer {
     app: App,
     frame_sender: UnboundedSender<FrameData>,
+    ready: bool,
 }
 
 impl BevyRenderer {
@@ -134,6 +403,7 @@ impl BevyRenderer {
             BevyRenderer {
                 app,
                 frame_sender: sender,
+                ready: false,
             },
             receiver,
         )
@@ -150,6 +420,22 @@ impl BevyRenderer {
     pub fn insert_resource<R: Resource>(&mut self, resource: R) {
         self.app.insert_resource(resource);
     }
+
+    /// Finish building the app so it could be updated manually, this is
+    /// usually done by the app runner
+    fn ensure_ready(&mut self) {
+        if self.ready {
+            return;
+        }
+        while !self.app.ready() {
+            bevy::tasks::tick_global_task_pools_on_main_thread();
+        }
+        self.app.finish();
+        self.app.cleanup();
+        // Run the startup systems to spawn the lottie
+        self.app.update();
+        self.ready = true;
+    }
 }
 
 impl Renderer for BevyRenderer {
@@ -189,11 +475,6 @@ impl Renderer for BevyRenderer {
             // .add_plugin(FrameTimeDiagnosticsPlugin)
             // .add_plugin(LogDiagnosticsPlugin::default())
             .add_plugins(LottiePlugin)
-            .add_event::<TweenCompleted>()
-            .add_systems(Update, component_animator_system::<Transform>)
-            .add_systems(Update, component_animator_system::<Path>)
-            .add_systems(Update, component_animator_system::<DrawMode>)
-            .add_systems(Update, animate_system)
             .add_systems(Startup, setup_system);
 
         if let Config::Window(window_conf) = &config {
@@ -211,6 +492,11 @@ impl Renderer for BevyRenderer {
         }
 
         let frame_rate = lottie.model.frame_rate as f64;
+        let target = if let Config::Headless(headless) = &config {
+            headless.target
+        } else {
+            Target::Default
+        };
         self.app.insert_resource(LottieGlobals {
             lottie: Some(lottie),
             capturing,
@@ -218,6 +504,9 @@ impl Renderer for BevyRenderer {
         });
 
         if capturing {
+            if target == Target::Mask {
+                self.app.add_systems(Update, mask_capture_system);
+            }
             self.app
                 .add_plugins(ImageCopyPlugin)
                 .insert_resource(ClearColor(Color::rgb(1.0, 1.0, 1.0)))
@@ -237,274 +526,498 @@ impl Renderer for BevyRenderer {
     fn render(&mut self) {
         self.app.run()
     }
+
+    /// Render a single frame by seeking and updating the app manually. This
+    /// only works in headless mode, and should not be mixed with
+    /// [BevyRenderer::render]
+    fn render_frame(&mut self, frame: f32) -> Option<FrameData> {
+        if !self.app.world.get_resource::<LottieGlobals>()?.capturing {
+            log::warn!("render_frame is only available in headless mode");
+            return None;
+        }
+        self.ensure_ready();
+        let timestamp = {
+            let mut query = self
+                .app
+                .world
+                .query_filtered::<&mut LottieAnimationInfo, With<PrimaryLottie>>();
+            let mut info = query.get_single_mut(&mut self.app.world).ok()?;
+            info.manual = true;
+            info.paused = false;
+            info.current_time = frame / info.frame_rate;
+            (info.current_time * 1000.0) as i32
+        };
+        // Time is frozen, so every update renders the same frame. Wait until
+        // it arrives at the CPU side
+        for _ in 0..=CAPTURE_LATENCY {
+            self.app.update();
+        }
+        let world = &mut self.app.world;
+        let handle = world
+            .query::<&ImageToSave>()
+            .get_single(world)
+            .ok()?
+            .0
+            .clone();
+        let image = world.resource::<Assets<Image>>().get(&handle)?;
+        frame_data(image, timestamp)
+    }
 }
 
 fn setup_system(
     mut commands: Commands,
     mut lottie_globals: ResMut<LottieGlobals>,
     mut image_assets: ResMut<Assets<Image>>,
-    mut audio_assets: ResMut<Assets<AudioSource>>,
-    mut material_assets: ResMut<Assets<LottieMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    window: Query<&Window, With<PrimaryWindow>>,
+    mut lottie_assets: ResMut<Assets<LottieAsset>>,
     render_device: Res<RenderDevice>,
 ) {
     let lottie = lottie_globals.lottie.take().unwrap();
-    let scale = if let Ok(primary) = window.get_single() {
-        primary.scale_factor() as f32
-    } else {
-        1.0
-    };
     let mut camera = Camera2dBundle::default();
     camera.camera_2d.clear_color = ClearColorConfig::Custom(Color::NONE);
-    let transform = Transform::from_scale(Vec3::new(1.0, -1.0, 1.0));
-    camera.transform = transform;
-    let mask_count = lottie
-        .timeline()
-        .items()
-        .filter(|layer| layer.is_mask)
-        .count() as u32;
     let width = (lottie.model.width as f32 * lottie.scale).round() as u32;
     let height = (lottie.model.height as f32 * lottie.scale).round() as u32;
-    let root_translation = Vec3::new(
-        lottie.model.width as f32 * (lottie.scale / -2.0),
-        lottie.model.height as f32 * (lottie.scale / -2.0),
-        0.0,
-    );
-    // Create the mask texture
-    let mask_size = Extent3d {
-        width: std::cmp::max(1, width * mask_count),
-        height,
-        depth_or_array_layers: 1,
+
+    let target = if let Config::Headless(headless) = &lottie_globals.config {
+        headless.target
+    } else {
+        Target::Default
     };
-    let mut mask = Image {
+    // Masks are captured by `mask_capture_system` once they are spawned
+    if lottie_globals.capturing && target == Target::Default {
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let mut render_target_image = Image {
+            texture_descriptor: TextureDescriptor {
+                label: Some("render target image"),
+                size,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::bevy_default(),
+                mip_level_count: 1,
+                sample_count: 1,
+                usage: TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_DST
+                    | TextureUsages::COPY_SRC
+                    | TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+            ..Default::default()
+        };
+        render_target_image.resize(size);
+        let render_target_image_handle = image_assets.add(render_target_image);
+        camera.camera.target = RenderTarget::Image(render_target_image_handle.clone());
+        spawn_image_copier(
+            &mut commands,
+            &mut image_assets,
+            render_target_image_handle,
+            size,
+            &render_device,
+        );
+    }
+
+    commands.spawn(camera);
+    commands.spawn((
+        LottieBundle {
+            lottie: LottieComp {
+                lottie: lottie_assets.add(LottieAsset(lottie)),
+            },
+            ..default()
+        },
+        PrimaryLottie,
+    ));
+}
+
+/// Capture the mask texture instead of the rendered frames, once the masks are
+/// spawned
+fn mask_capture_system(
+    mut commands: Commands,
+    instances: Query<&LottieAnimationInfo, Added<LottieAnimationInfo>>,
+    mut image_assets: ResMut<Assets<Image>>,
+    render_device: Res<RenderDevice>,
+) {
+    for info in instances.iter() {
+        let size = match image_assets.get(&info.mask_texture) {
+            Some(image) => image.texture_descriptor.size,
+            None => continue,
+        };
+        spawn_image_copier(
+            &mut commands,
+            &mut image_assets,
+            info.mask_texture.clone(),
+            size,
+            &render_device,
+        );
+    }
+}
+
+/// Copy `source` back to the CPU side every frame, to be saved by `save_img`
+/// or `render_frame`
+fn spawn_image_copier(
+    commands: &mut Commands,
+    image_assets: &mut Assets<Image>,
+    source: Handle<Image>,
+    size: Extent3d,
+    render_device: &RenderDevice,
+) {
+    let mut cpu_image = Image {
         texture_descriptor: TextureDescriptor {
-            label: Some("mask_texture"),
-            size: mask_size,
+            label: Some("cpu image"),
+            size,
             dimension: TextureDimension::D2,
             format: TextureFormat::bevy_default(),
             mip_level_count: 1,
             sample_count: 1,
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_DST
-                | TextureUsages::COPY_SRC
-                | TextureUsages::RENDER_ATTACHMENT,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
             view_formats: &[],
         },
-        ..default()
+        ..Default::default()
     };
-    mask.resize(mask_size);
-    let mask_texture_handle = image_assets.add(mask);
-    let mask_camera = Camera2dBundle {
-        camera_2d: Camera2d {
-            clear_color: ClearColorConfig::Custom(Color::NONE),
-        },
-        camera: Camera {
-            target: RenderTarget::Image(mask_texture_handle.clone()),
-            order: -1,
-            ..default()
-        },
-        transform: Transform::from_scale(Vec3::new(1.0, -1.0, 1.0)).with_translation(Vec3::new(
-            lottie.model.width as f32 * (lottie.scale / -2.0) + mask_size.width as f32 / 2.0,
-            0.0,
-            0.0,
-        )),
-        ..default()
-    };
-    commands.spawn(mask_camera).insert(RenderLayers::layer(1));
+    cpu_image.resize(size);
+    let cpu_image_handle = image_assets.add(cpu_image);
+    commands.spawn(ImageCopier::new(
+        source,
+        cpu_image_handle.clone(),
+        size,
+        render_device,
+    ));
+    commands.spawn(ImageToSave(cpu_image_handle));
+}
 
-    if lottie_globals.capturing {
-        let target = if let Config::Headless(headless) = &lottie_globals.config {
-            headless.target
-        } else {
-            Target::Default
-        };
-        let size = if target == Target::Mask {
-            mask_size
-        } else {
-            Extent3d {
-                width,
-                height,
-                depth_or_array_layers: 1,
-            }
+/// Spawn the layers of Lottie instances once their animation is loaded
+fn spawn_system(
+    mut commands: Commands,
+    instances: Query<(Entity, &LottieComp), Without<LottieAnimationInfo>>,
+    lottie_assets: Res<Assets<LottieAsset>>,
+    mut render_layers: ResMut<RenderLayerAllocator>,
+    mut image_assets: ResMut<Assets<Image>>,
+    mut audio_assets: ResMut<Assets<AudioSource>>,
+    mut material_assets: ResMut<Assets<LottieMaterial>>,
+    mut effect_material_assets: ResMut<Assets<EffectMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+) {
+    let scale = if let Ok(primary) = window.get_single() {
+        primary.scale_factor() as f32
+    } else {
+        1.0
+    };
+    for (instance, comp) in instances.iter() {
+        let lottie = match lottie_assets.get(&comp.lottie) {
+            Some(asset) => &asset.0,
+            None => continue,
         };
+        let mask_count = lottie
+            .timeline()
+            .items()
+            .filter(|layer| layer.is_mask)
+            .count() as u32;
+        let model_size = Vec2::new(lottie.model.width as f32, lottie.model.height as f32);
+        let width = (model_size.x * lottie.scale).round() as u32;
+        let height = (model_size.y * lottie.scale).round() as u32;
+        let depth = lottie
+            .timeline()
+            .items()
+            .map(|layer| layer.zindex)
+            .fold(0.0, f32::max)
+            + 1.0;
+        // Center the composition on the instance, with its layers between the
+        // depth of the instance and one unit above
+        let root_transform =
+            Transform::from_scale(Vec3::new(lottie.scale, -lottie.scale, 1.0 / depth))
+                .with_translation(Vec3::new(
+                    model_size.x * (lottie.scale / -2.0),
+                    model_size.y * (lottie.scale / 2.0),
+                    1.0,
+                ));
+        let root_entity = commands
+            .spawn(SpatialBundle::from_transform(root_transform))
+            .insert(LottieRoot {
+                instance,
+                scale: lottie.scale,
+            })
+            .id();
+        commands.entity(instance).add_child(root_entity);
+        let mask_transform = Mat4::from_scale(Vec3::new(lottie.scale, lottie.scale, 1.0))
+            * root_transform.compute_matrix().inverse();
 
-        let mut cpu_image = Image {
+        // Create the mask texture
+        let mask_size = Extent3d {
+            width: std::cmp::max(1, width * mask_count),
+            height,
+            depth_or_array_layers: 1,
+        };
+        let mut mask = Image {
             texture_descriptor: TextureDescriptor {
-                label: Some("cpu image"),
-                size,
+                label: Some("mask_texture"),
+                size: mask_size,
                 dimension: TextureDimension::D2,
                 format: TextureFormat::bevy_default(),
                 mip_level_count: 1,
                 sample_count: 1,
-                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                usage: TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_DST
+                    | TextureUsages::COPY_SRC
+                    | TextureUsages::RENDER_ATTACHMENT,
                 view_formats: &[],
             },
-            ..Default::default()
+            ..default()
         };
-        cpu_image.resize(size);
-        let cpu_image_handle = image_assets.add(cpu_image);
-        let render_target_image_handle = if target == Target::Default {
-            let mut render_target_image = Image {
-                texture_descriptor: TextureDescriptor {
-                    label: Some("render target image"),
-                    size,
-                    dimension: TextureDimension::D2,
-                    format: TextureFormat::bevy_default(),
-                    mip_level_count: 1,
-                    sample_count: 1,
-                    usage: TextureUsages::TEXTURE_BINDING
-                        | TextureUsages::COPY_DST
-                        | TextureUsages::COPY_SRC
-                        | TextureUsages::RENDER_ATTACHMENT,
-                    view_formats: &[],
+        mask.resize(mask_size);
+        let mask_texture_handle = image_assets.add(mask);
+        let mask_layers = if mask_count == 0 {
+            RenderLayers::none()
+        } else if let Some(render_layer) = render_layers.allocate(instance) {
+            // Masks are laid out side by side, looked at regardless of the
+            // transform of the instance
+            let mask_camera = Camera2dBundle {
+                camera_2d: Camera2d {
+                    clear_color: ClearColorConfig::Custom(Color::NONE),
+                },
+                camera: Camera {
+                    target: RenderTarget::Image(mask_texture_handle.clone()),
+                    // before the layers drawn offscreen, which may be masked
+                    order: MASK_CAMERA_ORDER,
+                    ..default()
                 },
-                ..Default::default()
+                transform: Transform::from_scale(Vec3::new(
+                    1.0 / lottie.scale,
+                    -1.0 / lottie.scale,
+                    1.0,
+                ))
+                .with_translation(Vec3::new(
+                    model_size.x * mask_count as f32 / 2.0,
+                    model_size.y / 2.0,
+                    0.0,
+                )),
+                ..default()
             };
-            render_target_image.resize(size);
-            image_assets.add(render_target_image)
+            let mask_camera = commands
+                .spawn(mask_camera)
+                .insert(RenderLayers::layer(render_layer))
+                .id();
+            commands.entity(root_entity).add_child(mask_camera);
+            RenderLayers::layer(render_layer)
         } else {
-            mask_texture_handle.clone()
+            log::warn!("no render layer left for the masks of {:?}", instance);
+            RenderLayers::none()
         };
 
-        if target == Target::Default {
-            camera.camera.target = RenderTarget::Image(render_target_image_handle.clone());
-        }
-        commands.spawn(ImageCopier::new(
-            render_target_image_handle,
-            cpu_image_handle.clone(),
-            size,
-            &render_device,
-        ));
-        commands.spawn(ImageToSave(cpu_image_handle.clone()));
-    }
-
-    commands.spawn(camera);
-
-    let mut info = LottieAnimationInfo {
-        start_frame: lottie.model.start_frame,
-        end_frame: lottie.model.end_frame,
-        frame_rate: lottie.model.frame_rate,
-        current_time: 0.0,
-        paused: false,
-        width: lottie.model.width as f32,
-        height: lottie.model.height as f32,
-        finished_once: false,
-        entities: HashMap::new(),
-    };
-
-    let root_entity = commands
-        .spawn(VisibilityBundle::default())
-        .insert(TransformBundle::from_transform(
-            Transform::from_scale(Vec3::new(lottie.scale, lottie.scale, 1.0))
-                .with_translation(root_translation),
-        ))
-        .id();
-    let mut unresolved: HashMap<TimelineItemId, Vec<Entity>> = HashMap::new();
-    let mut mask_index = 0_u32;
-    let mut mask_registry = HashMap::new();
-    let mut zindexes = lottie
-        .timeline()
-        .items()
-        .map(|layer| OrderedFloat(layer.zindex))
-        .collect::<Vec<_>>();
-    zindexes.sort();
-    // First we spawn all mask layers
-    for layer in lottie.timeline().items() {
-        if layer.is_mask {
-            let prev_zindex = zindexes
+        let mut info = LottieAnimationInfo {
+            start_frame: lottie.model.start_frame,
+            end_frame: lottie.model.end_frame,
+            frame_rate: lottie.model.frame_rate,
+            current_time: 0.0,
+            paused: false,
+            width: lottie.model.width as f32,
+            height: lottie.model.height as f32,
+            finished_once: false,
+            manual: false,
+            entities: HashMap::new(),
+            root: root_entity,
+            mask_texture: mask_texture_handle.clone(),
+            segments: lottie
+                .model
+                .markers
                 .iter()
-                .position(|i| *i == OrderedFloat(layer.zindex))
-                .and_then(|pos| {
-                    if pos == 0 {
-                        None
-                    } else {
-                        zindexes.get(pos - 1)
-                    }
-                })
-                .cloned()
-                .unwrap_or(OrderedFloat(-1.0))
-                .0;
-            let entity = BevyStagedLayer {
-                layer,
-                zindex_window: layer.zindex - prev_zindex,
-                meshes: &mut meshes,
-                image_assets: &mut image_assets,
-                audio_assets: &mut audio_assets,
-                material_assets: &mut material_assets,
-                mask_handle: mask_texture_handle.clone(),
-                mask_index: &mut mask_index,
-                mask_registry: &mut mask_registry,
-                mask_count,
-                model_size: Vec2::new(lottie.model.width as f32, lottie.model.height as f32),
-                scale,
+                .filter_map(|marker| Some((marker.name.clone(), lottie.segment(&marker.name)?)))
+                .collect(),
+            frame_range: None,
+            speed: 1.0,
+            reversed: false,
+            ping_pong: false,
+            loop_count: None,
+            loops_done: 0,
+            shown_frame: None,
+        };
+
+        let mut unresolved: HashMap<TimelineItemId, Vec<Entity>> = HashMap::new();
+        let mut mask_index = 0_u32;
+        let mut mask_registry = HashMap::new();
+        let mut offscreen_registry = HashMap::new();
+        // Blended layers need what is drawn below them, which is drawn once
+        // more on a render layer of their own
+        let backdrops = lottie
+            .timeline()
+            .items()
+            .filter(|layer| is_blended(layer))
+            .filter_map(|layer| match render_layers.allocate(instance) {
+                Some(render_layer) => Some(Backdrop {
+                    zindex: layer.zindex,
+                    render_layer,
+                    compositing: lottie.timeline().compositing_layer(layer).map(|l| l.id),
+                }),
+                None => {
+                    log::warn!("no render layer left for the backdrop of {:?}", layer.name);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut zindexes = lottie
+            .timeline()
+            .items()
+            .map(|layer| OrderedFloat(layer.zindex))
+            .collect::<Vec<_>>();
+        zindexes.sort();
+        // First we spawn all mask layers
+        for layer in lottie.timeline().items() {
+            if layer.is_mask {
+                let prev_zindex = zindexes
+                    .iter()
+                    .position(|i| *i == OrderedFloat(layer.zindex))
+                    .and_then(|pos| {
+                        if pos == 0 {
+                            None
+                        } else {
+                            zindexes.get(pos - 1)
+                        }
+                    })
+                    .cloned()
+                    .unwrap_or(OrderedFloat(-1.0))
+                    .0;
+                let entity = BevyStagedLayer {
+                    layer,
+                    lottie,
+                    zindex_window: layer.zindex - prev_zindex,
+                    meshes: &mut meshes,
+                    image_assets: &mut image_assets,
+                    audio_assets: &mut audio_assets,
+                    material_assets: &mut material_assets,
+                    mask_handle: mask_texture_handle.clone(),
+                    mask_index: &mut mask_index,
+                    mask_registry: &mut mask_registry,
+                    mask_count,
+                    mask_layers,
+                    mask_transform,
+                    model_size,
+                    scale,
+                    effect_material_assets: &mut effect_material_assets,
+                    offscreen_registry: &mut offscreen_registry,
+                    backdrops: &backdrops,
+                    render_layers: &mut render_layers,
+                    instance,
+                    root: root_entity,
+                    target_size: UVec2::new(width, height),
+                }
+                .spawn(&mut commands)
+                .unwrap();
+                info.entities.insert(layer.id, entity);
             }
-            .spawn(&mut commands)
-            .unwrap();
-            info.entities.insert(layer.id, entity);
         }
-    }
-    for layer in lottie.timeline().items() {
-        let entity = if !layer.is_mask {
-            let prev_zindex = zindexes
-                .iter()
-                .position(|i| *i == OrderedFloat(layer.zindex))
-                .and_then(|pos| {
-                    if pos == 0 {
-                        None
-                    } else {
-                        zindexes.get(pos - 1)
-                    }
-                })
-                .cloned()
-                .unwrap_or(OrderedFloat(-1.0))
-                .0;
-            let entity = BevyStagedLayer {
-                zindex_window: layer.zindex - prev_zindex,
-                layer,
-                meshes: &mut meshes,
-                image_assets: &mut image_assets,
-                audio_assets: &mut audio_assets,
-                material_assets: &mut material_assets,
-                mask_handle: mask_texture_handle.clone(),
-                mask_index: &mut mask_index,
-                mask_registry: &mut mask_registry,
-                mask_count,
-                model_size: Vec2::new(lottie.model.width as f32, lottie.model.height as f32),
-                scale,
-            }
-            .spawn(&mut commands)
-            .unwrap();
-            info.entities.insert(layer.id, entity);
-            entity
-        } else {
-            *info.entities.get(&layer.id).unwrap()
-        };
-        if let Some(parent_id) = layer.parent {
-            if let Some(parent_entity) = info.entities.get(&parent_id) {
-                log::trace!("adding {:?} -> {:?}", entity, parent_entity);
-                commands.entity(*parent_entity).add_child(entity);
+        for layer in lottie.timeline().items() {
+            let entity = if !layer.is_mask {
+                let prev_zindex = zindexes
+                    .iter()
+                    .position(|i| *i == OrderedFloat(layer.zindex))
+                    .and_then(|pos| {
+                        if pos == 0 {
+                            None
+                        } else {
+                            zindexes.get(pos - 1)
+                        }
+                    })
+                    .cloned()
+                    .unwrap_or(OrderedFloat(-1.0))
+                    .0;
+                let entity = BevyStagedLayer {
+                    zindex_window: layer.zindex - prev_zindex,
+                    layer,
+                    lottie,
+                    meshes: &mut meshes,
+                    image_assets: &mut image_assets,
+                    audio_assets: &mut audio_assets,
+                    material_assets: &mut material_assets,
+                    mask_handle: mask_texture_handle.clone(),
+                    mask_index: &mut mask_index,
+                    mask_registry: &mut mask_registry,
+                    mask_count,
+                    mask_layers,
+                    mask_transform,
+                    model_size,
+                    scale,
+                    effect_material_assets: &mut effect_material_assets,
+                    offscreen_registry: &mut offscreen_registry,
+                    backdrops: &backdrops,
+                    render_layers: &mut render_layers,
+                    instance,
+                    root: root_entity,
+                    target_size: UVec2::new(width, height),
+                }
+                .spawn(&mut commands)
+                .unwrap();
+                info.entities.insert(layer.id, entity);
+                entity
             } else {
-                unresolved.entry(parent_id).or_default().push(entity);
+                *info.entities.get(&layer.id).unwrap()
+            };
+            if let Some(parent_id) = layer.parent {
+                if let Some(parent_entity) = info.entities.get(&parent_id) {
+                    log::trace!("adding {:?} -> {:?}", entity, parent_entity);
+                    commands.entity(*parent_entity).add_child(entity);
+                } else {
+                    unresolved.entry(parent_id).or_default().push(entity);
+                }
+            } else {
+                log::trace!("adding {:?} -> {:?}", entity, root_entity);
+                commands.entity(root_entity).add_child(entity);
             }
-        } else {
-            log::trace!("adding {:?} -> {:?}", entity, root_entity);
-            commands.entity(root_entity).add_child(entity);
-        }
-        if let Some(entities) = unresolved.remove(&layer.id) {
-            let mut current = commands.entity(entity);
-            for entity in entities {
-                current.add_child(entity);
+            if let Some(entities) = unresolved.remove(&layer.id) {
+                let mut current = commands.entity(entity);
+                for entity in entities {
+                    current.add_child(entity);
+                }
             }
         }
+        commands.entity(instance).insert(info);
     }
-    commands.insert_resource(info);
+}
 
-    let comp = LottieComp { lottie };
-    commands.entity(root_entity).insert(comp);
+/// Free the render layers of despawned instances
+fn release_render_layers_system(
+    mut removed: RemovedComponents<LottieAnimationInfo>,
+    mut render_layers: ResMut<RenderLayerAllocator>,
+) {
+    for instance in removed.iter() {
+        render_layers.release(instance);
+    }
+}
+
+/// Keep sampling masks at the right place when instances move, as masks are
+/// drawn regardless of the transform of their instance
+fn mask_transform_system(
+    roots: Query<(&LottieRoot, &GlobalTransform), Changed<GlobalTransform>>,
+    shapes: Query<(&Handle<LottieMaterial>, &FrameTracker)>,
+    mut material_assets: ResMut<Assets<LottieMaterial>>,
+) {
+    let transforms = roots
+        .iter()
+        .map(|(root, transform)| {
+            let scale = Mat4::from_scale(Vec3::new(root.scale, root.scale, 1.0));
+            (root.instance, scale * transform.compute_matrix().inverse())
+        })
+        .collect::<HashMap<_, _>>();
+    if transforms.is_empty() {
+        return;
+    }
+    for (handle, tracker) in shapes.iter() {
+        let transform = match transforms.get(&tracker.instance) {
+            Some(transform) => *transform,
+            None => continue,
+        };
+        let masked = material_assets
+            .get(handle)
+            .map_or(false, |material| material.mask_info.mask_count > 0);
+        if masked {
+            if let Some(material) = material_assets.get_mut(handle) {
+                material.mask_transform = transform;
+            }
+        }
+    }
 }
 
 fn animate_system(
+    mut instances: Query<(Entity, &mut LottieAnimationInfo)>,
     mut visibility_query: Query<(
         Entity,
         &mut Visibility,
@@ -515,40 +1028,24 @@ fn animate_system(
     mut transform_animation: Query<(&mut Animator<Transform>, &FrameTracker)>,
     mut path_animation: Query<(&mut Animator<Path>, &FrameTracker)>,
     mut draw_mode_animation: Query<(&mut Animator<DrawMode>, &FrameTracker)>,
-    mut info: ResMut<LottieAnimationInfo>,
-    lottie: Res<LottieGlobals>,
+    lottie: Option<Res<LottieGlobals>>,
     time: Res<Time>,
 ) {
-    let capturing = lottie.capturing;
-    if info.paused {
-        for (mut a, _) in transform_animation.iter_mut() {
-            a.state = AnimatorState::Paused;
-        }
-        for (mut a, _) in path_animation.iter_mut() {
-            a.state = AnimatorState::Paused;
-        }
-        for (mut a, _) in draw_mode_animation.iter_mut() {
-            a.state = AnimatorState::Paused;
-        }
-        return;
-    }
-    let delta = if capturing {
-        1.0 / info.frame_rate
-    } else {
-        time.delta_seconds()
-    };
-    if info.current_time >= info.end_frame / info.frame_rate {
-        info.finished_once = true;
-        if capturing {
-            info.current_time += delta;
-            return;
-        } else {
-            info.current_time = 0.0;
-        }
-    }
-    let current_frame = info.current_time * info.frame_rate;
+    let capturing = lottie.map_or(false, |lottie| lottie.capturing);
+    let frames = instances
+        .iter_mut()
+        .map(|(entity, mut info)| (entity, info.tick(capturing, time.delta_seconds())))
+        .collect::<HashMap<_, _>>();
+    let current_frame = |tracker: &FrameTracker| frames.get(&tracker.instance).copied().flatten();
 
     for (mut a, tracker) in transform_animation.iter_mut() {
+        let current_frame = match current_frame(tracker) {
+            Some(frame) => frame,
+            None => {
+                a.state = AnimatorState::Paused;
+                continue;
+            }
+        };
         let total = a.tweenable().duration().as_secs_f32();
         if total == 0.0 {
             a.tweenable_mut()
@@ -563,6 +1060,13 @@ fn animate_system(
     }
 
     for (mut a, tracker) in path_animation.iter_mut() {
+        let current_frame = match current_frame(tracker) {
+            Some(frame) => frame,
+            None => {
+                a.state = AnimatorState::Paused;
+                continue;
+            }
+        };
         let total = a.tweenable().duration().as_secs_f32();
         if total == 0.0 {
             a.tweenable_mut()
@@ -577,6 +1081,13 @@ fn animate_system(
     }
 
     for (mut a, tracker) in draw_mode_animation.iter_mut() {
+        let current_frame = match current_frame(tracker) {
+            Some(frame) => frame,
+            None => {
+                a.state = AnimatorState::Paused;
+                continue;
+            }
+        };
         if let Some(frame) = tracker.value(current_frame) {
             a.state = AnimatorState::Playing;
             let secs = frame / tracker.frame_rate();
@@ -588,6 +1099,10 @@ fn animate_system(
 
     for (_, mut visibility, computed_visibility, audio_sink, tracker) in visibility_query.iter_mut()
     {
+        let current_frame = match current_frame(tracker) {
+            Some(frame) => frame,
+            None => continue,
+        };
         let visible = tracker.value(current_frame).is_some();
         if let Some(sink) = audio_sink {
             if !computed_visibility.is_visible() && visible {
@@ -602,20 +1117,97 @@ fn animate_system(
             Visibility::Hidden
         };
     }
+}
 
-    info.current_time += delta;
+/// Update the gradient uniforms of materials whose gradients are animated, at
+/// the frame `animate_system` drove the other animations to
+fn gradient_animation_system(
+    query: Query<(&Handle<LottieMaterial>, &GradientAnimator, &FrameTracker)>,
+    mut material_assets: ResMut<Assets<LottieMaterial>>,
+    instances: Query<&LottieAnimationInfo>,
+) {
+    for (handle, animator, tracker) in query.iter() {
+        let shown_frame = instances
+            .get(tracker.instance)
+            .ok()
+            .and_then(|info| info.shown_frame);
+        let frame = match shown_frame.and_then(|frame| tracker.value(frame)) {
+            Some(frame) => frame,
+            None => continue,
+        };
+        if let Some(material) = material_assets.get_mut(handle) {
+            if let Some(gradient) = animator.fill.as_ref() {
+                material.gradient = GradientDataUniform::new(gradient, frame);
+            }
+            if let Some(gradient) = animator.stroke.as_ref() {
+                material.stroke_gradient = GradientDataUniform::new(gradient, frame);
+            }
+        }
+    }
+}
+
+/// Update the feather of layer masks whose feather is animated
+fn mask_animation_system(
+    query: Query<(&Handle<LottieMaterial>, &MaskAnimator, &FrameTracker)>,
+    mut material_assets: ResMut<Assets<LottieMaterial>>,
+    instances: Query<&LottieAnimationInfo>,
+) {
+    for (handle, animator, tracker) in query.iter() {
+        let shown_frame = instances
+            .get(tracker.instance)
+            .ok()
+            .and_then(|info| info.shown_frame);
+        let frame = match shown_frame.and_then(|frame| tracker.value(frame)) {
+            Some(frame) => frame,
+            None => continue,
+        };
+        if let Some(material) = material_assets.get_mut(handle) {
+            for (index, composition) in animator.feathers.iter() {
+                material.mask_info.masks[*index].w = composition.feather_step(frame).to_bits();
+            }
+        }
+    }
+}
+
+/// Update the effect uniforms of layers whose effects are animated
+fn effect_animation_system(
+    query: Query<(&Handle<EffectMaterial>, &EffectAnimator, &FrameTracker)>,
+    mut material_assets: ResMut<Assets<EffectMaterial>>,
+    instances: Query<&LottieAnimationInfo>,
+) {
+    for (handle, animator, tracker) in query.iter() {
+        let shown_frame = instances
+            .get(tracker.instance)
+            .ok()
+            .and_then(|info| info.shown_frame);
+        let frame = match shown_frame.and_then(|frame| tracker.value(frame)) {
+            Some(frame) => frame,
+            None => continue,
+        };
+        if let Some(material) = material_assets.get_mut(handle) {
+            material.effects =
+                EffectDataUniform::new(&animator.effects, frame, animator.pixel_ratio);
+        }
+    }
 }
 
 fn save_img(
     image_to_save: Query<&ImageToSave>,
-    info: Res<LottieAnimationInfo>,
-    mut images: ResMut<Assets<Image>>,
+    instances: Query<&LottieAnimationInfo, With<PrimaryLottie>>,
+    images: Res<Assets<Image>>,
     image_sender: Res<FrameSender>,
     mut exit: EventWriter<AppExit>,
 ) {
-    // Capture has 3 frames latency
+    let info = match instances.get_single() {
+        Ok(info) => info,
+        Err(_) => return,
+    };
+    // Frames are requested one by one with `render_frame`
+    if info.manual {
+        return;
+    }
     let delta = 1.0 / info.frame_rate;
-    let timestamp = info.current_time - 3.0 * delta;
+    let timestamp = info.current_time - CAPTURE_LATENCY as f32 * delta;
     if timestamp <= 0.0 {
         return;
     } else if info.finished_once && timestamp * info.frame_rate > info.end_frame {
@@ -628,38 +1220,191 @@ fn save_img(
     }
     log::trace!("capturing frame at timestamp {}", timestamp - delta);
     for capture in image_to_save.iter() {
-        let image = images.get_mut(capture).unwrap();
-        let (width, height) = (image.size().x as u32, image.size().y as u32);
-        let data = &mut image.data;
-        if data.is_empty() {
-            continue;
+        let image = images.get(capture).unwrap();
+        if let Some(frame) = frame_data(image, (timestamp * 1000.0) as i32) {
+            image_sender.sender.unbounded_send(frame).unwrap();
         }
-        let unpadded_len = (width * height) as usize * 4;
-        let data = if data.len() != unpadded_len {
-            // Has padding
-            let padded_width = width * 4
-                + (wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
-                    - (width * 4 % wgpu::COPY_BYTES_PER_ROW_ALIGNMENT));
-            let mut result = Vec::with_capacity(unpadded_len);
-            for chunk in data
-                .chunks_exact_mut(padded_width as usize)
-                .take(height as usize)
-            {
-                result.extend_from_slice(&chunk[..(unpadded_len / height as usize)]);
-            }
-            assert_eq!(unpadded_len, result.len());
-            Cow::Owned(result)
-        } else {
-            Cow::Borrowed(data)
-        };
-        image_sender
-            .sender
-            .unbounded_send(FrameData {
-                data: data.into_owned(),
-                width,
-                height,
-                timestamp: (timestamp * 1000.0) as i32,
-            })
-            .unwrap();
+    }
+}
+
+/// Convert a captured image into [FrameData], removing row paddings if any.
+/// Returns `None` if the image has not been captured yet
+fn frame_data(image: &Image, timestamp: i32) -> Option<FrameData> {
+    let (width, height) = (image.size().x as u32, image.size().y as u32);
+    let data = &image.data;
+    if data.is_empty() {
+        return None;
+    }
+    let unpadded_len = (width * height) as usize * 4;
+    let data = if data.len() != unpadded_len {
+        // Has padding
+        let padded_width = width * 4
+            + (wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
+                - (width * 4 % wgpu::COPY_BYTES_PER_ROW_ALIGNMENT));
+        let mut result = Vec::with_capacity(unpadded_len);
+        for chunk in data
+            .chunks_exact(padded_width as usize)
+            .take(height as usize)
+        {
+            result.extend_from_slice(&chunk[..(unpadded_len / height as usize)]);
+        }
+        assert_eq!(unpadded_len, result.len());
+        result
+    } else {
+        data.clone()
+    };
+    Some(FrameData {
+        data,
+        width,
+        height,
+        timestamp,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An animation of 8 frames at 8 fps, with an "intro" segment on its first
+    /// half and an "outro" one on its second half
+    fn info() -> LottieAnimationInfo {
+        LottieAnimationInfo {
+            start_frame: 0.0,
+            end_frame: 8.0,
+            frame_rate: 8.0,
+            current_time: 0.0,
+            paused: false,
+            width: 100.0,
+            height: 100.0,
+            finished_once: false,
+            manual: false,
+            entities: HashMap::new(),
+            root: Entity::PLACEHOLDER,
+            mask_texture: Handle::default(),
+            segments: HashMap::from_iter([
+                (String::from("intro"), 0.0..4.0),
+                (String::from("outro"), 4.0..8.0),
+            ]),
+            frame_range: None,
+            speed: 1.0,
+            reversed: false,
+            ping_pong: false,
+            loop_count: None,
+            loops_done: 0,
+            shown_frame: None,
+        }
+    }
+
+    /// Frames shown by `count` updates a quarter of a second apart
+    fn play(info: &mut LottieAnimationInfo, count: usize) -> Vec<Option<f32>> {
+        (0..count).map(|_| info.tick(false, 0.25)).collect()
+    }
+
+    fn shown(frames: &[f32]) -> Vec<Option<f32>> {
+        frames.iter().copied().map(Some).collect()
+    }
+
+    #[test]
+    fn loops_forever() {
+        let mut info = info();
+        assert_eq!(play(&mut info, 5), shown(&[0.0, 2.0, 4.0, 6.0, 0.0]));
+        // Systems running after `animate_system` see the frame it wrapped to
+        assert_eq!(info.shown_frame, Some(0.0));
+        assert!(info.finished_once);
+        assert!(!info.paused());
+    }
+
+    #[test]
+    fn speed() {
+        let mut info = info();
+        info.set_speed(2.0);
+        assert_eq!(play(&mut info, 3), shown(&[0.0, 4.0, 0.0]));
+    }
+
+    #[test]
+    fn reverse_start() {
+        let mut info = info();
+        info.set_loop_count(Some(1));
+        info.set_reversed(true);
+        // Starts from the last frame instead of finishing on the first one
+        assert_eq!(play(&mut info, 3), shown(&[8.0, 6.0, 4.0]));
+        assert!(!info.finished_once);
+        assert!(!info.paused());
+    }
+
+    #[test]
+    fn ping_pong_turnaround() {
+        let mut info = info();
+        info.set_ping_pong(true);
+        assert_eq!(
+            play(&mut info, 10),
+            shown(&[0.0, 2.0, 4.0, 6.0, 8.0, 6.0, 4.0, 2.0, 0.0, 2.0])
+        );
+        assert!(!info.paused());
+    }
+
+    #[test]
+    fn finish_loop_count() {
+        let mut info = info();
+        info.set_loop_count(Some(2));
+        assert_eq!(
+            play(&mut info, 9),
+            shown(&[0.0, 2.0, 4.0, 6.0, 0.0, 2.0, 4.0, 6.0, 8.0])
+        );
+        assert!(info.paused());
+        assert_eq!(play(&mut info, 2), vec![None, None]);
+        assert_eq!(info.current_frame(), 8.0);
+
+        // Each way counts as a loop when ping-ponging
+        let mut info = self::info();
+        info.set_ping_pong(true);
+        info.set_loop_count(Some(2));
+        assert_eq!(
+            play(&mut info, 9),
+            shown(&[0.0, 2.0, 4.0, 6.0, 8.0, 6.0, 4.0, 2.0, 0.0])
+        );
+        assert_eq!(play(&mut info, 1), vec![None]);
+    }
+
+    #[test]
+    fn resume_after_finishing() {
+        let mut info = info();
+        info.set_loop_count(Some(1));
+        play(&mut info, 5);
+        assert!(info.paused());
+        info.pause(false);
+        assert_eq!(play(&mut info, 2), shown(&[0.0, 2.0]));
+
+        let mut info = self::info();
+        info.set_loop_count(Some(1));
+        info.set_reversed(true);
+        assert_eq!(play(&mut info, 5), shown(&[8.0, 6.0, 4.0, 2.0, 0.0]));
+        assert_eq!(play(&mut info, 1), vec![None]);
+        info.pause(false);
+        assert_eq!(play(&mut info, 2), shown(&[8.0, 6.0]));
+    }
+
+    #[test]
+    fn segments() {
+        let mut info = info();
+        assert!(!info.play_segment("missing"));
+
+        assert!(info.play_segment("intro"));
+        assert_eq!(play(&mut info, 3), shown(&[0.0, 2.0, 4.0]));
+        assert_eq!(play(&mut info, 1), vec![None]);
+
+        assert!(info.loop_segment("outro"));
+        assert_eq!(play(&mut info, 4), shown(&[4.0, 6.0, 4.0, 6.0]));
+
+        // The segment jumped to is played the same way
+        assert!(info.jump_to_segment("intro"));
+        assert_eq!(play(&mut info, 3), shown(&[0.0, 2.0, 0.0]));
+
+        info.play_all();
+        assert_eq!(play(&mut info, 5), shown(&[0.0, 2.0, 4.0, 6.0, 0.0]));
+
+        // Without a segment being played, the animation goes on past it
+        assert!(info.jump_to_segment("outro"));
+        assert_eq!(play(&mut info, 3), shown(&[4.0, 6.0, 0.0]));
     }
 }
@@ -1,6 +1,6 @@
 use bevy::asset::AssetPath;
 use bevy::math::Vec2;
-use bevy::prelude::{Color, Handle, Image, UVec4, Vec4};
+use bevy::prelude::{Color, Handle, Image, Mat4, UVec4, Vec4};
 use bevy::reflect::{TypePath, TypeUuid};
 use bevy::render::mesh::MeshVertexBufferLayout;
 use bevy::render::render_resource::{
@@ -8,13 +8,13 @@ use bevy::render::render_resource::{
     VertexBufferLayout,
 };
 use bevy::sprite::{Material2d, Material2dKey};
-use lottie_core::prelude::GradientColor;
+use lottie_core::prelude::{Gradient, GradientColor, GradientType, LayerEffect, Rgba};
 use wgpu::*;
 
 #[derive(AsBindGroup, TypeUuid, Clone, TypePath)]
 #[uuid = "e66b6c0e-bcac-4128-bdc6-9a3cace5c2fc"]
 // #[uniform(3, GradientDataUniform)]
-#[bind_group_data(GradientDataKey)]
+#[bind_group_data(LottieMaterialKey)]
 pub struct LottieMaterial {
     #[texture(0)]
     #[sampler(1)]
@@ -22,40 +22,28 @@ pub struct LottieMaterial {
     // width, height, scale
     #[uniform(2)]
     pub size: Vec4,
-    /// Mask index, mask count, matte mode
+    /// Mask index, matte mode, mask mode with whether it starts a new set of
+    /// layer masks in its 4th bit, and feather step as `f32` bits, along with
+    /// the mask count
     #[uniform(3)]
     pub mask_info: MaskDataUniform,
     #[uniform(4)]
     pub gradient: GradientDataUniform,
+    #[uniform(5)]
+    pub stroke_gradient: GradientDataUniform,
+    /// From world space to the pixels of the mask texture, which follows the
+    /// transform of the Lottie instance
+    #[uniform(6)]
+    pub mask_transform: Mat4,
 }
 
 impl Material2d for LottieMaterial {
     fn vertex_shader() -> ShaderRef {
-        let current_exe = std::env::current_exe().unwrap();
-        let mut path = current_exe.clone();
-        path.push("assets/shader.wgsl");
-
-        while !path.exists() && path.parent().is_some() {
-            path.pop();
-            path.pop();
-            path.pop();
-            path.push("assets/shader.wgsl");
-        }
-        AssetPath::from(path).into()
+        shader_path("assets/shader.wgsl")
     }
 
     fn fragment_shader() -> ShaderRef {
-        let current_exe = std::env::current_exe().unwrap();
-        let mut path = current_exe.clone();
-        path.push("assets/shader.wgsl");
-
-        while !path.exists() && path.parent().is_some() {
-            path.pop();
-            path.pop();
-            path.pop();
-            path.push("assets/shader.wgsl");
-        }
-        AssetPath::from(path).into()
+        shader_path("assets/shader.wgsl")
     }
 
     fn specialize(
@@ -80,14 +68,29 @@ impl Material2d for LottieMaterial {
             VertexBufferLayout::from_vertex_formats(VertexStepMode::Vertex, formats);
         descriptor.vertex.buffers = vec![vertex_layout];
 
+        let fragment = descriptor.fragment.as_mut().unwrap();
         if key.bind_group_data.use_gradient {
-            let fragment = descriptor.fragment.as_mut().unwrap();
             fragment.shader_defs.push("USE_GRADIENT".into());
         }
         Ok(())
     }
 }
 
+/// Look for `name` in the ancestors of the current executable
+fn shader_path(name: &str) -> ShaderRef {
+    let current_exe = std::env::current_exe().unwrap();
+    let mut path = current_exe.clone();
+    path.push(name);
+
+    while !path.exists() && path.parent().is_some() {
+        path.pop();
+        path.pop();
+        path.pop();
+        path.push(name);
+    }
+    AssetPath::from(path).into()
+}
+
 #[derive(Clone)]
 pub struct GradientInfo {
     pub start_pos: Vec2,
@@ -95,27 +98,96 @@ pub struct GradientInfo {
 }
 
 #[derive(Eq, PartialEq, Hash, Clone)]
-pub struct GradientDataKey {
+pub struct LottieMaterialKey {
     use_gradient: bool,
 }
 
+/// Most stops of a single gradient, which must match the size of
+/// `GradientInfo::stops` in `shader.wgsl`. Gradients with more stops are
+/// reduced to this many, see [reduce_stops]
+pub const MAX_GRADIENT_STOPS: usize = 32;
+
 #[derive(Clone, Default, ShaderType)]
 pub struct GradientDataUniform {
     pub start: Vec2,
     pub end: Vec2,
+    /// Focal point of radial gradients
+    pub focal: Vec2,
+    /// 0 without a gradient, 1 for linear and 2 for radial gradients
+    pub use_gradient: u32,
+    pub stop_count: u32,
     // #[size(runtime)]
     // TODO: change this to a Vec (which compiles to a storage buffer) when bevy supports it
     // tracking: https://github.com/bevyengine/bevy/issues/5499
-    pub use_gradient: u32,
-    pub stops: [GradientDataStop; 2],
+    pub stops: [GradientDataStop; MAX_GRADIENT_STOPS],
 }
 
+impl GradientDataUniform {
+    pub fn new(gradient: &Gradient, frame: f32) -> Self {
+        let start = gradient.start.value(frame);
+        let end = gradient.end.value(frame);
+        let (use_gradient, focal) = match gradient.gradient_ty {
+            GradientType::Linear => (1, start),
+            GradientType::Radial => (2, gradient.focal_point(frame)),
+        };
+        let colors = reduce_stops(gradient.colors.colors.value(frame));
+        let mut stops: [GradientDataStop; MAX_GRADIENT_STOPS] = Default::default();
+        for (stop, color) in stops.iter_mut().zip(&colors) {
+            *stop = color.into();
+        }
+        GradientDataUniform {
+            start: Vec2::new(start.x, start.y),
+            end: Vec2::new(end.x, end.y),
+            focal: Vec2::new(focal.x, focal.y),
+            use_gradient,
+            stop_count: colors.len().min(MAX_GRADIENT_STOPS) as u32,
+            stops,
+        }
+    }
+}
+
+/// Drop the stops changing the gradient the least until at most
+/// [MAX_GRADIENT_STOPS] are left. The first and last stops, and both stops of
+/// hard edges, are dropped last
+fn reduce_stops(mut colors: Vec<GradientColor>) -> Vec<GradientColor> {
+    let channels = |c: &Rgba| [c.r, c.g, c.b, c.a].map(|v| v as f32);
+    while colors.len() > MAX_GRADIENT_STOPS {
+        // How far a stop is from the color between its neighbors
+        let error = |index: usize| {
+            let (prev, stop, next) = (&colors[index - 1], &colors[index], &colors[index + 1]);
+            let span = next.offset - prev.offset;
+            let t = if span > 0.0 {
+                (stop.offset - prev.offset) / span
+            } else {
+                0.0
+            };
+            let (a, b, c) = (
+                channels(&prev.color),
+                channels(&stop.color),
+                channels(&next.color),
+            );
+            (0..4)
+                .map(|i| (a[i] + (c[i] - a[i]) * t - b[i]).abs())
+                .fold(0.0, f32::max)
+        };
+        let index = (1..colors.len() - 1)
+            .min_by(|a, b| error(*a).total_cmp(&error(*b)))
+            .unwrap_or(1);
+        colors.remove(index);
+    }
+    colors
+}
+
+/// Most masks applied to a single layer, which must match the size of
+/// `MaskInfo::masks` in `shader.wgsl`
+pub const MAX_MASKS: usize = 16;
+
 #[derive(Clone, Default, ShaderType)]
 pub struct MaskDataUniform {
     // #[size(runtime)]
     // TODO: change this to a Vec (which compiles to a storage buffer) when bevy supports it
     // tracking: https://github.com/bevyengine/bevy/issues/5499
-    pub masks: [UVec4; 4],
+    pub masks: [UVec4; MAX_MASKS],
     pub mask_count: u32,
     pub mask_total_count: u32,
 }
@@ -140,10 +212,155 @@ impl<'a> From<&'a GradientColor> for GradientDataStop {
     }
 }
 
-impl From<&LottieMaterial> for GradientDataKey {
+impl From<&LottieMaterial> for LottieMaterialKey {
     fn from(material: &LottieMaterial) -> Self {
         Self {
-            use_gradient: material.gradient.stops.is_empty(),
+            use_gradient: material.gradient.use_gradient != 0
+                || material.stroke_gradient.use_gradient != 0,
+        }
+    }
+}
+
+/// Composites the offscreen target of a layer, applying its effects and then
+/// blending it with its backdrop
+#[derive(AsBindGroup, TypeUuid, Clone, TypePath)]
+#[uuid = "e248400e-3aac-4061-80fd-ae053c2a4dcc"]
+pub struct EffectMaterial {
+    #[texture(0)]
+    #[sampler(1)]
+    pub texture: Handle<Image>,
+    #[uniform(2)]
+    pub effects: EffectDataUniform,
+    /// What is drawn below the layer, `None` with the normal blend mode
+    #[texture(3)]
+    #[sampler(4)]
+    pub backdrop: Option<Handle<Image>>,
+    /// Blend mode of the layer as its `bm` value
+    #[uniform(5)]
+    pub blend_mode: u32,
+}
+
+impl Material2d for EffectMaterial {
+    fn fragment_shader() -> ShaderRef {
+        shader_path("assets/effect.wgsl")
+    }
+}
+
+pub const MAX_EFFECTS: usize = 4;
+
+#[derive(Clone, Default, ShaderType)]
+pub struct EffectDataUniform {
+    // TODO: change this to a Vec (which compiles to a storage buffer) when bevy supports it
+    // tracking: https://github.com/bevyengine/bevy/issues/5499
+    pub effects: [EffectData; MAX_EFFECTS],
+    pub effect_count: u32,
+}
+
+impl EffectDataUniform {
+    /// Effect parameters at `frame`, with lengths converted to pixels of the
+    /// target which is `pixel_ratio` times the composition size
+    pub fn new(effects: &[LayerEffect], frame: f32, pixel_ratio: f32) -> Self {
+        let mut data: [EffectData; MAX_EFFECTS] = Default::default();
+        for (data, effect) in data.iter_mut().zip(effects) {
+            *data = EffectData::new(effect, frame, pixel_ratio);
+        }
+        EffectDataUniform {
+            effects: data,
+            effect_count: effects.len().min(MAX_EFFECTS) as u32,
+        }
+    }
+}
+
+#[derive(Clone, Default, ShaderType)]
+pub struct EffectData {
+    /// 1 for fill, 2 for tint, 3 for tritone, 4 for drop shadow and 5 for
+    /// gaussian blur
+    pub ty: u32,
+    /// sRGB colors: fill color, tint black and white, tritone highlights,
+    /// midtones and shadows, or shadow color
+    pub colors: [Vec4; 3],
+    /// Fill opacity, tint amount or tritone blend from 0 to 1, blur sigma in
+    /// both directions, or shadow opacity, sigma and whether it is drawn
+    /// without the layer
+    pub values: Vec4,
+    /// Shadow offset in pixels
+    pub offset: Vec2,
+}
+
+impl EffectData {
+    fn new(effect: &LayerEffect, frame: f32, pixel_ratio: f32) -> Self {
+        let color = |color: Rgba| {
+            Vec4::new(
+                color.r as f32 / 255.0,
+                color.g as f32 / 255.0,
+                color.b as f32 / 255.0,
+                color.a as f32 / 255.0,
+            )
+        };
+        let (sigma_x, sigma_y) = effect.blur_sigma(frame);
+        let (offset_x, offset_y) = effect.shadow_offset(frame);
+        let (ty, colors, values) = match effect {
+            LayerEffect::Fill {
+                color: fill,
+                opacity,
+            } => (
+                1,
+                [color(fill.value(frame)), Vec4::ZERO, Vec4::ZERO],
+                Vec4::new(opacity.value(frame).clamp(0.0, 1.0), 0.0, 0.0, 0.0),
+            ),
+            LayerEffect::Tint {
+                black,
+                white,
+                amount,
+            } => (
+                2,
+                [
+                    color(black.value(frame)),
+                    color(white.value(frame)),
+                    Vec4::ZERO,
+                ],
+                Vec4::new((amount.value(frame) / 100.0).clamp(0.0, 1.0), 0.0, 0.0, 0.0),
+            ),
+            LayerEffect::Tritone {
+                highlights,
+                midtones,
+                shadows,
+                blend,
+            } => (
+                3,
+                [
+                    color(highlights.value(frame)),
+                    color(midtones.value(frame)),
+                    color(shadows.value(frame)),
+                ],
+                Vec4::new((blend.value(frame) / 100.0).clamp(0.0, 1.0), 0.0, 0.0, 0.0),
+            ),
+            LayerEffect::DropShadow {
+                color: shadow,
+                opacity,
+                shadow_only,
+                ..
+            } => (
+                4,
+                [color(shadow.value(frame)), Vec4::ZERO, Vec4::ZERO],
+                Vec4::new(
+                    (opacity.value(frame) / 255.0).clamp(0.0, 1.0),
+                    sigma_x * pixel_ratio,
+                    (shadow_only.value(frame) != 0.0) as u32 as f32,
+                    0.0,
+                ),
+            ),
+            LayerEffect::GaussianBlur { .. } => (
+                5,
+                [Vec4::ZERO; 3],
+                Vec4::new(sigma_x * pixel_ratio, sigma_y * pixel_ratio, 0.0, 0.0),
+            ),
+        };
+        EffectData {
+            ty,
+            colors,
+            values,
+            offset: Vec2::new(offset_x, offset_y) * pixel_ratio,
         }
     }
 }
@@ -2,13 +2,23 @@ use bevy::prelude::*;
 use bevy::render::mesh::{Indices, MeshVertexAttribute};
 use bevy::render::render_resource::*;
 use bevy::sprite::*;
+use bevy_tweening::TweenCompleted;
+use lottie_core::prelude::dash_path;
 use lottie_core::tiny_skia_path as ts;
 use lyon::geom::euclid::point2;
 use lyon::lyon_tessellation::*;
 use lyon::path::{Event, Path as LyonPath};
 
-use crate::material::LottieMaterial;
+use crate::material::{EffectMaterial, LottieMaterial};
+use crate::render::RenderLayerAllocator;
 use crate::shape::*;
+use crate::system::component_animator_system;
+#[cfg(not(all(target_os = "unknown", target_arch = "wasm32")))]
+use crate::LottieLoader;
+use crate::{
+    animate_system, effect_animation_system, gradient_animation_system, mask_animation_system,
+    mask_transform_system, release_render_layers_system, spawn_system, LottieAsset,
+};
 
 #[derive(Component, Clone, Copy)]
 pub struct MaskMarker;
@@ -22,6 +32,9 @@ pub struct Vertex {
     /// Use a texture instead of solid color, when this field contains a valid
     /// coord, `color` is ignored
     pub texture_anchor: [f32; 2],
+    /// Index of the material gradient coloring this vertex, or 0 for solid
+    /// colors. Only the alpha of `color` applies to gradients
+    pub gradient: u32,
 }
 
 type IndexType = u32;
@@ -33,6 +46,7 @@ pub type VertexBuffers = lyon::tessellation::VertexBuffers<Vertex, IndexType>;
 pub enum VertexConstructor {
     Solid(Color),
     Texture { anchor: [f32; 2], opacity: f32 },
+    Gradient { index: u32, opacity: f32 },
 }
 
 impl VertexConstructor {
@@ -43,7 +57,10 @@ impl VertexConstructor {
                 c.set_a(c.a() * opacity);
                 VertexConstructor::Solid(c)
             }
-            SolidOrGradient::Gradient(g) => unimplemented!(),
+            SolidOrGradient::Gradient(index) => VertexConstructor::Gradient {
+                index: *index,
+                opacity,
+            },
         }
     }
 }
@@ -56,6 +73,7 @@ impl FillVertexConstructor<Vertex> for VertexConstructor {
                 position: vertex.position().to_array(),
                 color: color.as_linear_rgba_u32(),
                 texture_anchor: [-1.0, -1.0],
+                gradient: 0,
             },
             VertexConstructor::Texture { anchor, opacity } => {
                 let mut color = Color::WHITE;
@@ -64,6 +82,17 @@ impl FillVertexConstructor<Vertex> for VertexConstructor {
                     position: vertex.position().to_array(),
                     color: color.as_linear_rgba_u32(),
                     texture_anchor: *anchor,
+                    gradient: 0,
+                }
+            }
+            VertexConstructor::Gradient { index, opacity } => {
+                let mut color = Color::WHITE;
+                color.set_a(*opacity);
+                Vertex {
+                    position: vertex.position().to_array(),
+                    color: color.as_linear_rgba_u32(),
+                    texture_anchor: [-1.0, -1.0],
+                    gradient: *index,
                 }
             }
         }
@@ -78,6 +107,7 @@ impl StrokeVertexConstructor<Vertex> for VertexConstructor {
                 position: vertex.position().to_array(),
                 color: color.as_linear_rgba_u32(),
                 texture_anchor: [-1.0, -1.0],
+                gradient: 0,
             },
             VertexConstructor::Texture { anchor, opacity } => {
                 let mut color = Color::WHITE;
@@ -86,6 +116,17 @@ impl StrokeVertexConstructor<Vertex> for VertexConstructor {
                     position: vertex.position().to_array(),
                     color: color.as_linear_rgba_u32(),
                     texture_anchor: *anchor,
+                    gradient: 0,
+                }
+            }
+            VertexConstructor::Gradient { index, opacity } => {
+                let mut color = Color::WHITE;
+                color.set_a(*opacity);
+                Vertex {
+                    position: vertex.position().to_array(),
+                    color: color.as_linear_rgba_u32(),
+                    texture_anchor: [-1.0, -1.0],
+                    gradient: *index,
                 }
             }
         }
@@ -103,6 +144,8 @@ pub struct FillTessRes(FillTessellator);
 #[derive(Resource, Deref, DerefMut)]
 pub struct StrokeTessRes(StrokeTessellator);
 
+/// Renders the entities with a [LottieComp](crate::LottieComp), usable in any
+/// Bevy app
 pub struct LottiePlugin;
 
 impl Plugin for LottiePlugin {
@@ -112,12 +155,31 @@ impl Plugin for LottiePlugin {
         app.insert_resource(FillTessRes(fill_tess))
             .insert_resource(StrokeTessRes(stroke_tess))
             .add_plugins(Material2dPlugin::<LottieMaterial>::default())
+            .add_plugins(Material2dPlugin::<EffectMaterial>::default())
+            .add_asset::<LottieAsset>()
+            .init_resource::<RenderLayerAllocator>()
+            .add_event::<TweenCompleted>()
+            .add_systems(Update, component_animator_system::<Transform>)
+            .add_systems(Update, component_animator_system::<Path>)
+            .add_systems(Update, component_animator_system::<DrawMode>)
+            .add_systems(Update, spawn_system)
+            .add_systems(Update, animate_system)
+            .add_systems(Update, gradient_animation_system.after(animate_system))
+            .add_systems(Update, effect_animation_system.after(animate_system))
+            .add_systems(Update, mask_animation_system.after(animate_system))
+            .add_systems(Update, release_render_layers_system)
             .add_systems(
                 PostUpdate,
                 mesh_shapes_system
                     .in_set(BuildShapes)
                     .after(bevy::transform::TransformSystem::TransformPropagate),
+            )
+            .add_systems(
+                PostUpdate,
+                mask_transform_system.after(bevy::transform::TransformSystem::TransformPropagate),
             );
+        #[cfg(not(all(target_os = "unknown", target_arch = "wasm32")))]
+        app.init_asset_loader::<LottieLoader>();
     }
 }
 
@@ -164,6 +226,13 @@ fn stroke(
     mode: &Stroke,
     buffers: &mut VertexBuffers,
 ) {
+    let dashed;
+    let path = if mode.dashes.is_empty() {
+        path
+    } else {
+        dashed = dash_path(path.clone(), &mode.dashes, mode.dash_frame);
+        &dashed
+    };
     let path = stroke_path(path, &mode.options);
     let mut opts = FillOptions::default();
     opts.fill_rule = FillRule::NonZero;
@@ -202,7 +271,15 @@ fn build_mesh(buffers: &VertexBuffers) -> Mesh {
             .map(|v| v.color)
             .collect::<Vec<u32>>(),
     );
-    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; len]);
+    // the gradient index is passed to the shader as UV
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_UV_0,
+        buffers
+            .vertices
+            .iter()
+            .map(|v| [v.gradient as f32, 0.0])
+            .collect::<Vec<[f32; 2]>>(),
+    );
     mesh
 }
 
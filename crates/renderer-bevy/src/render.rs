@@ -4,18 +4,72 @@ use bevy::ecs::system::EntityCommands;
 use bevy::prelude::{Entity, Image, Transform};
 use bevy::render::texture::{CompressedImageFormats, ImageType, TextureError};
 use bevy::render::view::RenderLayers;
-use bevy_tweening::{Animator, EaseMethod, Sequence, Tracks, Tween};
+use bevy::sprite::MaterialMesh2dBundle;
+use bevy_tweening::{Animator, EaseMethod, Lens, Sequence, Tracks, Tween};
 use lottie_core::prelude::{Transform as LottieTransform, *};
 
-use crate::lens::{OpacityLens, PathFactoryLens, PathLens, StrokeWidthLens, TransformLens};
+use crate::lens::{
+    DashLens, OpacityLens, PathFactoryLens, StrokeColorLens, StrokeWidthLens, TransformLens,
+};
 use crate::material::*;
 use crate::plugin::MaskMarker;
 use crate::shape::ShapeBundle;
-use crate::tween::TweenProducer;
 use crate::*;
 
+/// First render layer of offscreen passes, the main one being 0
+const FIRST_OFFSCREEN_RENDER_LAYER: u8 = 1;
+
+/// Order of the cameras drawing layers offscreen, times the number of
+/// composited layers they are nested in plus one. They run before the cameras
+/// drawing backdrops at the same depth, which are ordered from there downwards
+/// so that the backdrops of lower layers are ready first
+pub(crate) const OFFSCREEN_CAMERA_ORDER: isize = -64;
+
+/// Order of the cameras drawing masks, which every other pass samples
+pub(crate) const MASK_CAMERA_ORDER: isize = isize::MIN;
+
+/// Render layers of the offscreen passes drawing masks, layers with effects or
+/// blend modes and backdrops, which cannot be shared between Lottie instances.
+/// Each instance takes one for its masks, one per layer drawn offscreen and one
+/// per backdrop
+#[derive(Resource, Default)]
+pub struct RenderLayerAllocator {
+    used: HashMap<u8, Entity>,
+}
+
+impl RenderLayerAllocator {
+    pub fn allocate(&mut self, instance: Entity) -> Option<u8> {
+        let layer = (FIRST_OFFSCREEN_RENDER_LAYER..RenderLayers::TOTAL_LAYERS as u8)
+            .find(|layer| !self.used.contains_key(layer))?;
+        self.used.insert(layer, instance);
+        Some(layer)
+    }
+
+    /// Free the render layers of a despawned instance
+    pub fn release(&mut self, instance: Entity) {
+        self.used.retain(|_, owner| *owner != instance);
+    }
+}
+
+/// Render layer of the backdrop a layer with a blend mode other than normal is
+/// blended with. Every layer below it is also drawn on that render layer
+#[derive(Clone, Copy)]
+pub struct Backdrop {
+    pub zindex: f32,
+    pub render_layer: u8,
+    /// Composited layer the blended layer is part of the content of
+    pub compositing: Option<Id>,
+}
+
+/// Whether `layer` is blended with what is drawn below it through a backdrop.
+/// Precomposition and media layers are blended once their content is drawn
+pub(crate) fn is_blended(layer: &StagedLayer) -> bool {
+    !layer.is_mask && layer.blend_mode != BlendMode::Normal
+}
+
 pub struct BevyStagedLayer<'a> {
     pub layer: &'a StagedLayer,
+    pub lottie: &'a Lottie,
     pub meshes: &'a mut Assets<Mesh>,
     pub image_assets: &'a mut Assets<Image>,
     pub audio_assets: &'a mut Assets<AudioSource>,
@@ -26,7 +80,23 @@ pub struct BevyStagedLayer<'a> {
     pub mask_index: &'a mut u32,
     pub mask_count: u32,
     pub mask_registry: &'a mut HashMap<Id, u32>,
+    /// Render layers the masks of the instance are drawn on
+    pub mask_layers: RenderLayers,
+    /// From world space to the pixels of the mask texture
+    pub mask_transform: Mat4,
     pub zindex_window: f32,
+    pub effect_material_assets: &'a mut Assets<EffectMaterial>,
+    /// Render layers of the layers drawn offscreen
+    pub offscreen_registry: &'a mut HashMap<Id, u8>,
+    /// Backdrops of the blended layers of the instance
+    pub backdrops: &'a [Backdrop],
+    pub render_layers: &'a mut RenderLayerAllocator,
+    /// Entity of the Lottie instance the layer belongs to
+    pub instance: Entity,
+    /// Entity the effect composites are attached to
+    pub root: Entity,
+    /// Size of the rendered frame in pixels
+    pub target_size: UVec2,
 }
 
 impl<'a> BevyStagedLayer<'a> {
@@ -38,15 +108,18 @@ impl<'a> BevyStagedLayer<'a> {
             .map(|s| s.as_str())
             .unwrap_or("Layer")
             .to_string();
-        let mut c = commands.spawn(Name::new(name));
         let mut initial_transform = Transform::from_matrix(self.layer.transform.value(0.0));
         initial_transform.translation.z = self.layer.zindex as f32 * -1.0;
         if self.layer.is_mask {
             initial_transform.translation.x += (*self.mask_index as f32) * self.model_size.x
                 / self.layer.transform_hierarchy.scale_x(0.0);
             self.mask_registry.insert(self.layer.id, *self.mask_index);
+        } else if !self.layer.effects.is_empty() || is_blended(self.layer) {
+            // The content of composited layers is drawn into their target
+            self.spawn_offscreen(commands);
         }
 
+        let mut c = commands.spawn(Name::new(name));
         log::trace!(
             "spawn layer {:?}: start {}, end {}, transform: {:?}",
             c.id(),
@@ -56,7 +129,7 @@ impl<'a> BevyStagedLayer<'a> {
         );
         match &self.layer.content {
             RenderableContent::Shape(shapes) => {
-                self.spawn_shapes(&shapes, self.zindex_window, &mut c);
+                self.spawn_shapes(&shapes, &[], self.zindex_window, &mut c);
             }
             RenderableContent::Media(media) => {
                 let mime = infer::get(&media.content).unwrap();
@@ -91,6 +164,7 @@ impl<'a> BevyStagedLayer<'a> {
                     };
                     bundle.sprite.flip_x = true;
                     c.insert(bundle);
+                    c.insert(self.content_layers());
                 } else if mime.mime_type().starts_with("audio") {
                     let source = AudioSource {
                         bytes: media.content.as_slice().into(),
@@ -126,13 +200,197 @@ impl<'a> BevyStagedLayer<'a> {
         }
 
         let id = c.id();
-        c.insert(FrameTracker(self.layer.frame_transform_hierarchy.clone()));
+        c.insert(self.frame_tracker());
         c.insert(VisibilityBundle::default());
         Ok(id)
     }
 
-    fn spawn_shapes(&mut self, group: &ShapeGroup, zindex_window: f32, c: &mut EntityCommands) {
-        let shapes = group.styled_shapes();
+    /// Draw the layer into a target of its own through a dedicated camera, and
+    /// composite that target with a quad applying the layer effects, then
+    /// blending it with its backdrop
+    fn spawn_offscreen(&mut self, commands: &mut Commands) {
+        let render_layer = match self.render_layers.allocate(self.instance) {
+            Some(render_layer) => render_layer,
+            None => {
+                log::warn!(
+                    "effects and blend modes are only supported on {} layers at once",
+                    RenderLayers::TOTAL_LAYERS - FIRST_OFFSCREEN_RENDER_LAYER as usize
+                );
+                return;
+            }
+        };
+        if self.layer.effects.len() > MAX_EFFECTS {
+            log::warn!("only the first {} effects are supported", MAX_EFFECTS);
+        }
+        let depth = self.depth();
+        let texture =
+            self.spawn_target(render_layer, OFFSCREEN_CAMERA_ORDER * (depth + 1), commands);
+        let compositing = self.compositing_layer().map(|layer| layer.id);
+        let zindex = self.layer.zindex;
+        let backdrops = self.backdrops;
+        let backdrops = backdrops
+            .iter()
+            .filter(|backdrop| backdrop.compositing == compositing);
+        let backdrop = match backdrops.clone().find(|backdrop| backdrop.zindex == zindex) {
+            Some(backdrop) => {
+                // backdrops of lower layers are drawn first
+                let above = backdrops.filter(|other| other.zindex < zindex).count();
+                let order = OFFSCREEN_CAMERA_ORDER * depth - 1 - above as isize;
+                Some(self.spawn_target(backdrop.render_layer, order, commands))
+            }
+            None => None,
+        };
+        let pixel_ratio = self.target_size.x as f32 / self.model_size.x;
+        let material = self.effect_material_assets.add(EffectMaterial {
+            texture,
+            effects: EffectDataUniform::new(&self.layer.effects, 0.0, pixel_ratio),
+            blend_mode: match backdrop {
+                Some(_) => self.layer.blend_mode as u32,
+                None => BlendMode::Normal as u32,
+            },
+            backdrop,
+        });
+        let mesh = self
+            .meshes
+            .add(Mesh::from(bevy::prelude::shape::Quad::new(self.model_size)));
+        let mut c = commands.spawn(Name::new("Composite"));
+        c.insert(MaterialMesh2dBundle {
+            mesh: mesh.into(),
+            material,
+            transform: Transform::from_translation(
+                (self.model_size / 2.0).extend(-self.global_zindex()),
+            ),
+            ..default()
+        });
+        c.insert(self.main_layers());
+        c.insert(self.frame_tracker());
+        if self.layer.effects.iter().any(|effect| effect.is_animated()) {
+            c.insert(EffectAnimator {
+                effects: self.layer.effects.clone(),
+                pixel_ratio,
+            });
+        }
+        let id = c.id();
+        commands.entity(self.root).add_child(id);
+        self.offscreen_registry.insert(self.layer.id, render_layer);
+    }
+
+    /// Texture the composition is drawn into by a camera looking at
+    /// `render_layer`, at `order` among the other cameras
+    fn spawn_target(
+        &mut self,
+        render_layer: u8,
+        order: isize,
+        commands: &mut Commands,
+    ) -> Handle<Image> {
+        let size = Extent3d {
+            width: self.target_size.x,
+            height: self.target_size.y,
+            depth_or_array_layers: 1,
+        };
+        let mut image = Image {
+            texture_descriptor: TextureDescriptor {
+                label: Some("offscreen_texture"),
+                size,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::bevy_default(),
+                mip_level_count: 1,
+                sample_count: 1,
+                usage: TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_DST
+                    | TextureUsages::COPY_SRC
+                    | TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+            ..default()
+        };
+        image.resize(size);
+        let texture = self.image_assets.add(image);
+        let pixel_ratio = self.target_size.x as f32 / self.model_size.x;
+        // Looking at the composition from its center, so that the target is
+        // not affected by the transform of the instance
+        let camera = Camera2dBundle {
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::Custom(Color::NONE),
+            },
+            camera: Camera {
+                target: RenderTarget::Image(texture.clone()),
+                order,
+                ..default()
+            },
+            transform: Transform::from_translation((self.model_size / 2.0).extend(0.0))
+                .with_scale(Vec3::new(1.0 / pixel_ratio, -1.0 / pixel_ratio, 1.0)),
+            ..default()
+        };
+        let camera = commands
+            .spawn(camera)
+            .insert(RenderLayers::layer(render_layer))
+            .id();
+        commands.entity(self.root).add_child(camera);
+        texture
+    }
+
+    /// Render layers drawing the layer into the frame, or into the target of
+    /// the composited layer it is part of: that one, and the backdrops of the
+    /// blended layers above it
+    fn main_layers(&self) -> RenderLayers {
+        let compositing = self.compositing_layer().map(|layer| layer.id);
+        let layers = match compositing.and_then(|id| self.offscreen_registry.get(&id)) {
+            Some(render_layer) => RenderLayers::layer(*render_layer),
+            None => RenderLayers::layer(0),
+        };
+        self.backdrops
+            .iter()
+            .filter(|backdrop| backdrop.compositing == compositing)
+            .filter(|backdrop| backdrop.zindex < self.layer.zindex)
+            .fold(layers, |layers, backdrop| {
+                layers.with(backdrop.render_layer)
+            })
+    }
+
+    /// Composited precomposition or media layer the layer is part of the
+    /// content of
+    fn compositing_layer(&self) -> Option<&'a StagedLayer> {
+        self.lottie.timeline().compositing_layer(self.layer)
+    }
+
+    /// Number of composited layers the layer is nested in
+    fn depth(&self) -> isize {
+        let timeline = self.lottie.timeline();
+        std::iter::successors(self.compositing_layer(), |layer| {
+            timeline.compositing_layer(layer)
+        })
+        .count() as isize
+    }
+
+    /// Zindex of the layer in the composition, which is the sum of all
+    /// zindexes in its parenting chain
+    fn global_zindex(&self) -> f32 {
+        let timeline = self.lottie.timeline();
+        std::iter::successors(Some(self.layer), |layer| {
+            layer.parent.and_then(|id| timeline.item(id))
+        })
+        .map(|layer| layer.zindex)
+        .sum()
+    }
+
+    /// Render layers the content of the layer is drawn on, which is its own
+    /// when it is drawn offscreen
+    fn content_layers(&self) -> RenderLayers {
+        match self.offscreen_registry.get(&self.layer.id) {
+            Some(render_layer) => RenderLayers::layer(*render_layer),
+            None => self.main_layers(),
+        }
+    }
+
+    fn spawn_shapes(
+        &mut self,
+        group: &ShapeGroup,
+        operations: &[PathOperation],
+        zindex_window: f32,
+        c: &mut EntityCommands,
+    ) {
+        let shapes = group.styled_shapes().with_operations(operations);
         let count = shapes.shape_count() as f32 + 1.0;
         // root layers have a window of exactly 1.0
         let step = zindex_window / count;
@@ -154,19 +412,9 @@ impl<'a> BevyStagedLayer<'a> {
                     {
                         group.insert(animator);
                     }
-                    let mut new_group = ShapeGroup { shapes };
-                    // if current group has a trim, add this trim to shapes list, so it will be
-                    // applied correctly
-                    if !shape.trims.is_empty() {
-                        for trim in &shape.trims {
-                            new_group.shapes.push(ShapeLayer {
-                                name: None,
-                                hidden: false,
-                                shape: Shape::Trim(trim.trim.clone()),
-                            })
-                        }
-                    }
-                    self.spawn_shapes(&new_group, step, &mut group);
+                    let new_group = ShapeGroup { shapes };
+                    // trims and modifiers of the current group also apply to its children
+                    self.spawn_shapes(&new_group, &shape.operations, step, &mut group);
                     Some(group.id())
                 }
                 _ => self.spawn_shape(zindex, shape, c.commands()),
@@ -189,7 +437,7 @@ impl<'a> BevyStagedLayer<'a> {
         }
         let mut draw_mode = utils::shape_draw_mode(&shape);
         let opacity = OpacityHierarchy::from(&self.layer.transform_hierarchy);
-        let global_opacity = opacity.initial_value();
+        let global_opacity = opacity.initial_value() * shape.transform.opacity_value(0.0) / 100.0;
         if global_opacity < 1.0 {
             if let Some(fill) = draw_mode.fill.as_mut() {
                 fill.opacity *= global_opacity;
@@ -202,13 +450,8 @@ impl<'a> BevyStagedLayer<'a> {
         let mut material = LottieMaterial {
             size: Vec4::new(self.model_size.x, self.model_size.y, self.scale, 0.0),
             mask_info: MaskDataUniform {
-                masks: [
-                    UVec4::default(),
-                    UVec4::default(),
-                    UVec4::default(),
-                    UVec4::default(),
-                ],
-                mask_count: self.layer.mask_hierarchy.len() as u32,
+                masks: [UVec4::default(); MAX_MASKS],
+                mask_count: self.layer.mask_hierarchy.len().min(MAX_MASKS) as u32,
                 mask_total_count: self.mask_count,
             },
             mask: if !self.layer.is_mask {
@@ -217,13 +460,35 @@ impl<'a> BevyStagedLayer<'a> {
                 None
             },
             gradient: GradientDataUniform::default(),
+            stroke_gradient: GradientDataUniform::default(),
+            mask_transform: self.mask_transform,
         };
 
+        let mut mask_animator = MaskAnimator { feathers: vec![] };
         if !self.layer.is_mask {
-            for (index, item) in self.layer.mask_hierarchy.masks().iter().enumerate() {
+            if self.layer.mask_hierarchy.len() > MAX_MASKS {
+                log::warn!(
+                    "only the first {} masks of a layer are supported",
+                    MAX_MASKS
+                );
+            }
+            let masks = self.layer.mask_hierarchy.masks().iter().take(MAX_MASKS);
+            for (index, item) in masks.enumerate() {
                 let mask_index = *self.mask_registry.get(&item.id).unwrap();
                 let mode = item.mode as u32;
-                material.mask_info.masks[index] = UVec4::new(mask_index, mode, 0, 0);
+                // Track mattes have no composition and are encoded as 0
+                let (composition, feather) = item
+                    .composition
+                    .as_ref()
+                    .map(|c| (c.mode as u32 | (c.first as u32) << 3, c.feather_step(0.0)))
+                    .unwrap_or_default();
+                material.mask_info.masks[index] =
+                    UVec4::new(mask_index, mode, composition, feather.to_bits());
+                if let Some(c) = item.composition.as_ref() {
+                    if c.feather.as_ref().is_some_and(|f| f.is_animated()) {
+                        mask_animator.feathers.push((index, c.clone()));
+                    }
+                }
             }
         }
 
@@ -241,13 +506,15 @@ impl<'a> BevyStagedLayer<'a> {
         let mut c = commands.spawn(Name::new(name));
 
         if self.layer.is_mask {
-            c.insert(MaskMarker).insert(RenderLayers::from_layers(&[1]));
+            c.insert(MaskMarker).insert(self.mask_layers);
+        } else {
+            c.insert(self.content_layers());
         }
 
         let mut initial_pos = Vector2D::new(0.0, 0.0);
         match &shape.shape.shape {
             Shape::Ellipse(ellipse) => {
-                let path = ellipse.path(0.0);
+                let path = apply_path_operations(ellipse.path(0.0), &shape.operations, 0.0);
                 c.insert(ShapeBundle::new(path, draw_mode, transform));
 
                 if let Some(animator) = self.transform_animator(&shape.transform, zindex, None) {
@@ -256,13 +523,13 @@ impl<'a> BevyStagedLayer<'a> {
                 if let Some(animator) = self.draw_mode_animator(&shape) {
                     c.insert(animator);
                 }
-                if let Some(animator) = self.path_animator(ellipse.clone()) {
+                if let Some(animator) = self.path_animator(ellipse.clone(), &shape.operations) {
                     c.insert(animator);
                 }
             }
             Shape::PolyStar(star) => {
                 initial_pos = star.position.initial_value();
-                let path = star.path(0.0);
+                let path = apply_path_operations(star.path(0.0), &shape.operations, 0.0);
                 c.insert(ShapeBundle::new(path, draw_mode, transform));
                 if let Some(animator) = self.transform_animator(&shape.transform, zindex, None) {
                     c.insert(animator);
@@ -270,10 +537,13 @@ impl<'a> BevyStagedLayer<'a> {
                 if let Some(animator) = self.draw_mode_animator(&shape) {
                     c.insert(animator);
                 }
+                if let Some(animator) = self.path_animator(star.clone(), &shape.operations) {
+                    c.insert(animator);
+                }
             }
             Shape::Rectangle(rect) => {
                 initial_pos = rect.position.initial_value();
-                let path = rect.path(0.0);
+                let path = apply_path_operations(rect.path(0.0), &shape.operations, 0.0);
                 c.insert(ShapeBundle::new(path, draw_mode, transform));
                 if let Some(animator) = self.transform_animator(&shape.transform, zindex, None) {
                     c.insert(animator);
@@ -281,10 +551,13 @@ impl<'a> BevyStagedLayer<'a> {
                 if let Some(animator) = self.draw_mode_animator(&shape) {
                     c.insert(animator);
                 }
+                if let Some(animator) = self.path_animator(rect.clone(), &shape.operations) {
+                    c.insert(animator);
+                }
             }
             Shape::Path { d, text_range } => {
                 let beziers = d.initial_value();
-                let path = beziers.path(0.0);
+                let path = apply_path_operations(beziers.path(0.0), &shape.operations, 0.0);
                 c.insert(ShapeBundle::new(path, draw_mode, transform));
 
                 if let Some(animator) =
@@ -296,43 +569,53 @@ impl<'a> BevyStagedLayer<'a> {
                     c.insert(animator);
                 }
 
-                // Add bezier tween
-                if d.is_animated() || !shape.trims.is_empty() {
-                    let tween = d.keyframes.tween(
-                        self.layer.end_frame,
-                        self.layer.frame_rate,
-                        |start, end, start_frame, end_frame| PathLens {
-                            start,
-                            end,
-                            start_frame,
-                            end_frame,
-                            trims: shape.trims.clone(),
-                        },
-                    );
-                    let animator = Animator::new(tween).with_state(AnimatorState::Paused);
+                if let Some(animator) =
+                    self.path_animator(shape.shape.shape.clone(), &shape.operations)
+                {
                     c.insert(animator);
                 }
             }
-            Shape::Group { .. } => {
-                unreachable!()
-            }
-            _ => {
-                println!("{:?}", shape.shape.shape);
-                todo!()
+            Shape::Merge { .. } => {
+                let merge = shape.shape.shape.clone();
+                let path = apply_path_operations(merge.path(0.0), &shape.operations, 0.0);
+                c.insert(ShapeBundle::new(path, draw_mode, transform));
+                if let Some(animator) = self.transform_animator(&shape.transform, zindex, None) {
+                    c.insert(animator);
+                }
+                if let Some(animator) = self.draw_mode_animator(&shape) {
+                    c.insert(animator);
+                }
+                if let Some(animator) = self.path_animator(merge, &shape.operations) {
+                    c.insert(animator);
+                }
             }
+            // Styled shapes only hold drawable shapes and groups, groups being
+            // spawned by `spawn_shapes`
+            _ => unreachable!("{:?} is not a drawable shape", shape.shape.shape),
         }
 
         // register gradient texture if any
+        let mut animator = GradientAnimator {
+            fill: None,
+            stroke: None,
+        };
         if let AnyFill::Gradient(g) = &shape.fill {
-            let start = g.gradient.start.initial_value();
-            let end = g.gradient.end.initial_value();
-            let stops = g.gradient.colors.colors.initial_value();
-            assert!(stops.len() >= 2, "gradient stops must be at least 2");
-            let stops = stops.iter().map(GradientDataStop::from).collect::<Vec<_>>();
-            material.gradient.stops = [stops[0].clone(), stops[1].clone()];
-            material.gradient.start = Vec2::new(start.x, start.y);
-            material.gradient.end = Vec2::new(end.x, end.y);
-            material.gradient.use_gradient = 1;
+            material.gradient = GradientDataUniform::new(&g.gradient, 0.0);
+            if g.gradient.is_animated() {
+                animator.fill = Some(g.gradient.clone());
+            }
+        }
+        if let Some(AnyStroke::Gradient(g)) = &shape.stroke {
+            material.stroke_gradient = GradientDataUniform::new(&g.gradient, 0.0);
+            if g.gradient.is_animated() {
+                animator.stroke = Some(g.gradient.clone());
+            }
+        }
+        if animator.fill.is_some() || animator.stroke.is_some() {
+            c.insert(animator);
+        }
+        if !mask_animator.feathers.is_empty() {
+            c.insert(mask_animator);
         }
         // let stroke_index = if let AnyFill::Gradient(g) = &shape.fill {
         //     self.gradient
@@ -344,10 +627,17 @@ impl<'a> BevyStagedLayer<'a> {
 
         let handle = self.material_assets.add(material);
         c.insert(handle);
-        c.insert(FrameTracker(self.layer.frame_transform_hierarchy.clone()));
+        c.insert(self.frame_tracker());
         Some(c.id())
     }
 
+    fn frame_tracker(&self) -> FrameTracker {
+        FrameTracker {
+            hierarchy: self.layer.frame_transform_hierarchy.clone(),
+            instance: self.instance,
+        }
+    }
+
     fn transform_animator(
         &self,
         transform: &LottieTransform,
@@ -362,10 +652,9 @@ impl<'a> BevyStagedLayer<'a> {
             Vec2::ZERO
         };
         if transform.is_animated() || text_range.is_some() {
-            let mut frames = transform.frames();
-            if text_range.is_some() {
-                frames = frames.max(self.layer.end_frame);
-            }
+            // Bound expressions and text ranges change the transform past its
+            // last keyframe
+            let frames = transform.frames().max(self.layer.end_frame);
             let secs = frames as f32 / frame_rate as f32;
             let transform = TransformLens {
                 data: transform.clone(),
@@ -392,33 +681,50 @@ impl<'a> BevyStagedLayer<'a> {
 
     fn draw_mode_animator(&self, shape: &StyledShape) -> Option<Animator<DrawMode>> {
         let mut tweens = vec![];
-        let frame_rate = self.layer.frame_rate;
         if let Some(stroke) = shape.stroke.as_ref() {
             if stroke.width().is_animated() {
-                tweens.push(stroke.width().keyframes.tween(
-                    self.layer.end_frame,
-                    frame_rate,
-                    |start, end, _, _| StrokeWidthLens { start, end },
-                ));
+                tweens.push(self.layer_tween(StrokeWidthLens {
+                    width: stroke.width().clone(),
+                    frames: self.layer.end_frame,
+                }));
+            }
+            if let AnyStroke::Solid(stroke) = stroke {
+                if stroke.color.is_animated() {
+                    tweens.push(self.layer_tween(StrokeColorLens {
+                        color: stroke.color.clone(),
+                        frames: self.layer.end_frame,
+                    }));
+                }
             }
         }
 
+        let dashes_animated = shape
+            .stroke
+            .as_ref()
+            .is_some_and(|stroke| stroke.dashes().iter().any(|dash| dash.length.is_animated()));
+        if dashes_animated {
+            tweens.push(self.layer_tween(DashLens {
+                frames: self.layer.end_frame,
+            }));
+        }
+
         let opacity = OpacityHierarchy::from(&self.layer.transform_hierarchy);
-        if opacity.is_animated() {
-            let opacity_lens = OpacityLens {
+        let stroke_opacity_animated = shape
+            .stroke
+            .as_ref()
+            .is_some_and(|stroke| stroke.opacity().is_animated());
+        if opacity.is_animated()
+            || shape.transform.is_opacity_animated()
+            || shape.fill.opacity().is_animated()
+            || stroke_opacity_animated
+        {
+            tweens.push(self.layer_tween(OpacityLens {
                 opacity,
+                transform: shape.transform.clone(),
                 frames: self.layer.end_frame,
                 fill_opacity: shape.fill.opacity().clone(),
                 stroke_opacity: shape.stroke.as_ref().map(|s| s.opacity().clone()),
-            };
-            let secs =
-                (opacity_lens.frames as f32 / self.layer.frame_rate as f32).max(f32::EPSILON);
-            let tween = Tween::new(
-                EaseMethod::Linear,
-                Duration::from_secs_f32(secs),
-                opacity_lens,
-            );
-            tweens.push(Sequence::from_single(tween));
+            }));
         }
 
         if !tweens.is_empty() {
@@ -432,26 +738,63 @@ impl<'a> BevyStagedLayer<'a> {
     fn path_animator(
         &self,
         factory: impl PathFactory + Send + Sync + 'static,
+        operations: &[PathOperation],
     ) -> Option<Animator<Path>> {
-        if !factory.is_animated() {
+        // trims and modifiers may be animated on their own
+        if !factory.is_animated() && operations.is_empty() {
             return None;
         }
-        let frames = self.layer.end_frame - self.layer.start_frame;
-        let secs = frames / self.layer.frame_rate;
-        Some(Animator::new(Tween::new(
+        Some(Animator::new(self.layer_tween(PathFactoryLens {
+            frames: self.layer.end_frame,
+            factory: Box::new(factory),
+            operations: operations.to_vec(),
+        })))
+    }
+
+    /// Tween of `lens` over the frames of the layer, for lenses evaluating
+    /// properties at `frames * ratio` themselves
+    fn layer_tween<T: 'static>(&self, lens: impl Lens<T> + Send + Sync + 'static) -> Sequence<T> {
+        let secs = (self.layer.end_frame / self.layer.frame_rate).max(f32::EPSILON);
+        Sequence::from_single(Tween::new(
             EaseMethod::Linear,
             Duration::from_secs_f32(secs),
-            PathFactoryLens {
-                start_frame: self.layer.start_frame,
-                end_frame: self.layer.end_frame,
-                factory: Box::new(factory),
-            },
-        )))
+            lens,
+        ))
     }
 }
 
 #[derive(Component, Deref)]
-pub struct FrameTracker(FrameTransformHierarchy);
+pub struct FrameTracker {
+    #[deref]
+    hierarchy: FrameTransformHierarchy,
+    /// The Lottie instance whose playback drives the entity
+    pub instance: Entity,
+}
+
+/// Animated gradients of a shape, which are written to its material every
+/// frame by `gradient_animation_system`
+#[derive(Component)]
+pub struct GradientAnimator {
+    pub fill: Option<Gradient>,
+    pub stroke: Option<Gradient>,
+}
+
+/// Animated mask feathers of a shape, along with the index of their mask in
+/// the material, which are written to it every frame by
+/// `mask_animation_system`
+#[derive(Component)]
+pub struct MaskAnimator {
+    pub feathers: Vec<(usize, MaskComposition)>,
+}
+
+/// Animated effects of a layer, which are written to the material of its
+/// effect composite every frame by `effect_animation_system`
+#[derive(Component)]
+pub struct EffectAnimator {
+    pub effects: Vec<LayerEffect>,
+    /// Size of a composition unit in pixels of the effect target
+    pub pixel_ratio: f32,
+}
 
 #[derive(Component)]
 pub struct LottieAudio;
@@ -1,8 +1,8 @@
-use bevy::math::Vec2;
 use bevy::prelude::{
     Bundle, Color, Component, ComputedVisibility, Deref, GlobalTransform, Transform, Visibility,
 };
 use bevy::sprite::Mesh2dHandle;
+use lottie_core::prelude::StrokeDash;
 use lyon::path::Path as LyonPath;
 use lyon::tessellation::{FillOptions, StrokeOptions};
 
@@ -31,19 +31,22 @@ pub struct Stroke {
     pub color: SolidOrGradient,
     pub options: StrokeOptions,
     pub opacity: f32,
+    /// Dash pattern the path is split into before stroking
+    pub dashes: Vec<StrokeDash>,
+    /// Frame animated dash lengths are evaluated at
+    pub dash_frame: f32,
 }
 
+/// Index of the fill gradient of a [`LottieMaterial`](crate::material::LottieMaterial)
+pub const FILL_GRADIENT: u32 = 1;
+/// Index of the stroke gradient of a [`LottieMaterial`](crate::material::LottieMaterial)
+pub const STROKE_GRADIENT: u32 = 2;
+
 #[derive(Clone)]
 pub enum SolidOrGradient {
     Solid(Color),
-    Gradient(Gradient),
-}
-
-#[derive(Clone)]
-pub struct Gradient {
-    start: Vec2,
-    end: Vec2,
-    ty: (),
+    /// Colored by the material gradient with the given index
+    Gradient(u32),
 }
 
 #[derive(Bundle)]
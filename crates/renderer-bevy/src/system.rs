@@ -6,15 +6,25 @@ use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 use bevy_tweening::{Animator, AnimatorState, Targetable, TweenCompleted};
 
-use crate::LottieAnimationInfo;
+use crate::{LottieAnimationInfo, PrimaryLottie};
 
+/// Show playback controls for the instance marked with [PrimaryLottie]
 #[cfg(feature = "bevy_egui")]
-pub(crate) fn controls_system(mut egui_ctx: EguiContexts, mut info: ResMut<LottieAnimationInfo>) {
+pub fn controls_system(
+    mut egui_ctx: EguiContexts,
+    mut instances: Query<&mut LottieAnimationInfo, With<PrimaryLottie>>,
+) {
+    let mut info = match instances.get_single_mut() {
+        Ok(info) => info,
+        Err(_) => return,
+    };
     let value = info.progress();
     let progress = egui::ProgressBar::new(value);
     let button_text = if info.paused() { "▶" } else { "⏸" };
     let button = egui::Button::new(button_text);
     let secs = egui::Label::new(format!("{:.2}", info.frame_rate * info.current_time));
+    let mut segments = info.segments().map(String::from).collect::<Vec<_>>();
+    segments.sort();
     egui::TopBottomPanel::bottom("slider_panel").show(egui_ctx.ctx_mut(), |ui| {
         ui.horizontal(|ui| {
             ui.add(secs);
@@ -22,6 +32,44 @@ pub(crate) fn controls_system(mut egui_ctx: EguiContexts, mut info: ResMut<Lotti
                 let paused = !info.paused();
                 info.pause(paused);
             }
+            let mut speed = info.speed();
+            let speed_value = egui::DragValue::new(&mut speed)
+                .speed(0.05)
+                .clamp_range(0.0..=8.0)
+                .suffix("x");
+            if ui.add(speed_value).changed() {
+                info.set_speed(speed);
+            }
+            let mut reversed = info.reversed();
+            if ui.checkbox(&mut reversed, "Reverse").changed() {
+                info.set_reversed(reversed);
+            }
+            let mut ping_pong = info.ping_pong();
+            if ui.checkbox(&mut ping_pong, "Ping-pong").changed() {
+                info.set_ping_pong(ping_pong);
+            }
+            // 0 loops forever
+            let mut loops = info.loop_count().unwrap_or(0);
+            let loops_value = egui::DragValue::new(&mut loops).prefix("loops ");
+            if ui.add(loops_value).changed() {
+                info.set_loop_count((loops > 0).then_some(loops));
+            }
+            let frames = info.frame_range();
+            let (mut start, mut end) = (frames.start, frames.end);
+            let start_changed = ui
+                .add(egui::DragValue::new(&mut start).prefix("from "))
+                .changed();
+            let end_changed = ui
+                .add(egui::DragValue::new(&mut end).prefix("to "))
+                .changed();
+            if start_changed || end_changed {
+                info.set_frame_range(Some(start..end));
+            }
+            for name in &segments {
+                if ui.button(name).clicked() {
+                    info.play_segment(name);
+                }
+            }
             ui.add_sized(ui.available_size(), progress);
         });
     });
@@ -39,8 +87,7 @@ pub fn component_animator_system<T: Component>(
     let mut events: Mut<Events<TweenCompleted>> = events.into();
     for (entity, target, mut animator) in query.iter_mut() {
         if animator.state != AnimatorState::Paused {
-            // TODO: maybe support animation speed, maybe not
-            // let speed = animator.speed();
+            // Playback speed is applied to the time `animate_system` seeks to
             let mut target = ComponentTarget::new(target);
             animator
                 .tweenable_mut()
@@ -1,36 +1,39 @@
 use bevy::prelude::Color;
 use lottie_core::prelude::{
     AnyFill, AnyStroke, FillRule as LottieFillRule, LineCap as LottieLineCap,
-    LineJoin as LottieLineJoin, Rgb, StyledShape,
+    LineJoin as LottieLineJoin, StyledShape,
 };
 use lyon::path::FillRule;
 use lyon::tessellation::{FillOptions, LineCap, LineJoin, StrokeOptions};
 
-use crate::shape::{DrawMode, Fill, SolidOrGradient, Stroke};
+use crate::shape::{DrawMode, Fill, SolidOrGradient, Stroke, FILL_GRADIENT, STROKE_GRADIENT};
 
-/// Get an initial draw mode for a shape. If fill/stroke uses gradient fill, a
-/// default white color is used in this method
+/// Get an initial draw mode for a shape. Gradient fills and strokes are
+/// colored by the gradients of the shape material, see [`FILL_GRADIENT`] and
+/// [`STROKE_GRADIENT`]
 pub fn shape_draw_mode(shape: &StyledShape) -> DrawMode {
-    let (fill, fill_opacity) = match &shape.fill {
+    let fill_opacity = shape.fill.opacity().initial_value() / 100.0;
+    let (color, fill_rule) = match &shape.fill {
         AnyFill::Solid(fill) => {
-            let fill_opacity = (fill.opacity.initial_value() * 255.0) as u8;
-            let fill = fill.color.initial_value();
-            (fill, fill_opacity)
+            let color = fill.color.initial_value();
+            (
+                SolidOrGradient::Solid(Color::rgb_u8(color.r, color.g, color.b)),
+                &fill.fill_rule,
+            )
         }
-        AnyFill::Gradient(gradient) => (Rgb::new_u8(255, 255, 255), 255),
-    };
-    let fill_rule = match &shape.fill {
-        AnyFill::Solid(fill) => &fill.fill_rule,
-        AnyFill::Gradient(gradient) => &gradient.fill_rule,
+        AnyFill::Gradient(gradient) => (
+            SolidOrGradient::Gradient(FILL_GRADIENT),
+            &gradient.fill_rule,
+        ),
     };
     DrawMode {
-        fill: if fill_opacity == 0 {
+        fill: if fill_opacity <= 0.0 {
             None
         } else {
             let mut fill = Fill {
-                color: SolidOrGradient::Solid(Color::rgba_u8(fill.r, fill.g, fill.b, fill_opacity)),
+                color,
                 options: FillOptions::default(),
-                opacity: 1.0,
+                opacity: fill_opacity,
             };
             fill.options.fill_rule = match fill_rule {
                 LottieFillRule::NonZero => FillRule::NonZero,
@@ -40,24 +43,22 @@ pub fn shape_draw_mode(shape: &StyledShape) -> DrawMode {
         },
         stroke: shape.stroke.as_ref().map(|stroke| {
             let stroke_width: f32 = stroke.width().initial_value();
-            let (color, stroke_opacity) = match &stroke {
+            let color = match &stroke {
                 AnyStroke::Solid(stroke) => {
-                    let stroke_opacity = (stroke.opacity.initial_value() * 255.0) as u8;
-                    let stroke = stroke.color.initial_value();
-                    (stroke, stroke_opacity)
+                    let color = stroke.color.initial_value();
+                    SolidOrGradient::Solid(Color::rgb_u8(color.r, color.g, color.b))
                 }
-                AnyStroke::Gradient(gradient) => (Rgb::new_u8(255, 255, 255), 255),
+                AnyStroke::Gradient(_) => SolidOrGradient::Gradient(STROKE_GRADIENT),
             };
 
             let mut result = Stroke {
-                color: SolidOrGradient::Solid(Color::rgba_u8(
-                    color.r,
-                    color.g,
-                    color.b,
-                    stroke_opacity,
-                )),
-                options: StrokeOptions::default().with_line_width(stroke_width),
-                opacity: 1.0,
+                color,
+                options: StrokeOptions::default()
+                    .with_line_width(stroke_width)
+                    .with_miter_limit(stroke.miter_limit().max(StrokeOptions::MINIMUM_MITER_LIMIT)),
+                opacity: stroke.opacity().initial_value() / 100.0,
+                dashes: stroke.dashes().to_vec(),
+                dash_frame: 0.0,
             };
             let line_cap = match stroke.line_cap() {
                 LottieLineCap::Butt => LineCap::Butt,
@@ -1,7 +1,7 @@
 use std::fs::File;
 use std::path::PathBuf;
 
-use lottie_core::{Config, Error, HeadlessConfig, Lottie, Renderer};
+use lottie_core::{Config, Error, FrameData, HeadlessConfig, Lottie, Renderer};
 use lottie_renderer_bevy::BevyRenderer;
 use rstest::rstest;
 use smol::stream::StreamExt;
@@ -70,3 +70,89 @@ fn check_fixture(
     });
     Ok(())
 }
+
+#[rstest]
+fn render_single_frame(
+    #[files("../../fixtures/ui/checked/**/*.json")] path: PathBuf,
+) -> Result<(), Error> {
+    let f = File::open(&path)?;
+    let lottie = Lottie::from_reader(f, "../../")?;
+    let (width, height) = (lottie.model.width, lottie.model.height);
+    let (mut renderer, _) = BevyRenderer::new();
+    renderer.load_lottie(
+        lottie,
+        Config::Headless(HeadlessConfig {
+            target: lottie_core::Target::Default,
+            filename: String::from("test.webp"),
+            frame: None,
+        }),
+    );
+    let frame = renderer.render_frame(0.0).unwrap();
+    assert_eq!((frame.width, frame.height), (width, height));
+    assert_eq!(frame.data.len(), (width * height * 4) as usize);
+    Ok(())
+}
+
+/// Render `fixtures/ui/simple/<name>.json` at its first frame
+fn render_simple_fixture(name: &str) -> Result<FrameData, Error> {
+    let f = File::open(format!("../../fixtures/ui/simple/{}.json", name))?;
+    let lottie = Lottie::from_reader(f, "../../fixtures/ui/simple")?;
+    let (mut renderer, _) = BevyRenderer::new();
+    renderer.load_lottie(
+        lottie,
+        Config::Headless(HeadlessConfig {
+            target: lottie_core::Target::Default,
+            filename: String::from("test.webp"),
+            frame: None,
+        }),
+    );
+    Ok(renderer.render_frame(0.0).unwrap())
+}
+
+#[test]
+fn render_precomposition_effects() -> Result<(), Error> {
+    let frame = render_simple_fixture("precomposition_effects")?;
+    let pixel = |x: u32, y: u32| {
+        let index = ((y * frame.width + x) * 4) as usize;
+        &frame.data[index..index + 4]
+    };
+    // a red and a blue square side by side, moved right and filled with green
+    // as a whole
+    assert_eq!(pixel(25, 20), [0, 255, 0, 255]);
+    assert_eq!(pixel(55, 20), [0, 255, 0, 255]);
+    assert_eq!(pixel(15, 20)[3], 0);
+    assert_eq!(pixel(65, 20)[3], 0);
+    Ok(())
+}
+
+#[rstest]
+fn render_blend_modes(
+    #[values("blend_modes", "blend_modes_precomposition")] name: &str,
+) -> Result<(), Error> {
+    let frame = render_simple_fixture(name)?;
+    // Every row blends the same color with one mode, from normal to hard mix,
+    // over an orange and a blue backdrop, and then over nothing
+    let decoder = png::Decoder::new(File::open("../../fixtures/ui/simple/blend_modes.png")?);
+    let mut reader = decoder.read_info().unwrap();
+    let mut reference = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut reference).unwrap();
+    assert_eq!((frame.width, frame.height), (info.width, info.height));
+    // The reference is computed in floating point, while the GPU blends 8 bit
+    // targets
+    let pixels = frame.data.chunks_exact(4).zip(reference.chunks_exact(4));
+    for (index, (actual, expected)) in pixels.enumerate() {
+        let (x, y) = (index as u32 % info.width, index as u32 / info.width);
+        assert!(
+            actual
+                .iter()
+                .zip(expected)
+                .all(|(a, e)| a.abs_diff(*e) <= 3),
+            "mode {} at {}: {:?} != {:?}",
+            y / 10,
+            x,
+            actual,
+            expected
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use lottie_core::prelude::Id;
+use lottie_core::*;
+use tiny_skia::Pixmap;
+
+mod paint;
+mod render;
+
+use render::FrameRenderer;
+
+/// A [Renderer] rasterizing Lottie files on CPU with `tiny-skia`
+pub struct SkiaRenderer {
+    lottie: Option<Lottie>,
+    config: Option<Config>,
+    frame_sender: UnboundedSender<FrameData>,
+    images: HashMap<Id, Option<Pixmap>>,
+}
+
+impl SkiaRenderer {
+    pub fn new() -> (Self, UnboundedReceiver<FrameData>) {
+        let (sender, receiver) = unbounded();
+        (
+            SkiaRenderer {
+                lottie: None,
+                config: None,
+                frame_sender: sender,
+                images: HashMap::new(),
+            },
+            receiver,
+        )
+    }
+}
+
+impl Renderer for SkiaRenderer {
+    fn load_lottie(&mut self, lottie: Lottie, config: Config) {
+        match &config {
+            Config::Window(_) => {
+                log::warn!("window mode is not supported, frames will be rendered headlessly")
+            }
+            Config::Headless(HeadlessConfig { target, .. }) if *target == Target::Mask => {
+                log::warn!("mask target is not supported, default target will be rendered")
+            }
+            _ => {}
+        }
+        self.images.clear();
+        self.lottie = Some(lottie);
+        self.config = Some(config);
+    }
+
+    fn render(&mut self) {
+        let (start_frame, end_frame) = match self.lottie.as_ref() {
+            Some(lottie) => (lottie.model.start_frame, lottie.model.end_frame),
+            None => return,
+        };
+        let target_frame = match &self.config {
+            Some(Config::Headless(HeadlessConfig { frame, .. })) => *frame,
+            _ => None,
+        };
+        let frames = match target_frame {
+            Some(frame) => frame..(frame + 1),
+            None => (start_frame.floor() as u32)..(end_frame.ceil() as u32),
+        };
+        for frame in frames {
+            if let Some(data) = self.render_frame(frame as f32) {
+                if self.frame_sender.unbounded_send(data).is_err() {
+                    break;
+                }
+            }
+        }
+        self.frame_sender.close_channel();
+    }
+
+    fn render_frame(&mut self, frame: f32) -> Option<FrameData> {
+        let lottie = self.lottie.as_ref()?;
+        let pixmap = FrameRenderer::new(lottie, &mut self.images, frame).render();
+        let data = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let color = pixel.demultiply();
+                [color.red(), color.green(), color.blue(), color.alpha()]
+            })
+            .collect();
+        Some(FrameData {
+            data,
+            width: pixmap.width(),
+            height: pixmap.height(),
+            timestamp: (frame / lottie.model.frame_rate * 1000.0) as i32,
+        })
+    }
+}
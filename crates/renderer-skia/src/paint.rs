@@ -0,0 +1,149 @@
+use glam::Mat4;
+use lottie_core::prelude::{
+    AnyFill, AnyStroke, FillRule as LottieFillRule, Gradient, GradientType,
+    LineCap as LottieLineCap, LineJoin as LottieLineJoin, Rgb,
+};
+use lyon_path::{Event, Path as LyonPath};
+use tiny_skia::{
+    Color, FillRule, GradientStop, LineCap, LineJoin, LinearGradient, Paint, Path, PathBuilder,
+    Point, RadialGradient, Shader, SpreadMode, Stroke, Transform,
+};
+
+/// Convert a [Mat4] returned by lottie transforms into a 2D [Transform]
+pub fn transform_from_mat4(m: Mat4) -> Transform {
+    Transform::from_row(
+        m.x_axis.x, m.x_axis.y, m.y_axis.x, m.y_axis.y, m.w_axis.x, m.w_axis.y,
+    )
+}
+
+/// Convert a lyon [Path](LyonPath) into a tiny-skia [Path]. Returns `None` if
+/// the path is empty
+pub fn path_from_lyon(path: &LyonPath) -> Option<Path> {
+    let mut builder = PathBuilder::new();
+    for event in path.iter() {
+        match event {
+            Event::Begin { at } => builder.move_to(at.x, at.y),
+            Event::Line { to, .. } => builder.line_to(to.x, to.y),
+            Event::Quadratic { ctrl, to, .. } => builder.quad_to(ctrl.x, ctrl.y, to.x, to.y),
+            Event::Cubic {
+                ctrl1, ctrl2, to, ..
+            } => builder.cubic_to(ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y),
+            Event::End { close, .. } => {
+                if close {
+                    builder.close()
+                }
+            }
+        }
+    }
+    builder.finish()
+}
+
+/// Create the [Paint] used to fill a shape at `frame`, with a global
+/// `opacity` applied. Returns `None` if the fill is fully transparent
+pub fn fill_paint(fill: &AnyFill, frame: f32, opacity: f32) -> Option<(Paint<'static>, FillRule)> {
+    let opacity = opacity * fill.opacity().value(frame) / 100.0;
+    if opacity <= 0.0 {
+        return None;
+    }
+    let (shader, fill_rule) = match fill {
+        AnyFill::Solid(fill) => (solid_shader(fill.color.value(frame)), fill.fill_rule),
+        AnyFill::Gradient(gradient) => (
+            gradient_shader(&gradient.gradient, frame)?,
+            gradient.fill_rule,
+        ),
+    };
+    let fill_rule = match fill_rule {
+        LottieFillRule::NonZero => FillRule::Winding,
+        LottieFillRule::EvenOdd => FillRule::EvenOdd,
+    };
+    Some((paint(shader, opacity), fill_rule))
+}
+
+/// Create the [Paint] and [Stroke] used to stroke a shape at `frame`, with a
+/// global `opacity` applied. Returns `None` if the stroke is invisible
+pub fn stroke_paint(
+    stroke: &AnyStroke,
+    frame: f32,
+    opacity: f32,
+) -> Option<(Paint<'static>, Stroke)> {
+    let opacity = opacity * stroke.opacity().value(frame) / 100.0;
+    let width = stroke.width().value(frame);
+    if opacity <= 0.0 || width <= 0.0 {
+        return None;
+    }
+    let shader = match stroke {
+        AnyStroke::Solid(stroke) => solid_shader(stroke.color.value(frame)),
+        AnyStroke::Gradient(gradient) => gradient_shader(&gradient.gradient, frame)?,
+    };
+    let line_cap = match stroke.line_cap() {
+        LottieLineCap::Butt => LineCap::Butt,
+        LottieLineCap::Round => LineCap::Round,
+        LottieLineCap::Square => LineCap::Square,
+    };
+    let line_join = match stroke.line_join() {
+        LottieLineJoin::Miter => LineJoin::Miter,
+        LottieLineJoin::Round => LineJoin::Round,
+        LottieLineJoin::Bevel => LineJoin::Bevel,
+    };
+    let stroke = Stroke {
+        width,
+        line_cap,
+        line_join,
+        miter_limit: stroke.miter_limit(),
+        ..Default::default()
+    };
+    Some((paint(shader, opacity), stroke))
+}
+
+fn paint(mut shader: Shader<'static>, opacity: f32) -> Paint<'static> {
+    shader.apply_opacity(opacity.min(1.0));
+    Paint {
+        shader,
+        anti_alias: true,
+        ..Default::default()
+    }
+}
+
+fn solid_shader(color: Rgb) -> Shader<'static> {
+    Shader::SolidColor(Color::from_rgba8(color.r, color.g, color.b, 255))
+}
+
+fn gradient_shader(gradient: &Gradient, frame: f32) -> Option<Shader<'static>> {
+    let start = gradient.start.value(frame);
+    let end = gradient.end.value(frame);
+    let stops = gradient
+        .colors
+        .colors
+        .value(frame)
+        .iter()
+        .map(|stop| {
+            let color = stop.color;
+            GradientStop::new(
+                stop.offset,
+                Color::from_rgba8(color.r, color.g, color.b, color.a),
+            )
+        })
+        .collect::<Vec<_>>();
+    let start_point = Point::from_xy(start.x, start.y);
+    match gradient.gradient_ty {
+        GradientType::Linear => LinearGradient::new(
+            start_point,
+            Point::from_xy(end.x, end.y),
+            stops,
+            SpreadMode::Pad,
+            Transform::identity(),
+        ),
+        GradientType::Radial => {
+            // the highlight moves the focal point away from the center
+            let focal = gradient.focal_point(frame);
+            RadialGradient::new(
+                Point::from_xy(focal.x, focal.y),
+                start_point,
+                (end - start).length(),
+                stops,
+                SpreadMode::Pad,
+                Transform::identity(),
+            )
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use lottie_core::prelude::*;
+use lottie_core::Lottie;
+use ordered_float::OrderedFloat;
+use tiny_skia::{IntSize, Mask, MaskType, Pixmap, PixmapPaint, PremultipliedColorU8, Transform};
+
+use crate::paint::{fill_paint, path_from_lyon, stroke_paint, transform_from_mat4};
+
+/// Renders a single frame of a [Lottie] into a [Pixmap]
+pub(crate) struct FrameRenderer<'a> {
+    lottie: &'a Lottie,
+    images: &'a mut HashMap<Id, Option<Pixmap>>,
+    frame: f32,
+    width: u32,
+    height: u32,
+}
+
+impl<'a> FrameRenderer<'a> {
+    pub fn new(
+        lottie: &'a Lottie,
+        images: &'a mut HashMap<Id, Option<Pixmap>>,
+        frame: f32,
+    ) -> Self {
+        let width = ((lottie.model.width as f32 * lottie.scale).round() as u32).max(1);
+        let height = ((lottie.model.height as f32 * lottie.scale).round() as u32).max(1);
+        FrameRenderer {
+            lottie,
+            images,
+            frame,
+            width,
+            height,
+        }
+    }
+
+    pub fn render(mut self) -> Pixmap {
+        let mut pixmap = self.pixmap();
+        self.draw_layers(None, &mut pixmap);
+        pixmap
+    }
+
+    /// Draw the layers composited with `compositing`, or with the frame when
+    /// `None`
+    fn draw_layers(&mut self, compositing: Option<Id>, target: &mut Pixmap) {
+        let timeline = self.lottie.timeline();
+        let mut layers = timeline
+            .items()
+            .filter(|layer| !layer.is_mask)
+            .filter(|layer| timeline.compositing_layer(layer).map(|l| l.id) == compositing)
+            .collect::<Vec<_>>();
+        // Smaller zindex means closer to the viewer, so draw from the largest
+        layers.sort_by_key(|layer| Reverse(OrderedFloat(self.zindex(layer))));
+        for layer in layers {
+            self.draw_layer(layer, target);
+        }
+    }
+
+    fn pixmap(&self) -> Pixmap {
+        Pixmap::new(self.width, self.height).unwrap()
+    }
+
+    /// Global zindex of a layer, which is the sum of all zindexes in its
+    /// parenting chain
+    fn zindex(&self, layer: &StagedLayer) -> f32 {
+        let mut zindex = 0.0;
+        let mut current = Some(layer);
+        while let Some(l) = current {
+            zindex += l.zindex;
+            current = l.parent.and_then(|id| self.lottie.timeline().item(id));
+        }
+        zindex
+    }
+
+    /// Global transform of a layer at current frame. Returns `None` if the
+    /// layer or any of its ancestors is not visible
+    fn layer_transform(&self, layer: &StagedLayer) -> Option<Transform> {
+        let mut transform = Transform::identity();
+        let mut current = Some(layer);
+        while let Some(l) = current {
+            let frame = l.frame_transform_hierarchy.value(self.frame)?;
+            transform = transform_from_mat4(l.transform.value(frame)).pre_concat(transform);
+            current = l.parent.and_then(|id| self.lottie.timeline().item(id));
+        }
+        let scale = self.lottie.scale;
+        Some(Transform::from_scale(scale, scale).pre_concat(transform))
+    }
+
+    fn draw_layer(&mut self, layer: &StagedLayer, target: &mut Pixmap) {
+        if layer.mask_hierarchy.is_empty()
+            && layer.blend_mode == BlendMode::Normal
+            && layer.effects.is_empty()
+        {
+            self.draw_content(layer, target);
+            return;
+        }
+        let mut content = self.pixmap();
+        if layer.is_composited() {
+            // The content carries the masks of the layer already
+            self.draw_layers(Some(layer.id), &mut content);
+        } else {
+            self.draw_content(layer, &mut content);
+            self.apply_masks(layer, &mut content);
+        }
+        if let Some(frame) = layer.frame_transform_hierarchy.value(self.frame) {
+            apply_effects(
+                &layer.effects,
+                content.data_mut(),
+                self.width as usize,
+                self.height as usize,
+                frame,
+                self.lottie.scale,
+            );
+        }
+        composite(layer.blend_mode, &content, target);
+    }
+
+    fn apply_masks(&mut self, layer: &StagedLayer, content: &mut Pixmap) {
+        // Alpha of the layer masks combined so far
+        let mut combined: Option<Vec<f32>> = None;
+        for mask in layer.mask_hierarchy.masks() {
+            let (mask_type, inverted) = match mask.mode {
+                MatteMode::Normal => continue,
+                MatteMode::Alpha => (MaskType::Alpha, false),
+                MatteMode::InvertedAlpha => (MaskType::Alpha, true),
+                MatteMode::Luma => (MaskType::Luminance, false),
+                MatteMode::InvertedLuma => (MaskType::Luminance, true),
+            };
+            let mut mask_pixmap = self.pixmap();
+            if let Some(mask_layer) = self.lottie.timeline().item(mask.id) {
+                self.draw_content(mask_layer, &mut mask_pixmap);
+            }
+            let mut matte = Mask::from_pixmap(mask_pixmap.as_ref(), mask_type);
+            match &mask.composition {
+                Some(composition) => {
+                    if composition.first {
+                        if let Some(alpha) = combined.take() {
+                            self.apply_alpha(&alpha, content);
+                        }
+                    }
+                    let alpha = combined.get_or_insert_with(|| {
+                        vec![composition.initial_alpha(); matte.data().len()]
+                    });
+                    for (accumulated, value) in alpha.iter_mut().zip(matte.data()) {
+                        *accumulated = composition.combine(*accumulated, *value as f32 / 255.0);
+                    }
+                }
+                None => {
+                    if let Some(alpha) = combined.take() {
+                        self.apply_alpha(&alpha, content);
+                    }
+                    if inverted {
+                        matte.invert();
+                    }
+                    content.apply_mask(&matte);
+                }
+            }
+        }
+        if let Some(alpha) = combined.take() {
+            self.apply_alpha(&alpha, content);
+        }
+    }
+
+    /// Multiply `content` by the combined alpha of a set of layer masks
+    fn apply_alpha(&self, alpha: &[f32], content: &mut Pixmap) {
+        let mut mask = Mask::new(self.width, self.height).unwrap();
+        for (value, alpha) in mask.data_mut().iter_mut().zip(alpha) {
+            *value = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        content.apply_mask(&mask);
+    }
+
+    fn draw_content(&mut self, layer: &StagedLayer, target: &mut Pixmap) {
+        let (frame, transform) = match (
+            layer.frame_transform_hierarchy.value(self.frame),
+            self.layer_transform(layer),
+        ) {
+            (Some(frame), Some(transform)) => (frame, transform),
+            _ => return,
+        };
+        let opacity = OpacityHierarchy::from(&layer.transform_hierarchy).value(frame);
+        if opacity <= 0.0 {
+            return;
+        }
+        match &layer.content {
+            RenderableContent::Shape(group) => {
+                draw_shapes(group, &[], frame, opacity, transform, target)
+            }
+            RenderableContent::Media(media) => {
+                let image = self
+                    .images
+                    .entry(layer.id)
+                    .or_insert_with(|| decode_image(&media.content));
+                if let Some(image) = image.as_ref() {
+                    let width = if media.width == 0 {
+                        image.width()
+                    } else {
+                        media.width
+                    };
+                    let height = if media.height == 0 {
+                        image.height()
+                    } else {
+                        media.height
+                    };
+                    let transform = transform.pre_scale(
+                        width as f32 / image.width() as f32,
+                        height as f32 / image.height() as f32,
+                    );
+                    let paint = PixmapPaint {
+                        opacity: opacity.min(1.0),
+                        ..Default::default()
+                    };
+                    target.draw_pixmap(0, 0, image.as_ref(), &paint, transform, None);
+                }
+            }
+            RenderableContent::Group => {}
+        }
+    }
+}
+
+fn draw_shapes(
+    group: &ShapeGroup,
+    operations: &[PathOperation],
+    frame: f32,
+    opacity: f32,
+    transform: Transform,
+    target: &mut Pixmap,
+) {
+    let shapes = group
+        .styled_shapes()
+        .with_operations(operations)
+        .collect::<Vec<_>>();
+    // Shapes listed first are rendered on top
+    for shape in shapes.into_iter().rev() {
+        if shape.shape.hidden {
+            continue;
+        }
+        let transform = transform.pre_concat(transform_from_mat4(shape.transform.value(frame)));
+        let opacity = opacity * shape.transform.opacity_value(frame) / 100.0;
+        match &shape.shape.shape {
+            Shape::Group { shapes } => {
+                let group = ShapeGroup {
+                    shapes: shapes.clone(),
+                };
+                draw_shapes(&group, &shape.operations, frame, opacity, transform, target);
+            }
+            s => {
+                let path = apply_path_operations(s.path(frame), &shape.operations, frame);
+                if let Some(fill_path) = path_from_lyon(&path) {
+                    if let Some((paint, fill_rule)) = fill_paint(&shape.fill, frame, opacity) {
+                        target.fill_path(&fill_path, &paint, fill_rule, transform, None);
+                    }
+                }
+                if let Some(stroke) = shape.stroke.as_ref() {
+                    let path = dash_path(path, stroke.dashes(), frame);
+                    if let (Some(path), Some((paint, stroke))) =
+                        (path_from_lyon(&path), stroke_paint(stroke, frame, opacity))
+                    {
+                        target.stroke_path(&path, &paint, &stroke, transform, None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Composite a layer drawn offscreen onto the backdrop in `target`
+fn composite(blend_mode: BlendMode, content: &Pixmap, target: &mut Pixmap) {
+    let blend_mode = match blend_mode {
+        BlendMode::Normal => tiny_skia::BlendMode::SourceOver,
+        BlendMode::Multiply => tiny_skia::BlendMode::Multiply,
+        BlendMode::Screen => tiny_skia::BlendMode::Screen,
+        BlendMode::Overlay => tiny_skia::BlendMode::Overlay,
+        BlendMode::Darken => tiny_skia::BlendMode::Darken,
+        BlendMode::Lighten => tiny_skia::BlendMode::Lighten,
+        BlendMode::ColorDodge => tiny_skia::BlendMode::ColorDodge,
+        BlendMode::ColorBurn => tiny_skia::BlendMode::ColorBurn,
+        BlendMode::HighLight => tiny_skia::BlendMode::HardLight,
+        BlendMode::SoftLight => tiny_skia::BlendMode::SoftLight,
+        BlendMode::Difference => tiny_skia::BlendMode::Difference,
+        BlendMode::Exclusion => tiny_skia::BlendMode::Exclusion,
+        BlendMode::Hue => tiny_skia::BlendMode::Hue,
+        BlendMode::Saturation => tiny_skia::BlendMode::Saturation,
+        BlendMode::Color => tiny_skia::BlendMode::Color,
+        BlendMode::Luminosity => tiny_skia::BlendMode::Luminosity,
+        BlendMode::Add => tiny_skia::BlendMode::Plus,
+        BlendMode::HardMix => {
+            hard_mix(content, target);
+            return;
+        }
+    };
+    let paint = PixmapPaint {
+        blend_mode,
+        ..PixmapPaint::default()
+    };
+    target.draw_pixmap(0, 0, content.as_ref(), &paint, Transform::identity(), None);
+}
+
+/// Hard mix has no tiny-skia counterpart, so it is blended per pixel: every
+/// channel becomes 0 or 1 depending on whether source and backdrop add up to 1
+fn hard_mix(content: &Pixmap, target: &mut Pixmap) {
+    for (backdrop, source) in target.pixels_mut().iter_mut().zip(content.pixels()) {
+        if source.alpha() == 0 {
+            continue;
+        }
+        let sa = source.alpha() as f32 / 255.0;
+        let ba = backdrop.alpha() as f32 / 255.0;
+        let alpha = sa + ba - sa * ba;
+        let channel = |s: u8, b: u8| {
+            let (s, b) = (s as f32 / 255.0, b as f32 / 255.0);
+            let mixed = if ba > 0.0 && s / sa + b / ba >= 1.0 {
+                1.0
+            } else {
+                0.0
+            };
+            let value = s * (1.0 - ba) + b * (1.0 - sa) + sa * ba * mixed;
+            (value.min(alpha) * 255.0).round() as u8
+        };
+        let color = PremultipliedColorU8::from_rgba(
+            channel(source.red(), backdrop.red()),
+            channel(source.green(), backdrop.green()),
+            channel(source.blue(), backdrop.blue()),
+            (alpha * 255.0).round() as u8,
+        );
+        if let Some(color) = color {
+            *backdrop = color;
+        }
+    }
+}
+
+fn decode_image(content: &[u8]) -> Option<Pixmap> {
+    let image = match image::load_from_memory(content) {
+        Ok(image) => image.into_rgba8(),
+        Err(e) => {
+            log::debug!("skipping media which is not an image: {:?}", e);
+            return None;
+        }
+    };
+    let size = IntSize::from_wh(image.width(), image.height())?;
+    let mut data = image.into_raw();
+    for pixel in data.chunks_exact_mut(4) {
+        let a = pixel[3] as u16;
+        for c in &mut pixel[..3] {
+            *c = ((*c as u16 * a + 127) / 255) as u8;
+        }
+    }
+    Pixmap::from_vec(data, size)
+}
@@ -0,0 +1,400 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use futures::StreamExt;
+use lottie_core::{Config, Error, FrameData, HeadlessConfig, Lottie, Renderer, Target};
+use lottie_renderer_skia::SkiaRenderer;
+use rstest::rstest;
+
+const RECT: &str = r#"{
+    "v": "5.5.7", "ip": 0, "op": 10, "fr": 10, "w": 100, "h": 100,
+    "layers": [{
+        "ty": 4, "ind": 0, "st": 0, "ip": 0, "op": 10, "nm": "Rect",
+        "ks": {},
+        "shapes": [
+            {
+                "ty": "rc", "d": 1, "r": { "a": 0, "k": 0 },
+                "p": { "a": 0, "k": [50, 50] }, "s": { "a": 0, "k": [50, 50] }
+            },
+            {
+                "ty": "fl", "o": { "a": 0, "k": 100 }, "c": { "a": 0, "k": [1, 0, 0] }
+            }
+        ]
+    }]
+}"#;
+
+fn headless(frame: Option<u32>) -> Config {
+    Config::Headless(HeadlessConfig {
+        target: Target::Default,
+        filename: String::from("test"),
+        frame,
+    })
+}
+
+fn render(lottie: Lottie, frame: Option<u32>) -> Vec<FrameData> {
+    let (mut renderer, frame_stream) = SkiaRenderer::new();
+    renderer.load_lottie(lottie, headless(frame));
+    renderer.render();
+    smol::block_on(frame_stream.collect::<Vec<_>>())
+}
+
+/// Render `fixtures/ui/simple/<name>.json`, only at `frame` if given
+fn render_fixture_frame(name: &str, frame: Option<u32>) -> Vec<FrameData> {
+    let f = File::open(format!("../../fixtures/ui/simple/{}.json", name)).unwrap();
+    let lottie = Lottie::from_reader(f, "../../fixtures/ui/simple").unwrap();
+    render(lottie, frame)
+}
+
+fn pixel(frame: &FrameData, x: u32, y: u32) -> [u8; 4] {
+    let index = ((y * frame.width + x) * 4) as usize;
+    frame.data[index..index + 4].try_into().unwrap()
+}
+
+/// Whether every channel is within `tolerance` of the expected one
+fn near<const N: usize>(actual: [u8; N], expected: [u8; N], tolerance: u8) -> bool {
+    actual
+        .iter()
+        .zip(expected)
+        .all(|(a, e)| a.abs_diff(e) <= tolerance)
+}
+
+#[test]
+fn render_solid_rect() -> Result<(), Error> {
+    let lottie = Lottie::from_reader(RECT.as_bytes(), "")?;
+    let frames = render(lottie, None);
+    assert_eq!(frames.len(), 10);
+    let frame = &frames[0];
+    assert_eq!((frame.width, frame.height), (100, 100));
+    assert_eq!(pixel(frame, 50, 50), [255, 0, 0, 255]);
+    assert_eq!(pixel(frame, 10, 10), [0, 0, 0, 0]);
+    assert_eq!(frames[5].timestamp, 500);
+    Ok(())
+}
+
+#[test]
+fn render_scaled_single_frame() -> Result<(), Error> {
+    let mut lottie = Lottie::from_reader(RECT.as_bytes(), "")?;
+    lottie.scale = 2.0;
+    let frames = render(lottie, Some(3));
+    assert_eq!(frames.len(), 1);
+    let frame = &frames[0];
+    assert_eq!((frame.width, frame.height), (200, 200));
+    assert_eq!(frame.timestamp, 300);
+    assert_eq!(pixel(frame, 60, 60), [255, 0, 0, 255]);
+    assert_eq!(pixel(frame, 40, 40), [0, 0, 0, 0]);
+    Ok(())
+}
+
+#[test]
+fn render_frame_random_access() -> Result<(), Error> {
+    let f = File::open("../../fixtures/ui/simple/parenting.json")?;
+    let lottie = Lottie::from_reader(f, "../../fixtures/ui/simple")?;
+    let (mut renderer, frame_stream) = SkiaRenderer::new();
+    assert!(renderer.render_frame(0.0).is_none());
+    renderer.load_lottie(lottie, headless(None));
+    let later = renderer.render_frame(57.0).unwrap();
+    let earlier = renderer.render_frame(3.0).unwrap();
+    renderer.render();
+    let frames = smol::block_on(frame_stream.collect::<Vec<_>>());
+    assert_eq!(later.timestamp, frames[57].timestamp);
+    assert!(later.data == frames[57].data);
+    assert!(earlier.data == frames[3].data);
+    assert!(earlier.data != later.data);
+    Ok(())
+}
+
+#[test]
+fn render_mask_modes() -> Result<(), Error> {
+    let frames = render_fixture_frame("mask_modes", Some(0));
+    // Alpha of the left, middle and right part of every row, each of which
+    // combines an opaque mask over the left two parts with a half transparent
+    // one over the right two parts
+    let expected = [
+        ("add", [255, 255, 128]),
+        ("subtract", [255, 128, 0]),
+        ("intersect", [0, 128, 0]),
+        ("lighten", [255, 255, 128]),
+        ("darken", [0, 128, 0]),
+        ("difference", [255, 128, 128]),
+        ("inverted", [255, 0, 255]),
+        ("inverted add", [255, 255, 0]),
+        ("first subtract", [255, 0, 0]),
+    ];
+    for (row, (name, alphas)) in expected.iter().enumerate() {
+        let y = row as u32 * 20 + 10;
+        for (x, alpha) in [20, 60, 100].into_iter().zip(alphas) {
+            let actual = pixel(&frames[0], x, y)[3];
+            assert!(
+                (actual as i32 - alpha).abs() <= 1,
+                "{} at {}: {} != {}",
+                name,
+                x,
+                actual,
+                alpha
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn render_mask_feather() -> Result<(), Error> {
+    let frames = render_fixture_frame("mask_feather", Some(0));
+    let alpha = |row: u32, x: u32| pixel(&frames[0], x, row * 20 + 10)[3];
+    // expanded, shrunk and counterclockwise masks all end up at 30..90
+    for row in 0..3 {
+        assert_eq!(
+            [25, 35, 85, 95].map(|x| alpha(row, x)),
+            [0, 255, 255, 0],
+            "row {}",
+            row
+        );
+    }
+    // feathered edges fade over 20 pixels around x = 30
+    for row in [3, 5] {
+        assert_eq!([15, 45, 60].map(|x| alpha(row, x)), [0, 255, 255]);
+        assert!((alpha(row, 30) as i32 - 128).abs() < 20);
+        assert!(alpha(row, 25) < alpha(row, 30) && alpha(row, 30) < alpha(row, 35));
+    }
+    assert_eq!([15, 45, 60].map(|x| alpha(4, x)), [255, 0, 0]);
+    assert!((alpha(4, 30) as i32 - 128).abs() < 20);
+    Ok(())
+}
+
+#[test]
+fn render_masked_layers() -> Result<(), Error> {
+    let frames = render_fixture_frame("mask_layers", Some(0));
+    let alpha = |row: u32, x: u32| pixel(&frames[0], x, row * 20 + 10)[3];
+    // a precomposition, a solid and an image, all masked to 40..80
+    for row in 0..3 {
+        assert_eq!(
+            [20, 60, 100].map(|x| alpha(row, x)),
+            [0, 255, 0],
+            "row {}",
+            row
+        );
+    }
+    // children only share the transform of a masked parent
+    assert_eq!([20, 60, 100].map(|x| alpha(3, x)), [255, 0, 255]);
+    Ok(())
+}
+
+#[test]
+fn render_luma_mattes() -> Result<(), Error> {
+    let frames = render_fixture_frame("matte_luma", Some(0));
+    let alpha = |row: u32, x: u32| pixel(&frames[0], x, row * 20 + 10)[3];
+    // the mattes are white, 50% gray and transparent from left to right
+    let luma = [20, 60, 100].map(|x| alpha(0, x));
+    assert!(near(luma, [255, 128, 0], 2), "{:?}", luma);
+    let inverted = [20, 60, 100].map(|x| alpha(1, x));
+    assert!(near(inverted, [0, 127, 255], 2), "{:?}", inverted);
+    // `tp` picks a matte other than the layer above, which stays visible
+    assert_eq!([20, 60, 100].map(|x| alpha(2, x)), [255, 0, 0]);
+    assert_eq!([20, 60, 100].map(|x| alpha(3, x)), [255, 255, 255]);
+    Ok(())
+}
+
+#[test]
+fn render_gradients() -> Result<(), Error> {
+    let frames = render_fixture_frame("gradients", Some(0));
+    let color = |row: u32, x: u32| pixel(&frames[0], x, row * 20 + 10);
+    // red, green and blue stops
+    for (x, expected) in [
+        (0, [255, 0, 0, 255]),
+        (60, [0, 255, 0, 255]),
+        (119, [0, 0, 255, 255]),
+    ] {
+        assert!(near(color(0, x), expected, 8), "{}: {:?}", x, color(0, x));
+    }
+    // white in the center fading to black at a radius of 40
+    assert!(near(color(1, 60), [255, 255, 255, 255], 8));
+    assert!(near(color(1, 100), [0, 0, 0, 255], 8));
+    assert!(near(color(1, 20), [0, 0, 0, 255], 8));
+    // the highlight moves the white focal point half way to the edge
+    assert!(near(color(2, 80), [255, 255, 255, 255], 8));
+    assert!(color(2, 60)[0] < 240);
+    assert!(color(2, 70)[0] > color(2, 50)[0]);
+    // alpha stops fade out towards the middle, independently of color stops
+    let alpha = [0, 60, 119].map(|x| color(3, x)[3]);
+    assert!(
+        alpha[0] > 247 && alpha[1] < 8 && alpha[2] > 247,
+        "{:?}",
+        alpha
+    );
+    // gradient strokes
+    assert!(near(color(4, 2), [255, 0, 0, 255], 8));
+    assert!(near(color(4, 117), [0, 0, 255, 255], 8));
+    Ok(())
+}
+
+#[test]
+fn render_gradient_strokes() -> Result<(), Error> {
+    let frames = render_fixture_frame("gradient_strokes", Some(0));
+    // the gradient spans the untrimmed line, half of which is trimmed away
+    let color = pixel(&frames[0], 2, 10);
+    assert!(near(color, [250, 0, 4, 255], 8), "{:?}", color);
+    let color = pixel(&frames[0], 57, 10);
+    assert!(near(color, [134, 0, 121, 255], 8), "{:?}", color);
+    assert_eq!(pixel(&frames[0], 70, 10)[3], 0);
+    // a circle of radius 30 stroked with a radial gradient of radius 40
+    for (x, y) in [(90, 50), (30, 50), (60, 20), (60, 80)] {
+        let color = pixel(&frames[0], x, y);
+        assert!(near(color, [64, 64, 64, 255], 8), "{:?}", color);
+    }
+    assert_eq!(pixel(&frames[0], 60, 50)[3], 0);
+    Ok(())
+}
+
+#[test]
+fn render_dashes() -> Result<(), Error> {
+    let frames = render_fixture_frame("dashes", None);
+    // dashes of 10 and gaps of 10, offset by one unit per frame
+    assert_eq!(pixel(&frames[0], 5, 10), [255, 0, 0, 255]);
+    assert_eq!(pixel(&frames[0], 15, 10)[3], 0);
+    assert_eq!(pixel(&frames[5], 7, 10)[3], 0);
+    assert_eq!(pixel(&frames[5], 17, 10), [255, 0, 0, 255]);
+    // zero length dashes with round caps draw dots
+    assert_eq!(pixel(&frames[0], 10, 30), [0, 0, 255, 255]);
+    assert_eq!(pixel(&frames[0], 20, 30)[3], 0);
+    // gradient strokes are dashed as well
+    assert_eq!(pixel(&frames[0], 10, 50)[3], 255);
+    assert_eq!(pixel(&frames[0], 30, 50)[3], 0);
+    Ok(())
+}
+
+#[test]
+fn render_stroke_miter_and_color() -> Result<(), Error> {
+    let frames = render_fixture_frame("stroke_miter", None);
+    // the miter of the sharp corner is only drawn within the miter limit
+    assert_eq!(pixel(&frames[0], 30, 8), [255, 0, 0, 255]);
+    assert_eq!(pixel(&frames[0], 80, 8)[3], 0);
+    // the stroke color fades from red to blue
+    assert_eq!(pixel(&frames[0], 5, 55), [255, 0, 0, 255]);
+    let color = pixel(&frames[5], 5, 55);
+    assert!(
+        color[0].abs_diff(127) <= 2 && color[1] == 0 && color[2].abs_diff(127) <= 2,
+        "{:?}",
+        color
+    );
+    Ok(())
+}
+
+#[test]
+fn render_effects() -> Result<(), Error> {
+    let frames = render_fixture_frame("effects", None);
+    // a red square filled with blue
+    assert_eq!(pixel(&frames[0], 15, 15), [0, 0, 255, 255]);
+    // red has a luminance of 0.3, tinted from black to green
+    let color = pixel(&frames[0], 45, 15);
+    assert!(near(color, [0, 77, 0, 255], 2), "{:?}", color);
+    // a red shadow 10 units below a black square
+    assert_eq!(pixel(&frames[0], 75, 15), [0, 0, 0, 255]);
+    assert_eq!(pixel(&frames[0], 75, 30), [255, 0, 0, 255]);
+    assert_eq!(pixel(&frames[0], 75, 38)[3], 0);
+    // the blur grows horizontally from nothing
+    assert_eq!(pixel(&frames[0], 93, 15)[3], 0);
+    assert!(pixel(&frames[5], 93, 15)[3] > 0);
+    assert_eq!(pixel(&frames[5], 105, 3)[3], 0);
+    // gray is mapped to the midtones
+    let color = pixel(&frames[0], 135, 15);
+    assert!(near(color, [255, 127, 0, 255], 2), "{:?}", color);
+    Ok(())
+}
+
+#[test]
+fn render_precomposition_effects() -> Result<(), Error> {
+    let frames = render_fixture_frame("precomposition_effects", Some(0));
+    // a red and a blue square side by side, moved right and filled with green
+    // as a whole
+    assert_eq!(pixel(&frames[0], 25, 20), [0, 255, 0, 255]);
+    assert_eq!(pixel(&frames[0], 55, 20), [0, 255, 0, 255]);
+    assert_eq!(pixel(&frames[0], 15, 20)[3], 0);
+    assert_eq!(pixel(&frames[0], 65, 20)[3], 0);
+    Ok(())
+}
+
+#[test]
+fn render_animated_gradient() -> Result<(), Error> {
+    let frames = render_fixture_frame("gradient_animated", None);
+    // red to blue from x = 0 to 100
+    let color = pixel(&frames[0], 10, 10);
+    assert!(near(color, [230, 0, 25, 255], 8), "{:?}", color);
+    // half way the start point moved to x = 25, the first stop turned olive
+    // and the second stop moved to an offset of 0.75
+    for (x, expected) in [
+        (10, [128, 128, 0, 255]),
+        (53, [64, 64, 128, 255]),
+        (90, [0, 0, 255, 255]),
+    ] {
+        let color = pixel(&frames[5], x, 10);
+        assert!(near(color, expected, 8), "{}: {:?}", x, color);
+    }
+    Ok(())
+}
+
+#[rstest]
+fn render_blend_modes(
+    #[values("blend_modes", "blend_modes_precomposition")] name: &str,
+) -> Result<(), Error> {
+    let frames = render_fixture_frame(name, Some(0));
+    // Every row blends the same color with one mode, from normal to hard mix,
+    // over an orange and a blue backdrop, and then over nothing. Blending a
+    // precomposition blends its content as a whole the same way
+    let reference = image::open("../../fixtures/ui/simple/blend_modes.png")
+        .unwrap()
+        .into_rgba8();
+    assert_eq!((frames[0].width, frames[0].height), reference.dimensions());
+    // The reference is computed in floating point, while tiny-skia blends
+    // with 8 bit precision and approximates divisions
+    for (x, y, expected) in reference.enumerate_pixels() {
+        let actual = pixel(&frames[0], x, y);
+        assert!(
+            near(actual, expected.0, 3),
+            "mode {} at {}: {:?} != {:?}",
+            y / 10,
+            x,
+            actual,
+            expected.0
+        );
+    }
+    Ok(())
+}
+
+#[rstest]
+fn render_fixture(
+    #[values(
+        "parenting",
+        "polystar",
+        "precomposition",
+        "remapping",
+        "auto_orient",
+        "mask_modes",
+        "mask_feather",
+        "mask_layers",
+        "matte_luma",
+        "blend_modes",
+        "blend_modes_precomposition",
+        "gradients",
+        "gradient_animated",
+        "gradient_strokes",
+        "dashes",
+        "stroke_miter",
+        "effects",
+        "precomposition_effects"
+    )]
+    name: &str,
+) -> Result<(), Error> {
+    let path = PathBuf::from(format!("../../fixtures/ui/simple/{}.json", name));
+    let f = File::open(&path)?;
+    let lottie = Lottie::from_reader(f, "../../fixtures/ui/simple")?;
+    let size = (lottie.model.width, lottie.model.height);
+    let frame_count = (lottie.model.end_frame - lottie.model.start_frame).ceil() as usize;
+    let frames = render(lottie, None);
+    assert_eq!(frames.len(), frame_count);
+    for frame in frames {
+        assert_eq!((frame.width, frame.height), size);
+        assert_eq!(frame.data.len(), (size.0 * size.1 * 4) as usize);
+        assert!(frame.data.chunks_exact(4).any(|pixel| pixel[3] != 0));
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+#!/usr/bin/env python3
+"""Generate blend_modes.json and its reference rendering blend_modes.png.
+
+Every row of the animation blends the same green rectangle with one blend
+mode, from normal to hard mix, over an orange and a blue backdrop, and then
+over nothing. blend_modes_precomposition.json renders the same, with each row
+drawn by a precomposition layer holding the blend mode. The reference colors follow the separable and non-separable
+blend formulas of the W3C Compositing and Blending spec, computed in floating
+point without any of the renderers, so the rendering tests check against an
+independent result.
+
+Run it from anywhere with `python3 fixtures/ui/simple/blend_modes.py`, it only
+needs the standard library.
+"""
+import json
+import os
+import struct
+import zlib
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+# In the order of their `bm` value
+MODES = [
+    "Normal", "Multiply", "Screen", "Overlay", "Darken", "Lighten",
+    "Color dodge", "Color burn", "Hard light", "Soft light", "Difference",
+    "Exclusion", "Hue", "Saturation", "Color", "Luminosity", "Add", "Hard mix",
+]
+ORANGE = (0.8, 0.4, 0.2)
+BLUE = (0.2, 0.6, 0.9)
+SOURCE = (0.3, 0.7, 0.45)
+WIDTH, HEIGHT = 120, 180
+ROW = 10
+
+
+def transform():
+    return {
+        "a": {"a": 0, "k": [0, 0]},
+        "p": {"a": 0, "k": [0, 0]},
+        "s": {"a": 0, "k": [100, 100]},
+        "r": {"a": 0, "k": 0},
+        "o": {"a": 0, "k": 100},
+    }
+
+
+def rect(x0, y0, x1, y1, color):
+    return {"ty": "gr", "nm": "Rect", "it": [
+        {"ty": "rc", "d": 1, "nm": "Rect path",
+         "p": {"a": 0, "k": [(x0 + x1) / 2, (y0 + y1) / 2]},
+         "s": {"a": 0, "k": [x1 - x0, y1 - y0]},
+         "r": {"a": 0, "k": 0}},
+        {"ty": "fl", "nm": "Fill", "o": {"a": 0, "k": 100},
+         "c": {"a": 0, "k": [color[0], color[1], color[2], 1]}, "r": 1},
+        dict(ty="tr", **transform()),
+    ]}
+
+
+def layer(index, name, mode, shapes):
+    return {"ddd": 0, "ty": 4, "ind": index, "st": 0, "ip": 0, "op": 10,
+            "nm": name, "bm": mode, "ks": transform(), "shapes": shapes}
+
+
+def precomposition(index, name, mode, asset):
+    return {"ddd": 0, "ty": 0, "ind": index, "st": 0, "ip": 0, "op": 10,
+            "nm": name, "bm": mode, "ks": transform(), "refId": asset,
+            "w": WIDTH, "h": HEIGHT}
+
+
+def animation(precomposed=False):
+    layers, assets = [], []
+    for i, name in enumerate(MODES):
+        shapes = [rect(20, i * ROW, 100, (i + 1) * ROW, SOURCE)]
+        if precomposed:
+            asset = "row_{}".format(i)
+            assets.append({"id": asset, "layers": [layer(1, name, 0, shapes)]})
+            layers.append(precomposition(i + 1, name, i, asset))
+        else:
+            layers.append(layer(i + 1, name, i, shapes))
+    backdrop = [rect(0, 0, 40, HEIGHT, ORANGE), rect(40, 0, 80, HEIGHT, BLUE)]
+    layers.append(layer(len(MODES) + 1, "Backdrop", 0, backdrop))
+    return {"v": "5.7.1", "ip": 0, "op": 10, "nm": "Blend modes", "fr": 10,
+            "w": WIDTH, "h": HEIGHT, "assets": assets, "layers": layers}
+
+
+def quantize(c):
+    """Colors as stored in 8 bits by the renderers"""
+    return tuple(round(v * 255) / 255 for v in c)
+
+
+def lum(c):
+    return 0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+
+
+def clip_color(c):
+    l, n, x = lum(c), min(c), max(c)
+    if n < 0:
+        c = tuple(l + (v - l) * l / (l - n) for v in c)
+    if x > 1:
+        c = tuple(l + (v - l) * (1 - l) / (x - l) for v in c)
+    return c
+
+
+def set_lum(c, l):
+    d = l - lum(c)
+    return clip_color(tuple(v + d for v in c))
+
+
+def sat(c):
+    return max(c) - min(c)
+
+
+def set_sat(c, s):
+    mn, md, mx = sorted(range(3), key=lambda i: c[i])
+    result = [0.0] * 3
+    if c[mx] > c[mn]:
+        result[md] = (c[md] - c[mn]) * s / (c[mx] - c[mn])
+        result[mx] = s
+    return tuple(result)
+
+
+def separable(f):
+    return lambda b, s: tuple(f(x, y) for x, y in zip(b, s))
+
+
+def hard_light(b, s):
+    return b * 2 * s if s <= 0.5 else 1 - (1 - b) * (1 - (2 * s - 1))
+
+
+def soft_light(b, s):
+    if s <= 0.5:
+        return b - (1 - 2 * s) * b * (1 - b)
+    d = ((16 * b - 12) * b + 4) * b if b <= 0.25 else b ** 0.5
+    return b + (2 * s - 1) * (d - b)
+
+
+def color_dodge(b, s):
+    if b == 0:
+        return 0
+    return 1 if s >= 1 else min(1, b / (1 - s))
+
+
+def color_burn(b, s):
+    if b == 1:
+        return 1
+    return 0 if s <= 0 else 1 - min(1, (1 - b) / s)
+
+
+# Blend of backdrop `b` and source `s` for every mode
+BLEND = {
+    "Normal": separable(lambda b, s: s),
+    "Multiply": separable(lambda b, s: b * s),
+    "Screen": separable(lambda b, s: b + s - b * s),
+    "Overlay": separable(lambda b, s: hard_light(s, b)),
+    "Darken": separable(min),
+    "Lighten": separable(max),
+    "Color dodge": separable(color_dodge),
+    "Color burn": separable(color_burn),
+    "Hard light": separable(hard_light),
+    "Soft light": separable(soft_light),
+    "Difference": separable(lambda b, s: abs(b - s)),
+    "Exclusion": separable(lambda b, s: b + s - 2 * b * s),
+    "Hue": lambda b, s: set_lum(set_sat(s, sat(b)), lum(b)),
+    "Saturation": lambda b, s: set_lum(set_sat(b, sat(s)), lum(b)),
+    "Color": lambda b, s: set_lum(s, lum(b)),
+    "Luminosity": lambda b, s: set_lum(b, lum(s)),
+    "Add": separable(lambda b, s: min(1, b + s)),
+    "Hard mix": separable(lambda b, s: 1 if b + s >= 1 else 0),
+}
+
+
+def reference():
+    """RGBA rows of the expected rendering, each starting with a PNG filter
+    byte"""
+    pixels = bytearray()
+    orange, blue, source = quantize(ORANGE), quantize(BLUE), quantize(SOURCE)
+    for y in range(HEIGHT):
+        blend = BLEND[MODES[y // ROW]]
+        pixels.append(0)
+        for x in range(WIDTH):
+            if x < 20:
+                color, alpha = orange, 1
+            elif x < 40:
+                color, alpha = blend(orange, source), 1
+            elif x < 80:
+                color, alpha = blend(blue, source), 1
+            elif x < 100:
+                color, alpha = source, 1
+            else:
+                color, alpha = (0, 0, 0), 0
+            pixels += bytes([round(max(0, min(1, v)) * 255) for v in color])
+            pixels.append(alpha * 255)
+    return bytes(pixels)
+
+
+def png(width, height, rows):
+    def chunk(ty, data):
+        crc = zlib.crc32(ty + data) & 0xFFFFFFFF
+        return struct.pack(">I", len(data)) + ty + data + struct.pack(">I", crc)
+
+    header = struct.pack(">IIBBBBB", width, height, 8, 6, 0, 0, 0)
+    return (b"\x89PNG\r\n\x1a\n" + chunk(b"IHDR", header)
+            + chunk(b"IDAT", zlib.compress(rows, 9)) + chunk(b"IEND", b""))
+
+
+if __name__ == "__main__":
+    with open(os.path.join(HERE, "blend_modes.json"), "w") as f:
+        f.write(json.dumps(animation(), indent=4) + "\n")
+    with open(os.path.join(HERE, "blend_modes_precomposition.json"), "w") as f:
+        f.write(json.dumps(animation(precomposed=True), indent=4) + "\n")
+    with open(os.path.join(HERE, "blend_modes.png"), "wb") as f:
+        f.write(png(WIDTH, HEIGHT, reference()))